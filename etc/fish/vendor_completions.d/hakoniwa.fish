@@ -38,6 +38,7 @@ complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l rootdir -d 'Us
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l rootfs -d 'Bind mount all subdirectories in ROOTFS to the container root with read-only access' -r -f -a "(__fish_complete_directories)"
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -s b -l bindmount-ro -d 'Bind mount the HOST_PATH on CONTAINER_PATH with read-only access (repeatable)' -r -f -a "(__fish_complete_directories)"
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -s B -l bindmount-rw -d 'Bind mount the HOST_PATH on CONTAINER_PATH with read-write access (repeatable)' -r -f -a "(__fish_complete_directories)"
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l mount -d 'Bind mount with the specified mount options, e.g. type=bind,src=..,dst=..,ro (repeatable)' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l devfs -d 'Mount new devfs on CONTAINER_PATH (repeatable)' -r -f -a "(__fish_complete_directories)"
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l tmpfs -d 'Mount new tmpfs on CONTAINER_PATH (repeatable)' -r -f -a "(__fish_complete_directories)"
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l dir -d 'Create a new dir on CONTAINER_PATH with 700 permissions (repeatable)' -r -f -a "(__fish_complete_directories)"
@@ -49,6 +50,9 @@ complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l user -d 'Set u
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l hostname -d 'Set hostname for the container (implies --unshare-uts)' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l network -d 'Set network mode for the container' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -s e -l setenv -d 'Set an environment variable (repeatable)' -r
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l unsetenv -d 'Unset an environment variable (repeatable)' -r
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l inherit-env -d 'Inherit environment variables matching the glob PATTERN, or the default safe set with "default" (repeatable)' -r
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l env-file -d 'Read environment variables from a dotenv-style FILE (repeatable)' -r -F
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -s w -l workdir -d 'Bind mount the HOST_PATH on the same container path with read-write access, then run COMMAND inside it' -r -f -a "(__fish_complete_directories)"
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-as -d 'Limit the maximum size of the COMMAND\'s virtual memory' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-core -d 'Limit the maximum size of a core file in bytes that the COMMAND may dump' -r
@@ -56,6 +60,9 @@ complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-cpu -d '
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-fsize -d 'Limit the maximum size in bytes of files that the COMMAND may create' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-nofile -d 'Limit the maximum file descriptor number that can be opened by the COMMAND' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-walltime -d 'Limit the amount of wall time that the COMMAND can consume, in seconds' -r
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-memory -d 'Limit the amount of memory in bytes that the container can use (cgroup v2)' -r
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-cpus -d 'Limit the number of CPUs that the container can use, e.g. 0.5 (cgroup v2)' -r
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l limit-pids -d 'Limit the number of processes that can be created in the container (cgroup v2)' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l landlock-restrict -d 'Restrict ambient rights (e.g. global filesystem access) for the process' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l landlock-fs-ro -d 'Allow to read files beneath PATH (implies --landlock-restrict=fs)' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l landlock-fs-rw -d 'Allow to read-write files beneath PATH (implies --landlock-restrict=fs)' -r
@@ -63,12 +70,15 @@ complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l landlock-fs-rx
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l landlock-tcp-bind -d 'Allow binding a TCP socket to a local port (implies --landlock-restrict=tcp.bind)' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l landlock-tcp-connect -d 'Allow connecting an active TCP socket to a remote port (implies --landlock-restrict=tcp.connect)' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l seccomp -d 'Set the seccomp security profile' -r -F
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l cap-add -d 'Add a capability to the COMMAND, e.g. CAP_NET_BIND_SERVICE (repeatable)' -r
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l cap-drop -d 'Drop a capability from the COMMAND, or ALL to drop all capabilities (repeatable)' -r
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -s c -l config -d 'Load configuration from a specified file, ignoring all other cli arguments' -r -F
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l unshare-all -d 'Create new CGROUP, IPC, NETWORK, UTS, ... namespaces'
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l unshare-cgroup -d 'Create new CGROUP namespace'
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l unshare-ipc -d 'Create new IPC namespace'
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l unshare-network -d 'Create new NETWORK namespace'
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l unshare-uts -d 'Create new UTS namespace'
+complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l pty -d 'Allocate a pseudo-terminal for the COMMAND, requires a devfs mounted on /dev'
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -l allow-new-privs -d 'Set the NoNewPrivileges flag to off'
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -s v -l verbose -d 'Increase logging verbosity'
 complete -c hakoniwa -n "__fish_hakoniwa_using_subcommand run" -s q -l quiet -d 'Decrease logging verbosity'
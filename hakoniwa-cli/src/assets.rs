@@ -1,5 +0,0 @@
-use rust_embed::RustEmbed;
-
-#[derive(RustEmbed)]
-#[folder = "src/assets"]
-pub(crate) struct Assets;
@@ -1,5 +1,6 @@
 mod argparse;
 mod pathsearch;
+mod ptyproxy;
 mod subcommands;
 
 use clap::builder::styling::{AnsiColor, Styles};
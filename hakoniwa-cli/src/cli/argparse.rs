@@ -1,7 +1,9 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use nix::unistd::{Gid, Uid};
 use std::env;
 
+use hakoniwa::MountOptions;
+
 pub(crate) fn contains_arg(arg: &str) -> bool {
     for a in env::args() {
         match a.as_str() {
@@ -45,6 +47,57 @@ pub(crate) fn parse_bindmount(s: &str) -> Result<(String, String)> {
     }
 }
 
+pub(crate) fn parse_mount(s: &str) -> Result<(String, String, MountOptions)> {
+    let (mut source, mut destination, mut propagation) = (None, None, None);
+    let mut options = MountOptions::BIND | MountOptions::REC | MountOptions::NOSUID;
+    for e in s.split(',') {
+        let (key, value) = match e.find('=') {
+            Some(pos) => (&e[..pos], Some(&e[pos + 1..])),
+            None => (e, None),
+        };
+        match (key, value) {
+            ("type", Some("bind")) => {}
+            ("type", Some(v)) => Err(anyhow!("unsupported mount type {:?}", v))?,
+            ("src" | "source", Some(v)) => source = Some(v.to_string()),
+            ("dst" | "destination" | "target", Some(v)) => destination = Some(v.to_string()),
+            ("ro" | "readonly", None) => options.insert(MountOptions::RDONLY),
+            ("rw", None) => options.remove(MountOptions::RDONLY),
+            ("nodev", None) => options.insert(MountOptions::NODEV),
+            ("noexec", None) => options.insert(MountOptions::NOEXEC),
+            ("noatime", None) => options.insert(MountOptions::NOATIME),
+            ("relatime", None) => options.insert(MountOptions::RELATIME),
+            ("nosymfollow", None) => options.insert(MountOptions::NOSYMFOLLOW),
+            ("idmap", None) => options.insert(MountOptions::IDMAP),
+            ("propagation", Some(_)) if propagation.is_some() => {
+                Err(anyhow!("duplicate mount option \"propagation\""))?
+            }
+            ("propagation", Some(v)) => {
+                propagation = Some(match v {
+                    "private" => MountOptions::PRIVATE,
+                    "slave" => MountOptions::SLAVE,
+                    "shared" => MountOptions::SHARED,
+                    "unbindable" => MountOptions::UNBINDABLE,
+                    "rprivate" => MountOptions::RPRIVATE,
+                    "rslave" => MountOptions::RSLAVE,
+                    "rshared" => MountOptions::RSHARED,
+                    "runbindable" => MountOptions::RUNBINDABLE,
+                    _ => Err(anyhow!("unsupported mount propagation {:?}", v))?,
+                });
+            }
+            ("bind-recursive", Some("enabled")) => options.insert(MountOptions::REC),
+            ("bind-recursive", Some("disabled")) => options.remove(MountOptions::REC),
+            _ => Err(anyhow!("unsupported mount option {:?}", e))?,
+        }
+    }
+
+    if let Some(propagation) = propagation {
+        options.insert(propagation);
+    }
+    let source = source.ok_or_else(|| anyhow!("missing mount option \"src\""))?;
+    let destination = destination.unwrap_or(source.clone());
+    Ok((source, destination, options))
+}
+
 pub(crate) fn parse_symlink(s: &str) -> Result<(String, String)> {
     match s.find(':') {
         Some(pos) => Ok((s[..pos].to_string(), s[pos + 1..].to_string())),
@@ -78,6 +131,13 @@ pub(crate) fn parse_gidmap(s: &str) -> Result<(u32, u32, u32)> {
     }
 }
 
+pub(crate) fn parse_idmapped_owner(s: &str) -> Result<(u32, u32)> {
+    match s.find(':') {
+        Some(pos) => Ok((s[..pos].parse()?, s[pos + 1..].parse()?)),
+        None => Ok((s.parse()?, s.parse()?)),
+    }
+}
+
 pub(crate) fn parse_user(s: &str) -> Result<(String, Option<String>, Vec<String>)> {
     let parse_supplementary_groups = |s: &str| -> Vec<_> {
         match s.len() {
@@ -0,0 +1,120 @@
+use anyhow::Result;
+use nix::errno::Errno;
+use nix::libc;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::sys::signal::{SigSet, Signal};
+use nix::sys::signalfd::{SfdFlags, SignalFd};
+use nix::sys::termios::{self, SetArg, Termios};
+use nix::unistd;
+use std::fs::File;
+use std::io::{self, prelude::*};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+
+use hakoniwa::{Command, ExitStatus};
+
+/// Runs the command with a pseudo-terminal, and proxies it to the caller's
+/// terminal in raw mode until the command exits.
+pub(crate) fn status(command: &mut Command) -> Result<ExitStatus> {
+    let mut child = command.pty(true).spawn()?;
+    if let Some(master) = child.pty.take() {
+        let mut sigwinch = SigSet::empty();
+        sigwinch.add(Signal::SIGWINCH);
+        sigwinch.thread_block()?;
+        let sigwinch = SignalFd::with_flags(&sigwinch, SfdFlags::SFD_NONBLOCK)?;
+
+        let stdin = io::stdin();
+        let _raw = RawMode::enable(stdin.as_fd())?;
+        proxy(&master, &sigwinch)?;
+    }
+    Ok(child.wait()?)
+}
+
+fn proxy(mut master: &File, sigwinch: &SignalFd) -> Result<()> {
+    let (stdin, mut stdout) = (io::stdin(), io::stdout().lock());
+    let mut stdin_eof = false;
+    let mut buf = [0; 4096];
+    loop {
+        let mut fds = vec![
+            PollFd::new(master.as_fd(), PollFlags::POLLIN),
+            PollFd::new(sigwinch.as_fd(), PollFlags::POLLIN),
+        ];
+        if !stdin_eof {
+            fds.push(PollFd::new(stdin.as_fd(), PollFlags::POLLIN));
+        }
+        match poll(&mut fds, PollTimeout::NONE) {
+            Err(Errno::EINTR) => continue,
+            r => r?,
+        };
+        let revents = fds
+            .iter()
+            .map(|fd| fd.revents().unwrap_or(PollFlags::empty()))
+            .collect::<Vec<_>>();
+
+        // Output from the command. The master returns EIO once the command
+        // and all its descendants closed the slave.
+        if !revents[0].is_empty() {
+            match master.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    stdout.write_all(&buf[..n])?;
+                    stdout.flush()?;
+                }
+                Err(err) if err.raw_os_error() == Some(libc::EIO) => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => Err(err)?,
+            }
+        }
+
+        // Forward window size changes.
+        if !revents[1].is_empty() {
+            while let Ok(Some(_)) = sigwinch.read_signal() {}
+            copy_winsize(stdin.as_fd(), master.as_fd());
+        }
+
+        // Input from the caller.
+        if !stdin_eof && !revents[2].is_empty() {
+            match unistd::read(&stdin, &mut buf) {
+                Ok(0) => stdin_eof = true,
+                Ok(n) => master.write_all(&buf[..n])?,
+                Err(Errno::EINTR) => {}
+                Err(_) => stdin_eof = true,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn copy_winsize(from: BorrowedFd<'_>, to: BorrowedFd<'_>) {
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    unsafe {
+        if libc::ioctl(from.as_raw_fd(), libc::TIOCGWINSZ, &mut ws) == 0 {
+            libc::ioctl(to.as_raw_fd(), libc::TIOCSWINSZ, &ws);
+        }
+    }
+}
+
+/// Puts the caller's terminal into raw mode, and restores it on drop.
+struct RawMode<'fd> {
+    fd: BorrowedFd<'fd>,
+    termios: Termios,
+}
+
+impl<'fd> RawMode<'fd> {
+    fn enable(fd: BorrowedFd<'fd>) -> Result<Option<Self>> {
+        if !unistd::isatty(fd)? {
+            return Ok(None);
+        }
+
+        let termios = termios::tcgetattr(fd)?;
+        let mut raw = termios.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(fd, SetArg::TCSANOW, &raw)?;
+        Ok(Some(Self { fd, termios }))
+    }
+}
+
+impl Drop for RawMode<'_> {
+    fn drop(&mut self) {
+        _ = termios::tcsetattr(self.fd, SetArg::TCSANOW, &self.termios);
+    }
+}
@@ -1,13 +1,19 @@
 use anyhow::{Result, anyhow};
 use clap::{Args, ValueHint};
+use nix::libc;
 use nix::unistd::{Uid, User};
 use std::fs;
 use std::path::Path;
 use std::str::{self, FromStr};
 
-use crate::cli::{argparse, pathsearch};
-use crate::{config, seccomp};
-use hakoniwa::{Command, Container, Namespace, Pasta, Rlimit, Runctl, landlock::*};
+use crate::cli::{argparse, pathsearch, ptyproxy};
+use crate::config;
+use hakoniwa::{
+    Capability, Cgroup, Command, Container, ExitStatus, MountOptions, Namespace, Pasta,
+    RecordedFsAccess, RecordedSyscall, Rlimit, Runctl,
+    landlock::*,
+    seccomp::{Action, Filter, presets},
+};
 
 const SHELL: &str = "/bin/sh";
 
@@ -49,6 +55,10 @@ pub(crate) struct RunCommand {
     #[clap(short = 'B', long, value_name="HOST_PATH:CONTAINER_PATH", value_parser = argparse::parse_bindmount, value_hint = ValueHint::DirPath)]
     bindmount_rw: Vec<(String, String)>,
 
+    /// Bind mount with the specified mount options, e.g. type=bind,src=..,dst=..,ro (repeatable)
+    #[clap(long, value_name = "OPTIONS", value_parser = argparse::parse_mount)]
+    mount: Vec<(String, String, MountOptions)>,
+
     /// Mount new devfs on CONTAINER_PATH (repeatable)
     #[clap(long, value_name = "CONTAINER_PATH", value_hint = ValueHint::DirPath)]
     devfs: Vec<String>,
@@ -77,6 +87,10 @@ pub(crate) struct RunCommand {
     #[clap(short, long, value_name = "CONTAINER_ID:HOST_ID:COUNT", value_parser = argparse::parse_gidmap)]
     gidmap: Vec<(u32, u32, u32)>,
 
+    /// Files owned by the current user on the host appear owned by UID:GID in id-mapped mounts
+    #[clap(long, value_name = "UID:GID", value_parser = argparse::parse_idmapped_owner)]
+    idmapped_owner: Option<(u32, u32)>,
+
     /// Set user for the container
     #[clap(long, value_name = "USER:GROUP", value_parser = argparse::parse_user)]
     user: Option<(String, Option<String>, Vec<String>)>,
@@ -93,6 +107,22 @@ pub(crate) struct RunCommand {
     #[clap(short = 'e', long, value_name="NAME=VALUE", value_parser = argparse::parse_setenv)]
     setenv: Vec<(String, String)>,
 
+    /// Unset an environment variable (repeatable)
+    #[clap(long, value_name = "NAME")]
+    unsetenv: Vec<String>,
+
+    /// Inherit environment variables matching the glob PATTERN, or the default safe set with "default" (repeatable)
+    #[clap(long, value_name = "PATTERN")]
+    inherit_env: Vec<String>,
+
+    /// Read environment variables from a dotenv-style FILE (repeatable)
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    env_file: Vec<String>,
+
+    /// Allocate a pseudo-terminal for the COMMAND, requires a devfs mounted on /dev
+    #[clap(long)]
+    pty: bool,
+
     /// Bind mount the HOST_PATH on the same container path with read-write access, then run COMMAND inside it
     #[clap(short, long, value_name = "HOST_PATH", value_hint = ValueHint::DirPath)]
     workdir: Option<String>,
@@ -121,6 +151,18 @@ pub(crate) struct RunCommand {
     #[clap(long, value_name = "LIMIT")]
     limit_walltime: Option<u64>,
 
+    /// Limit the amount of memory in bytes that the container can use (cgroup v2)
+    #[clap(long, value_name = "LIMIT")]
+    limit_memory: Option<u64>,
+
+    /// Limit the number of CPUs that the container can use, e.g. 0.5 (cgroup v2)
+    #[clap(long, value_name = "LIMIT")]
+    limit_cpus: Option<f64>,
+
+    /// Limit the number of processes that can be created in the container (cgroup v2)
+    #[clap(long, value_name = "LIMIT")]
+    limit_pids: Option<u64>,
+
     /// Restrict ambient rights (e.g. global filesystem access) for the process
     #[clap(long, value_name = "RESOURCE, ...")]
     landlock_restrict: Option<String>,
@@ -145,14 +187,42 @@ pub(crate) struct RunCommand {
     #[clap(long, value_name = "PORT, ...", value_parser = argparse::parse_landlock_net_ports)]
     landlock_tcp_connect: Option<(u16, Vec<u16>)>,
 
+    /// Allow to access files beneath the mount points, as the mounts allow (implies --landlock-restrict=fs)
+    #[clap(long)]
+    landlock_auto: bool,
+
+    /// Record the filesystem accesses of the COMMAND, and save them as a landlock.fs config to FILE
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    landlock_record: Option<String>,
+
     /// Set the seccomp security profile
     #[clap(long, default_value = "podman", value_hint = ValueHint::FilePath)]
     seccomp: Option<String>,
 
+    /// Report the syscall blocked by the seccomp profile, the COMMAND is traced to catch it
+    #[clap(long)]
+    seccomp_report: bool,
+
+    /// Record the syscalls of the COMMAND, and save them as a seccomp profile to FILE
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    seccomp_record: Option<String>,
+
+    /// Add a capability to the COMMAND, e.g. CAP_NET_BIND_SERVICE (repeatable)
+    #[clap(long, value_name = "CAP")]
+    cap_add: Vec<String>,
+
+    /// Drop a capability from the COMMAND, or ALL to drop all capabilities (repeatable)
+    #[clap(long, value_name = "CAP")]
+    cap_drop: Vec<String>,
+
     /// Set the NoNewPrivileges flag to off
     #[clap(long)]
     allow_new_privs: bool,
 
+    /// Trace the syscalls of the COMMAND, and write them to FILE
+    #[clap(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    trace: Option<String>,
+
     /// Load configuration from a specified file, ignoring all other cli arguments
     #[clap(short, long, value_hint = ValueHint::FilePath)]
     config: Option<String>,
@@ -212,19 +282,15 @@ impl RunCommand {
                 continue;
             };
 
-            if mount.rw {
-                fs::canonicalize(host_path)
-                    .map_err(|_| anyhow!("--config: mount: path {:?} does not exist", host_path))
-                    .map(|host_path| {
-                        container.bindmount_rw(&host_path.to_string_lossy(), container_path)
-                    })?;
-            } else {
-                fs::canonicalize(host_path)
-                    .map_err(|_| anyhow!("--config: mount: path {:?} does not exist", host_path))
-                    .map(|host_path| {
-                        container.bindmount_ro(&host_path.to_string_lossy(), container_path)
-                    })?;
-            }
+            let host_path = fs::canonicalize(host_path)
+                .map_err(|_| anyhow!("--config: mount: path {:?} does not exist", host_path))?;
+            let host_path = &host_path.to_string_lossy();
+            match (mount.rw, mount.idmap) {
+                (true, true) => container.bindmount_rw_idmapped(host_path, container_path),
+                (true, false) => container.bindmount_rw(host_path, container_path),
+                (false, true) => container.bindmount_ro_idmapped(host_path, container_path),
+                (false, false) => container.bindmount_ro(host_path, container_path),
+            };
         }
 
         // CFG: filesystem
@@ -276,15 +342,37 @@ impl RunCommand {
 
         // CFG: limits
         let mut limit_walltime = None;
+        let mut cgroup = None;
         for limit in cfg.limits {
-            if limit.rtype == "walltime" {
-                limit_walltime = Some(limit.value);
-            } else {
-                let lim = Self::str_to_rlimit(&limit.rtype)
-                    .map_err(|e| anyhow!("--config: limit: {}", e))?;
-                container.setrlimit(lim, limit.value, limit.value);
+            match limit.rtype.as_ref() {
+                "walltime" => limit_walltime = Some(limit.value.as_u64()),
+                "memory" => {
+                    let val = limit.value.as_u64();
+                    cgroup.get_or_insert_with(Cgroup::new).memory_max(val);
+                }
+                "cpus" => {
+                    let (quota, period) = Self::cpus_to_cpu_max(limit.value.as_f64())
+                        .map_err(|e| anyhow!("--config: limits: {}", e))?;
+                    cgroup
+                        .get_or_insert_with(Cgroup::new)
+                        .cpu_max(quota, period);
+                }
+                "pids" => {
+                    let val = limit.value.as_u64();
+                    cgroup.get_or_insert_with(Cgroup::new).pids_max(val);
+                }
+                _ => {
+                    let val = limit.value.as_u64();
+                    let lim = Self::str_to_rlimit(&limit.rtype)
+                        .map_err(|e| anyhow!("--config: limit: {}", e))?;
+                    container.setrlimit(lim, val, val);
+                }
             }
         }
+        if let Some(mut cgroup) = cgroup {
+            cgroup.move_current_process();
+            container.cgroup(cgroup);
+        }
 
         // CFG: landlock
         if let Some(landlock) = cfg.landlock {
@@ -314,10 +402,31 @@ impl RunCommand {
             container.landlock_ruleset(ruleset);
         }
 
+        // CFG: capabilities
+        if let Some(capabilities) = cfg.capabilities {
+            for cap in capabilities.drop {
+                if cap == "ALL" {
+                    container.cap_drop_all();
+                    continue;
+                }
+                let cap = Self::str_to_capability(&cap)
+                    .map_err(|e| anyhow!("--config: capabilities: {}", e))?;
+                container.cap_drop(cap);
+            }
+            for cap in capabilities.add {
+                let cap = Self::str_to_capability(&cap)
+                    .map_err(|e| anyhow!("--config: capabilities: {}", e))?;
+                container.cap_add(cap);
+            }
+        }
+
         // CFG: seccomp
         let seccomp = cfg.seccomp.path.unwrap_or("podman".to_string());
         Self::configure_seccomp(&mut container, &seccomp)
             .map_err(|e| anyhow!("--config: seccomp: {}", e))?;
+        if cfg.seccomp.report {
+            container.runctl(Runctl::ReportBlockedSyscall);
+        }
 
         // ARG: -- <COMMAND>...
         // CFG: command::cmdline
@@ -346,12 +455,12 @@ impl RunCommand {
         limit_walltime.map(|val| command.wait_timeout(val));
 
         // Execute
-        let status = command.status()?;
-        if status.exit_code.is_none() {
-            // - the Container itself fails
-            // - or the Command killed by signal
-            log::error!("hakoniwa: {}", status.reason);
-        }
+        // CFG: command::pty
+        let status = match cfg.command.pty {
+            true => ptyproxy::status(&mut command)?,
+            false => command.status()?,
+        };
+        Self::log_exit_status(&status);
         Ok(status.code)
     }
 
@@ -434,6 +543,15 @@ impl RunCommand {
                 })?;
         }
 
+        // ARG: --mount
+        for (host_path, container_path, options) in self.mount.iter() {
+            let host_path = fs::canonicalize(host_path)
+                .map_err(|_| anyhow!("--mount: path {:?} does not exist", host_path))?;
+            container
+                .bindmount(&host_path.to_string_lossy(), container_path, *options)
+                .map_err(|e| anyhow!("--mount: {}", e))?;
+        }
+
         // ARG: --devfs
         for container_path in self.devfs.iter() {
             container.devfsmount(container_path);
@@ -485,6 +603,11 @@ impl RunCommand {
             container.gidmaps(&gidmaps);
         }
 
+        // ARG: --idmapped-owner
+        if let Some((uid, gid)) = self.idmapped_owner {
+            container.idmapped_owner(uid, gid);
+        }
+
         // ARG: --user
         if let Some((user, group, sgroups)) = &self.user {
             let sgroups: Vec<&str> = sgroups.iter().map(|g| g.as_ref()).collect();
@@ -514,6 +637,20 @@ impl RunCommand {
         self.limit_nofile
             .map(|val| container.setrlimit(Rlimit::Nofile, val, val));
 
+        // ARG: --limit-memory, --limit-cpus, --limit-pids
+        if self.limit_memory.is_some() || self.limit_cpus.is_some() || self.limit_pids.is_some() {
+            let mut cgroup = Cgroup::new();
+            cgroup.move_current_process();
+            self.limit_memory.map(|val| cgroup.memory_max(val));
+            if let Some(val) = self.limit_cpus {
+                let (quota, period) =
+                    Self::cpus_to_cpu_max(val).map_err(|e| anyhow!("--limit-cpus: {}", e))?;
+                cgroup.cpu_max(quota, period);
+            }
+            self.limit_pids.map(|val| cgroup.pids_max(val));
+            container.cgroup(cgroup);
+        }
+
         // ARG: --landlock
         if argparse::contains_arg_landlock() {
             let mut ruleset = Ruleset::default();
@@ -570,20 +707,82 @@ impl RunCommand {
             container.landlock_ruleset(ruleset);
         }
 
+        // ARG: --landlock-auto
+        if self.landlock_auto {
+            container.runctl(Runctl::LandlockFromMounts);
+        }
+
+        // ARG: --landlock-record
+        if self.landlock_record.is_some() {
+            container.runctl(Runctl::RecordFsAccess);
+        }
+
+        // ARG: --cap-drop
+        for cap in self.cap_drop.iter() {
+            if cap == "ALL" {
+                container.cap_drop_all();
+                continue;
+            }
+            let cap = Self::str_to_capability(cap).map_err(|e| anyhow!("--cap-drop: {}", e))?;
+            container.cap_drop(cap);
+        }
+
+        // ARG: --cap-add
+        for cap in self.cap_add.iter() {
+            let cap = Self::str_to_capability(cap).map_err(|e| anyhow!("--cap-add: {}", e))?;
+            container.cap_add(cap);
+        }
+
         // ARG: --seccomp
         let seccomp = &self.seccomp.clone().expect("--seccomp: missing value");
         Self::configure_seccomp(&mut container, seccomp)
             .map_err(|e| anyhow!("--seccomp: {}", e))?;
 
+        // ARG: --seccomp-report
+        if self.seccomp_report {
+            container.runctl(Runctl::ReportBlockedSyscall);
+        }
+
+        // ARG: --seccomp-record
+        if self.seccomp_record.is_some() {
+            container.runctl(Runctl::RecordSyscalls);
+        }
+
+        // ARG: --trace
+        if let Some(path) = &self.trace {
+            let file = fs::File::create(path).map_err(|e| anyhow!("--trace: {:?}: {}", path, e))?;
+            container.runctl(Runctl::TraceSyscalls).trace_sink(file);
+        }
+
         // ARG: -- <COMMAND>...
         let (prog, argv) = (&self.argv[0], &self.argv[1..]);
         let mut command = Self::build_command(&container, prog, argv);
 
+        // ARG: --inherit-env
+        for pattern in self.inherit_env.iter() {
+            match pattern.as_str() {
+                "default" => command.env_inherit_default(),
+                _ => command.env_inherit([pattern]),
+            };
+        }
+
+        // ARG: --env-file
+        for path in self.env_file.iter() {
+            command
+                .env_file(path)
+                .map_err(|e| anyhow!("--env-file: {:?}: {}", path, e))?;
+        }
+
         // ARG: --setenv
         for (name, value) in self.setenv.iter() {
             command.env(name, value);
         }
 
+        // ARG: --unsetenv
+        for name in self.unsetenv.iter() {
+            command.env_remove(name);
+        }
+
         // ARG: --workdir
         workdir.map(|dir| command.current_dir(dir));
 
@@ -591,11 +790,24 @@ impl RunCommand {
         self.limit_walltime.map(|val| command.wait_timeout(val));
 
         // Execute
-        let status = command.status()?;
-        if status.exit_code.is_none() {
-            // - the Container itself fails
-            // - or the Command killed by signal
-            log::error!("hakoniwa: {}", status.reason);
+        // ARG: --pty
+        let status = match self.pty {
+            true => ptyproxy::status(&mut command)?,
+            false => command.status()?,
+        };
+        Self::log_exit_status(&status);
+
+        // ARG: --landlock-record
+        if let (Some(path), Some(accesses)) = (&self.landlock_record, &status.recorded_fs_accesses)
+        {
+            Self::save_landlock_record(path, accesses)
+                .map_err(|e| anyhow!("--landlock-record: {}", e))?;
+        }
+
+        // ARG: --seccomp-record
+        if let (Some(path), Some(syscalls)) = (&self.seccomp_record, &status.recorded_syscalls) {
+            Self::save_seccomp_record(path, syscalls)
+                .map_err(|e| anyhow!("--seccomp-record: {}", e))?;
         }
         Ok(status.code)
     }
@@ -642,20 +854,57 @@ impl RunCommand {
 
     fn configure_seccomp(container: &mut Container, seccomp: &str) -> Result<()> {
         match seccomp {
-            "audit" | "podman" => {
-                seccomp::load(seccomp).map(|f| container.seccomp_filter(f))?;
+            "audit" => {
+                container.seccomp_filter(presets::audit());
+            }
+            "podman" => {
+                container.seccomp_filter(presets::podman());
             }
             "unconfined" => {
                 _ = container;
             }
             _ => {
                 let data = fs::read_to_string(seccomp)?;
-                seccomp::load_str(&data).map(|f| container.seccomp_filter(f))?;
+                Filter::from_oci_json(&data).map(|f| container.seccomp_filter(f))?;
             }
         }
         Ok(())
     }
 
+    fn save_landlock_record(path: &str, accesses: &[RecordedFsAccess]) -> Result<()> {
+        let ruleset = Ruleset::from_recorded_fs_accesses(accesses)?;
+        let mut data = "landlock.fs = [\n".to_string();
+        for rule in ruleset.get_fs_rules() {
+            let path = toml::Value::String(rule.get_path().to_string());
+            let access = rule.get_mode();
+            data.push_str(&format!("  {{ path = {path}, access = \"{access}\" }},\n"));
+        }
+        data.push_str("]\n");
+        fs::write(path, data)?;
+        Ok(())
+    }
+
+    fn log_exit_status(status: &ExitStatus) {
+        if status.exit_code.is_none() {
+            // - the Container itself fails
+            // - or the Command killed by signal
+            log::error!("hakoniwa: {}", status.reason);
+        }
+        if let Some(blocked) = &status.blocked_syscall {
+            log::error!(
+                "hakoniwa: blocked syscall: {} ({})",
+                blocked.sysname,
+                blocked.arch
+            );
+        }
+    }
+
+    fn save_seccomp_record(path: &str, syscalls: &[RecordedSyscall]) -> Result<()> {
+        let filter = Filter::from_recorded_syscalls(Action::Errno(libc::EPERM), syscalls)?;
+        fs::write(path, filter.to_oci_json()?)?;
+        Ok(())
+    }
+
     fn build_command(container: &Container, prog: &str, argv: &[String]) -> Command {
         if Path::new(prog).is_absolute() {
             let mut cmd = container.command(prog);
@@ -697,11 +946,31 @@ impl RunCommand {
         })
     }
 
+    fn cpus_to_cpu_max(cpus: f64) -> Result<(u64, u64)> {
+        if !(cpus > 0.0 && cpus.is_finite()) {
+            Err(anyhow!("invalid number of CPUs {cpus}"))?
+        }
+        let period = 100_000;
+        let quota = (cpus * period as f64) as u64;
+        Ok((quota, period))
+    }
+
+    fn str_to_capability(s: &str) -> Result<Capability> {
+        let name = s.to_uppercase();
+        let name = match name.starts_with("CAP_") {
+            true => name,
+            false => format!("CAP_{name}"),
+        };
+        Capability::from_str(&name).map_err(|_| anyhow!("unknown capability {s:?}"))
+    }
+
     fn str_to_landlock_resource(s: &str) -> Result<Resource> {
         Ok(match s {
             "fs" => Resource::FS,
             "tcp.bind" => Resource::NET_TCP_BIND,
             "tcp.connect" => Resource::NET_TCP_CONNECT,
+            "scope.abstract" => Resource::SCOPE_ABSTRACT_UNIX_SOCKET,
+            "scope.signal" => Resource::SCOPE_SIGNAL,
             _ => {
                 let msg = format!("unknown resource type {s:?}");
                 Err(anyhow!(msg))?
@@ -31,6 +31,8 @@ pub(crate) struct CfgConfig {
     pub(crate) hostname: Option<String>,
     #[serde(rename = "limits", default)]
     pub(crate) limits: Vec<CfgLimit>,
+    #[serde(rename = "capabilities")]
+    pub(crate) capabilities: Option<CfgCapabilities>,
     #[serde(rename = "seccomp", default)]
     pub(crate) seccomp: CfgSeccomp,
     #[serde(rename = "command", default)]
@@ -74,6 +76,8 @@ pub(crate) struct CfgMount {
     pub(crate) fstype: String,
     #[serde(rename = "rw", default)]
     pub(crate) rw: bool,
+    #[serde(rename = "idmap", default)]
+    pub(crate) idmap: bool,
 }
 
 #[derive(Deserialize, Clone)]
@@ -205,7 +209,39 @@ pub(crate) struct CfgLimit {
     #[serde(rename = "type")]
     pub(crate) rtype: String,
     #[serde(rename = "value")]
-    pub(crate) value: u64,
+    pub(crate) value: CfgLimitValue,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+pub(crate) enum CfgLimitValue {
+    Integer(u64),
+    Float(f64),
+}
+
+impl CfgLimitValue {
+    pub(crate) fn as_u64(&self) -> u64 {
+        match *self {
+            Self::Integer(val) => val,
+            Self::Float(val) => val as u64,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> f64 {
+        match *self {
+            Self::Integer(val) => val as f64,
+            Self::Float(val) => val,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CfgCapabilities {
+    #[serde(rename = "add", default)]
+    pub(crate) add: Vec<String>,
+    #[serde(rename = "drop", default)]
+    pub(crate) drop: Vec<String>,
 }
 
 #[derive(Deserialize, Default)]
@@ -213,6 +249,8 @@ pub(crate) struct CfgLimit {
 pub(crate) struct CfgSeccomp {
     #[serde(rename = "path")]
     pub(crate) path: Option<String>,
+    #[serde(rename = "report", default)]
+    pub(crate) report: bool,
 }
 
 #[derive(Deserialize, Default)]
@@ -222,6 +260,8 @@ pub(crate) struct CfgCommand {
     pub(crate) cmdline: Vec<String>,
     #[serde(rename = "cwd")]
     pub(crate) cwd: Option<String>,
+    #[serde(rename = "pty", default)]
+    pub(crate) pty: bool,
 }
 
 impl CfgEnv {
@@ -1,6 +1,4 @@
-mod assets;
 mod config;
-mod seccomp;
 mod xdg;
 
 pub mod cli;
@@ -1,4 +1,13 @@
 #[test]
 fn cli_test() {
-    trycmd::TestCases::new().case("tests/cli/*/*.md");
+    // Commands which run `hakoniwa` themselves, e.g. the fixture scripts,
+    // find it in the PATH.
+    let bin_dir = std::path::Path::new(env!("CARGO_BIN_EXE_hakoniwa"))
+        .parent()
+        .unwrap();
+    let path = std::env::var("PATH").unwrap_or_default();
+    trycmd::TestCases::new()
+        .register_bin("python3", trycmd::schema::Bin::Path("/bin/python3".into()))
+        .env("PATH", format!("{}:{path}", bin_dir.display()))
+        .case("tests/cli/*/*.md");
 }
@@ -0,0 +1,12 @@
+import socket
+import subprocess
+import sys
+
+# Listen on an abstract UNIX socket outside the container, then run the
+# command which tries to connect to it.
+name = sys.argv[1]
+listener = socket.socket(socket.AF_UNIX)
+listener.bind('\0' + name)
+listener.listen()
+
+sys.exit(subprocess.run(sys.argv[2:]).returncode)
@@ -0,0 +1,70 @@
+//! Measures the spawn latency of a container with a large seccomp filter.
+//!
+//! ```console
+//! $ cargo bench -p hakoniwa --features seccomp --bench seccomp_bench
+//! ```
+
+use std::time::{Duration, Instant};
+
+use hakoniwa::Container;
+use hakoniwa::seccomp::{Action, Filter};
+use libseccomp::ScmpSyscall;
+
+const ITERATIONS: u32 = 200;
+
+/// An allowlist of every syscall known to libseccomp, which is about the size
+/// of the podman profile.
+fn filter() -> Filter {
+    let mut filter = Filter::new(Action::Errno(libc::EPERM));
+    for nr in 0..512 {
+        if let Ok(name) = ScmpSyscall::from(nr).get_name() {
+            filter.add_rule(Action::Allow, &name);
+        }
+    }
+    filter
+}
+
+fn container() -> Container {
+    let mut container = Container::new();
+    container.rootfs("/").unwrap().seccomp_filter(filter());
+    container
+}
+
+fn spawn(container: &Container) {
+    let status = container.command("/bin/true").status().unwrap();
+    assert!(status.success());
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let mean = elapsed / ITERATIONS;
+    println!("{name:<32} {:>10.3} ms/spawn", mean.as_secs_f64() * 1000.0);
+}
+
+fn main() {
+    // Warm up.
+    spawn(&container());
+
+    // A new container for each spawn, the filter is compiled every time.
+    let started_at = Instant::now();
+    for _ in 0..ITERATIONS {
+        spawn(&container());
+    }
+    report("spawn (new container)", started_at.elapsed());
+
+    // The container is reused, the filter is compiled once.
+    let container = container();
+    let started_at = Instant::now();
+    for _ in 0..ITERATIONS {
+        spawn(&container);
+    }
+    report("spawn (reused container)", started_at.elapsed());
+
+    // Without seccomp filter, as a baseline.
+    let mut container = Container::new();
+    container.rootfs("/").unwrap();
+    let started_at = Instant::now();
+    for _ in 0..ITERATIONS {
+        spawn(&container);
+    }
+    report("spawn (no seccomp filter)", started_at.elapsed());
+}
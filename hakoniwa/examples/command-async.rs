@@ -0,0 +1,45 @@
+#[cfg(feature = "tokio")]
+fn main() -> Result<(), hakoniwa::Error> {
+    use hakoniwa::*;
+    use tokio::io::AsyncWriteExt;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+
+    runtime.block_on(async {
+        let mut container = Container::new();
+        container.rootfs("/")?;
+
+        // spawn `sed` process
+        let mut child = container
+            .command("/bin/sed")
+            .arg("s/tpyo/typo/")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn_async()?;
+
+        // write to sed#stdin
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(b"Oh no, a tpyo!\n").await.unwrap();
+        drop(stdin);
+
+        // wait
+        let output = child.wait_with_output().await?;
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "Oh no, a typo!\n");
+
+        Ok(())
+    })
+}
+
+#[cfg(not(feature = "tokio"))]
+fn main() -> Result<(), hakoniwa::Error> {
+    Ok(())
+}
+
+#[test]
+fn test_main() {
+    main().unwrap();
+}
@@ -0,0 +1,199 @@
+use nix::sys::wait::{self, Id, WaitPidFlag, WaitStatus};
+use std::os::fd::{AsFd, OwnedFd};
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, AsyncReadExt, Interest};
+use tokio::net::unix::pipe;
+use tokio::task::{self, JoinHandle};
+
+use crate::{CgroupStats, Child, ExitStatus, Output, error::*};
+
+/// Representation of a running or exited child process, whose stdio handles
+/// and [wait][AsyncChild::wait] are asynchronous.
+///
+/// A child process is created via the [Command::spawn_async]. This struct is
+/// similar to [tokio::process::Child].
+///
+/// [Command::spawn_async]: crate::Command::spawn_async
+/// [tokio::process::Child]: https://docs.rs/tokio/latest/tokio/process/struct.Child.html
+pub struct AsyncChild {
+    inner: Child,
+    pidfd: AsyncFd<OwnedFd>,
+    status_reader: Option<pipe::Receiver>,
+    status_encoded: Vec<u8>,
+    tracer_done: Option<pipe::Receiver>,
+    resources_released: Option<JoinHandle<Option<CgroupStats>>>,
+    pub stdin: Option<pipe::Sender>,
+    pub stdout: Option<pipe::Receiver>,
+    pub stderr: Option<pipe::Receiver>,
+}
+
+impl AsyncChild {
+    /// Constructs a new AsyncChild from a spawned Child.
+    pub(crate) fn new(mut inner: Child) -> Result<Self> {
+        let pidfd = inner
+            .pidfd
+            .try_clone()
+            .map_err(ProcessErrorKind::StdIoError)?;
+        let pidfd = AsyncFd::with_interest(pidfd, Interest::READABLE)
+            .map_err(ProcessErrorKind::StdIoError)?;
+
+        let status_reader = inner.status_reader.take().map(OwnedFd::from);
+        let tracer_done = inner.tracer_done.take().map(OwnedFd::from);
+        let stdin = inner.stdin.take().map(OwnedFd::from);
+        let stdout = inner.stdout.take().map(OwnedFd::from);
+        let stderr = inner.stderr.take().map(OwnedFd::from);
+        Ok(Self {
+            inner,
+            pidfd,
+            status_reader: status_reader.map(receiver).transpose()?,
+            status_encoded: vec![],
+            tracer_done: tracer_done.map(receiver).transpose()?,
+            resources_released: None,
+            stdin: stdin.map(sender).transpose()?,
+            stdout: stdout.map(receiver).transpose()?,
+            stderr: stderr.map(receiver).transpose()?,
+        })
+    }
+
+    /// Returns the OS-assigned process identifier associated with this child.
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    /// Forces the child process to exit.
+    pub fn kill(&mut self) -> Result<()> {
+        self.inner.kill()
+    }
+
+    /// Waits for the child to exit completely, returning the status that it
+    /// exited with.
+    ///
+    /// The stdin handle to the child process, if any, will be closed before
+    /// waiting.
+    ///
+    /// This method is cancel safe, if it is dropped before completion, e.g.
+    /// by `tokio::time::timeout`, it can be called again to resume waiting.
+    pub async fn wait(&mut self) -> Result<ExitStatus> {
+        drop(self.stdin.take());
+
+        if let Some(status) = &self.inner.status
+            && self.status_reader.is_none()
+            && self.resources_released.is_none()
+        {
+            return Ok(status.clone());
+        }
+
+        if self.resources_released.is_none() {
+            self.wait_exited().await?;
+        }
+
+        // The resources are released on a blocking thread, since it waits for
+        // the tracer thread and the processes left in the cgroup.
+        let handle = self.resources_released.as_mut().expect("released");
+        let cgroup_stats = handle.await.map_err(|err| {
+            let err = std::io::Error::from(err);
+            ProcessErrorKind::StdIoError(err)
+        })?;
+        self.resources_released = None;
+        self.inner.finish_exit_status(cgroup_stats)
+    }
+
+    /// Waits for the child to exit, and starts to release its resources.
+    async fn wait_exited(&mut self) -> Result<()> {
+        // The write end of the status pipe is closed when the child exits.
+        // The reader and the data read so far are kept until the status is
+        // decoded, so the future can be dropped and the wait resumed later.
+        if let Some(reader) = &mut self.status_reader {
+            read_to_eof(reader, &mut self.status_encoded).await?;
+        }
+
+        // Wait for the tracer thread to send all the traced syscalls, so it
+        // exits shortly after.
+        if let Some(reader) = &mut self.tracer_done {
+            read_to_eof(reader, &mut vec![]).await?;
+        }
+
+        let ws = loop {
+            let mut guard = self
+                .pidfd
+                .readable()
+                .await
+                .map_err(ProcessErrorKind::StdIoError)?;
+            let fd = self.pidfd.get_ref().as_fd();
+            let flags = WaitPidFlag::WEXITED | WaitPidFlag::WNOHANG;
+            match wait::waitid(Id::PIDFd(fd), flags).map_err(ProcessErrorKind::NixError)? {
+                WaitStatus::StillAlive => guard.clear_ready(),
+                ws => break ws,
+            }
+        };
+        self.inner.decode_exit_status(ws, &self.status_encoded)?;
+        self.status_reader = None;
+        self.status_encoded.clear();
+        self.tracer_done = None;
+
+        let resources = self.inner.take_resources();
+        self.resources_released = Some(task::spawn_blocking(move || resources.release()));
+        Ok(())
+    }
+
+    /// Simultaneously waits for the child to exit and collect all remaining
+    /// output on the stdout/stderr handles, returning an `Output` instance.
+    ///
+    /// The stdin handle to the child process, if any, will be closed before
+    /// waiting.
+    pub async fn wait_with_output(mut self) -> Result<Output> {
+        drop(self.stdin.take());
+
+        let (stdout, stderr) = (self.stdout.take(), self.stderr.take());
+        let (stdout, stderr, status) =
+            tokio::try_join!(read_to_end(stdout), read_to_end(stderr), self.wait())?;
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+impl AsFd for AsyncChild {
+    fn as_fd(&self) -> std::os::fd::BorrowedFd<'_> {
+        self.pidfd.get_ref().as_fd()
+    }
+}
+
+async fn read_to_end<R: AsyncRead + Unpin>(reader: Option<R>) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    if let Some(mut reader) = reader {
+        reader
+            .read_to_end(&mut buf)
+            .await
+            .map_err(ProcessErrorKind::StdIoError)?;
+    }
+    Ok(buf)
+}
+
+// Unlike read_to_end, the data read before the future is dropped is kept
+// in `buf`, since `read` is cancel safe.
+async fn read_to_eof<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut Vec<u8>) -> Result<()> {
+    let mut chunk = [0; 4096];
+    loop {
+        let n = reader
+            .read(&mut chunk)
+            .await
+            .map_err(ProcessErrorKind::StdIoError)?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+fn sender(fd: OwnedFd) -> Result<pipe::Sender> {
+    let sender = pipe::Sender::from_owned_fd(fd).map_err(ProcessErrorKind::StdIoError)?;
+    Ok(sender)
+}
+
+fn receiver(fd: OwnedFd) -> Result<pipe::Receiver> {
+    let receiver = pipe::Receiver::from_owned_fd(fd).map_err(ProcessErrorKind::StdIoError)?;
+    Ok(receiver)
+}
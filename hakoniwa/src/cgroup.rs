@@ -0,0 +1,419 @@
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{CgroupStats, error::*};
+
+static LEAF_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+static INIT_LEAF: Mutex<Option<InitLeaf>> = Mutex::new(None);
+
+/// Resource control through [cgroup v2](https://docs.kernel.org/admin-guide/cgroup-v2.html).
+///
+/// For each run, a new leaf cgroup is created under the [parent][Cgroup::parent]
+/// cgroup, the container is moved into it before the program is executed, and
+/// it will be removed when the container exits.
+///
+/// By default, the parent is the cgroup of the current process. The required
+/// controllers will be enabled in its `cgroup.subtree_control` automatically.
+///
+/// # Caveats
+///
+/// The parent must be writable by the current user, e.g. a subtree delegated
+/// by systemd, and due to the "no internal processes" rule, it should not
+/// contain any processes itself, thus the controllers can not be enabled in
+/// the cgroup of the current process unless [Cgroup::move_current_process]
+/// is set.
+#[derive(Clone, Default, Debug)]
+pub struct Cgroup {
+    pub(crate) parent: Option<PathBuf>,
+    pub(crate) memory_max: Option<u64>,
+    pub(crate) memory_swap_max: Option<u64>,
+    pub(crate) cpu_max: Option<(u64, u64)>,
+    pub(crate) pids_max: Option<u64>,
+    pub(crate) io_max: Vec<(String, String)>,
+    pub(crate) move_current_process: bool,
+}
+
+impl Cgroup {
+    /// Constructs a new Cgroup without any limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the leaf cgroup under `path`, e.g. `/sys/fs/cgroup/hakoniwa.slice`.
+    pub fn parent<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        self.parent = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Moves the current process into a sub-leaf of its cgroup, so that the
+    /// required controllers can be enabled in it, when no [parent] is set.
+    ///
+    /// The current process is moved back and the sub-leaf is removed once
+    /// all the containers exit, if possible. It requires the current process
+    /// to be the only process in its cgroup, e.g. a scope delegated by systemd.
+    ///
+    /// [parent]: Cgroup::parent
+    pub fn move_current_process(&mut self) -> &mut Self {
+        self.move_current_process = true;
+        self
+    }
+
+    /// Sets the memory usage hard limit in bytes, aka `memory.max`.
+    pub fn memory_max(&mut self, bytes: u64) -> &mut Self {
+        self.memory_max = Some(bytes);
+        self
+    }
+
+    /// Sets the swap usage hard limit in bytes, aka `memory.swap.max`.
+    pub fn memory_swap_max(&mut self, bytes: u64) -> &mut Self {
+        self.memory_swap_max = Some(bytes);
+        self
+    }
+
+    /// Allows the container to consume up to `quota` microseconds of CPU time
+    /// in each `period` microseconds, aka `cpu.max`.
+    pub fn cpu_max(&mut self, quota: u64, period: u64) -> &mut Self {
+        self.cpu_max = Some((quota, period));
+        self
+    }
+
+    /// Sets the maximum number of processes, aka `pids.max`.
+    pub fn pids_max(&mut self, pids: u64) -> &mut Self {
+        self.pids_max = Some(pids);
+        self
+    }
+
+    /// Sets the IO limits of the block device `device` (in `MAJ:MIN` format),
+    /// aka `io.max`, e.g. `io_max("8:16", "rbps=2097152 wiops=120")`.
+    pub fn io_max(&mut self, device: &str, limits: &str) -> &mut Self {
+        self.io_max.push((device.to_string(), limits.to_string()));
+        self
+    }
+
+    /// Returns the controllers required to apply the limits.
+    fn get_controllers(&self) -> Vec<&'static str> {
+        let mut controllers = vec![];
+        if self.memory_max.is_some() || self.memory_swap_max.is_some() {
+            controllers.push("memory");
+        }
+        if self.cpu_max.is_some() {
+            controllers.push("cpu");
+        }
+        if self.pids_max.is_some() {
+            controllers.push("pids");
+        }
+        if !self.io_max.is_empty() {
+            controllers.push("io");
+        }
+        controllers
+    }
+
+    /// Returns the limits in `(file, value)` format.
+    pub(crate) fn get_limits(&self) -> Vec<(&'static str, String)> {
+        let mut limits = vec![];
+        if let Some(val) = self.memory_max {
+            limits.push(("memory.max", val.to_string()));
+        }
+        if let Some(val) = self.memory_swap_max {
+            limits.push(("memory.swap.max", val.to_string()));
+        }
+        if let Some((quota, period)) = self.cpu_max {
+            limits.push(("cpu.max", format!("{quota} {period}")));
+        }
+        if let Some(val) = self.pids_max {
+            limits.push(("pids.max", val.to_string()));
+        }
+        for (device, val) in &self.io_max {
+            limits.push(("io.max", format!("{device} {val}")));
+        }
+        limits
+    }
+}
+
+/// A leaf cgroup created for a single run.
+#[derive(Debug)]
+pub(crate) struct CgroupLeaf {
+    path: PathBuf,
+    init: bool,
+}
+
+/// The sub-leaf which the current process has been moved into, see
+/// [Cgroup::move_current_process].
+#[derive(Debug)]
+struct InitLeaf {
+    path: PathBuf,
+    controllers: Vec<String>,
+    leaves: usize,
+}
+
+impl CgroupLeaf {
+    /// Creates a new leaf cgroup and applies the limits.
+    pub(crate) fn create(cgroup: &Cgroup) -> Result<Self> {
+        let mut init_leaf = INIT_LEAF.lock().unwrap_or_else(|e| e.into_inner());
+        let leaf = Self::create_leaf(cgroup, &mut init_leaf);
+        if init_leaf.as_ref().is_some_and(|init| init.leaves == 0) {
+            Self::remove_init_leaf(&mut init_leaf);
+        }
+        drop(init_leaf);
+
+        // Apply limits.
+        let leaf = leaf?;
+        for (file, val) in cgroup.get_limits() {
+            Self::write(&leaf.path.join(file), &val)?;
+        }
+        Ok(leaf)
+    }
+
+    /// Creates a new leaf cgroup, enabling the required controllers in the
+    /// parent.
+    fn create_leaf(cgroup: &Cgroup, init_leaf: &mut Option<InitLeaf>) -> Result<Self> {
+        let parent = match (&cgroup.parent, &init_leaf) {
+            (Some(parent), _) => parent.clone(),
+            (None, Some(init)) => init.path.parent().unwrap_or(&init.path).to_path_buf(),
+            (None, None) => Self::current()?,
+        };
+
+        // Enable the required controllers in the parent.
+        let controllers = cgroup.get_controllers();
+        if !controllers.is_empty() {
+            let available = Self::read(&parent.join("cgroup.controllers"))?;
+            let available = available.split_whitespace().collect::<Vec<_>>();
+            for controller in &controllers {
+                if !available.contains(controller) {
+                    let errmsg = format!("controller {controller:?} is not available");
+                    Err(ProcessErrorKind::SetupCgroupFailed(errmsg))?;
+                }
+            }
+
+            let enabled = Self::read(&parent.join("cgroup.subtree_control"))?;
+            let enabled = enabled.split_whitespace().collect::<Vec<_>>();
+            let missing = controllers
+                .iter()
+                .filter(|c| !enabled.contains(c))
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                // The root cgroup, which has no `cgroup.type`, is exempt from
+                // the "no internal processes" rule.
+                let moved = if cgroup.parent.is_none() && parent.join("cgroup.type").exists() {
+                    if init_leaf.is_none() {
+                        if !cgroup.move_current_process {
+                            let errmsg = format!(
+                                "can not enable controllers in the cgroup {parent:?} of the current process, set a delegated cgroup as the parent"
+                            );
+                            Err(ProcessErrorKind::SetupCgroupFailed(errmsg))?;
+                        }
+                        *init_leaf = Some(Self::move_into_init_leaf(&parent)?);
+                    }
+                    init_leaf.as_mut()
+                } else {
+                    None
+                };
+
+                let contents = missing.iter().map(|c| format!("+{c}")).collect::<Vec<_>>();
+                Self::write(&parent.join("cgroup.subtree_control"), &contents.join(" "))?;
+                if let Some(init) = moved {
+                    init.controllers
+                        .extend(missing.iter().map(|c| c.to_string()));
+                }
+            }
+        }
+
+        // Create the leaf.
+        let seq = LEAF_SEQ.fetch_add(1, Ordering::Relaxed);
+        let name = format!("hakoniwa-{}-{}", std::process::id(), seq);
+        let path = parent.join(name);
+        fs::create_dir(&path).map_err(|err| {
+            let errmsg = format!("mkdir({path:?}) => {err}");
+            ProcessErrorKind::SetupCgroupFailed(errmsg)
+        })?;
+
+        let init = cgroup.parent.is_none() && init_leaf.is_some();
+        if let Some(init_leaf) = init_leaf.as_mut().filter(|_| init) {
+            init_leaf.leaves += 1;
+        }
+        Ok(Self { path, init })
+    }
+
+    /// Returns the path of the leaf cgroup.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the peak/usage counters.
+    pub(crate) fn stats(&self) -> CgroupStats {
+        let read = |file: &str| fs::read_to_string(self.path.join(file)).unwrap_or_default();
+        let field = |contents: &str, key: &str| -> u64 {
+            contents
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .find(|(k, _)| *k == key)
+                .and_then(|(_, v)| v.trim().parse().ok())
+                .unwrap_or_default()
+        };
+
+        let cpu_stat = read("cpu.stat");
+        let memory_events = read("memory.events");
+        CgroupStats {
+            memory_peak: read("memory.peak").trim().parse().unwrap_or_default(),
+            pids_peak: read("pids.peak").trim().parse().unwrap_or_default(),
+            cpu_usage_usec: field(&cpu_stat, "usage_usec"),
+            cpu_user_usec: field(&cpu_stat, "user_usec"),
+            cpu_system_usec: field(&cpu_stat, "system_usec"),
+            oom_kill: field(&memory_events, "oom_kill"),
+        }
+    }
+
+    /// Moves the current process into a sub-leaf of `parent`, which requires
+    /// it to be the only process in `parent`.
+    fn move_into_init_leaf(parent: &Path) -> Result<InitLeaf> {
+        let pid = std::process::id().to_string();
+        let procs = Self::read(&parent.join("cgroup.procs"))?;
+        if procs.split_whitespace().any(|p| p != pid) {
+            let errmsg = format!(
+                "cgroup {parent:?} contains other processes, set an empty delegated cgroup as the parent"
+            );
+            Err(ProcessErrorKind::SetupCgroupFailed(errmsg))?;
+        }
+
+        let path = parent.join(format!("hakoniwa-{pid}-init"));
+        match fs::create_dir(&path) {
+            Err(err) if err.kind() != ErrorKind::AlreadyExists => {
+                let errmsg = format!("mkdir({path:?}) => {err}");
+                Err(ProcessErrorKind::SetupCgroupFailed(errmsg))?;
+            }
+            _ => {}
+        }
+        Self::write(&path.join("cgroup.procs"), &pid)?;
+        Ok(InitLeaf {
+            path,
+            controllers: vec![],
+            leaves: 0,
+        })
+    }
+
+    /// Moves the current process back into its cgroup, and removes the
+    /// sub-leaf. It is kept as is if any step fails, e.g. other processes
+    /// have been created in the sub-leaf meanwhile.
+    fn remove_init_leaf(init_leaf: &mut Option<InitLeaf>) {
+        let Some(init) = init_leaf else {
+            return;
+        };
+        let Some(parent) = init.path.parent() else {
+            return;
+        };
+
+        if !init.controllers.is_empty() {
+            let contents = init.controllers.iter().map(|c| format!("-{c}"));
+            let contents = contents.collect::<Vec<_>>().join(" ");
+            if fs::write(parent.join("cgroup.subtree_control"), contents).is_err() {
+                return;
+            }
+            init.controllers.clear();
+        }
+
+        let pid = std::process::id().to_string();
+        if fs::write(parent.join("cgroup.procs"), pid).is_err() {
+            return;
+        }
+        _ = fs::remove_dir(&init.path);
+        *init_leaf = None;
+    }
+
+    /// Waits for all processes in the leaf cgroup to exit, up to `timeout`.
+    fn wait_unpopulated(&self, timeout: Duration) {
+        let Ok(mut file) = File::open(self.path.join("cgroup.events")) else {
+            return;
+        };
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut contents = String::new();
+            if file.seek(SeekFrom::Start(0)).is_err() || file.read_to_string(&mut contents).is_err()
+            {
+                return;
+            }
+            if contents.lines().any(|line| line == "populated 0") {
+                return;
+            }
+
+            // A modification of the file is notified as POLLPRI.
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let timeout = PollTimeout::try_from(remaining).unwrap_or(PollTimeout::MAX);
+            let mut fds = [PollFd::new(file.as_fd(), PollFlags::POLLPRI)];
+            match poll(&mut fds, timeout) {
+                Ok(n) if n > 0 => {}
+                _ => return,
+            }
+        }
+    }
+
+    /// Returns the cgroup of the current process.
+    fn current() -> Result<PathBuf> {
+        let errmsg = |err: procfs::ProcError| ProcessErrorKind::SetupCgroupFailed(err.to_string());
+        let process = procfs::process::Process::myself().map_err(errmsg)?;
+
+        let mountinfo = process.mountinfo().map_err(errmsg)?;
+        let mount = mountinfo.into_iter().find(|m| m.fs_type == "cgroup2");
+        let mount = mount.ok_or_else(|| {
+            let errmsg = "cgroup2 filesystem is not mounted".to_string();
+            ProcessErrorKind::SetupCgroupFailed(errmsg)
+        })?;
+
+        let cgroups = process.cgroups().map_err(errmsg)?;
+        let cgroup = cgroups.into_iter().find(|c| c.hierarchy == 0);
+        let cgroup = cgroup.ok_or_else(|| {
+            let errmsg = "cgroup v2 hierarchy not found in /proc/self/cgroup".to_string();
+            ProcessErrorKind::SetupCgroupFailed(errmsg)
+        })?;
+
+        let root = mount.root.trim_end_matches('/');
+        let pathname = cgroup
+            .pathname
+            .strip_prefix(root)
+            .unwrap_or(&cgroup.pathname);
+        let pathname = pathname.trim_start_matches('/');
+        Ok(mount.mount_point.join(pathname))
+    }
+
+    fn read(path: &Path) -> Result<String> {
+        let contents = fs::read_to_string(path).map_err(|err| {
+            let errmsg = format!("read({path:?}) => {err}");
+            ProcessErrorKind::SetupCgroupFailed(errmsg)
+        })?;
+        Ok(contents)
+    }
+
+    fn write(path: &Path, contents: &str) -> Result<()> {
+        fs::write(path, contents).map_err(|err| {
+            let errmsg = format!("write({path:?}, {contents:?}) => {err}");
+            ProcessErrorKind::SetupCgroupFailed(errmsg)
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for CgroupLeaf {
+    fn drop(&mut self) {
+        // Kill the remaining processes, requires kernel 5.14. The cgroup is
+        // busy until all processes exit.
+        _ = fs::write(self.path.join("cgroup.kill"), "1");
+        self.wait_unpopulated(Duration::from_secs(1));
+        _ = fs::remove_dir(&self.path);
+
+        if self.init {
+            let mut init_leaf = INIT_LEAF.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(init) = init_leaf.as_mut() {
+                init.leaves -= 1;
+                if init.leaves == 0 {
+                    Self::remove_init_leaf(&mut init_leaf);
+                }
+            }
+        }
+    }
+}
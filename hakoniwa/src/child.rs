@@ -1,14 +1,19 @@
-use nix::sys::signal::{self, Signal};
-use nix::sys::wait::{self, WaitPidFlag, WaitStatus};
+use nix::sys::signal::Signal;
+use nix::sys::wait::{self, Id, WaitPidFlag, WaitStatus};
 use nix::unistd::Pid;
 use serde::{Deserialize, Serialize};
+use std::fs::File;
 use std::io::prelude::*;
 use std::io::{PipeReader, PipeWriter};
-use std::thread;
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::thread::{self, JoinHandle};
 use std::{fmt, str};
 use tempfile::TempDir;
 
-use crate::{Command, ProcPidSmapsRollup, ProcPidStatus, Rusage, error::*};
+use crate::cgroup::CgroupLeaf;
+use crate::{
+    Capability, CgroupStats, Command, ProcPidSmapsRollup, ProcPidStatus, Rusage, error::*, pidfd,
+};
 
 /// Result of a process after it has terminated.
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -30,6 +35,35 @@ pub struct ExitStatus {
 
     /// Memory usage and status information of the internal process.
     pub proc_pid_status: Option<ProcPidStatus>,
+
+    /// Peak/usage counters of the cgroup.
+    pub cgroup_stats: Option<CgroupStats>,
+
+    /// The syscall blocked by a seccomp rule with the `KillProcess`,
+    /// `KillThread` or `Trap` action in the internal process or its
+    /// descendants, see [Runctl::ReportBlockedSyscall].
+    ///
+    /// [Runctl::ReportBlockedSyscall]: crate::Runctl::ReportBlockedSyscall
+    pub blocked_syscall: Option<BlockedSyscall>,
+
+    /// The syscalls made by the internal process and its descendants, see
+    /// [Runctl::RecordSyscalls].
+    ///
+    /// [Runctl::RecordSyscalls]: crate::Runctl::RecordSyscalls
+    pub recorded_syscalls: Option<Vec<RecordedSyscall>>,
+
+    /// The directories accessed by the internal process and its descendants,
+    /// see [Runctl::RecordFsAccess].
+    ///
+    /// [Runctl::RecordFsAccess]: crate::Runctl::RecordFsAccess
+    pub recorded_fs_accesses: Option<Vec<RecordedFsAccess>>,
+
+    /// The enforcement status of the landlock ruleset, if any.
+    pub landlock_status: Option<LandlockStatus>,
+
+    /// The capability sets of the internal process after dropping/adding
+    /// capabilities, if any.
+    pub capability_status: Option<CapabilityStatus>,
 }
 
 impl ExitStatus {
@@ -45,6 +79,12 @@ impl ExitStatus {
             rusage: None,
             proc_pid_smaps_rollup: None,
             proc_pid_status: None,
+            cgroup_stats: None,
+            blocked_syscall: None,
+            recorded_syscalls: None,
+            recorded_fs_accesses: None,
+            landlock_status: None,
+            capability_status: None,
         }
     }
 
@@ -59,6 +99,12 @@ impl ExitStatus {
                 rusage: None,
                 proc_pid_smaps_rollup: None,
                 proc_pid_status: None,
+                cgroup_stats: None,
+                blocked_syscall: None,
+                recorded_syscalls: None,
+                recorded_fs_accesses: None,
+                landlock_status: None,
+                capability_status: None,
             },
             WaitStatus::Signaled(_, signal, _) => Self {
                 code: 128 + signal as i32,
@@ -67,6 +113,12 @@ impl ExitStatus {
                 rusage: None,
                 proc_pid_smaps_rollup: None,
                 proc_pid_status: None,
+                cgroup_stats: None,
+                blocked_syscall: None,
+                recorded_syscalls: None,
+                recorded_fs_accesses: None,
+                landlock_status: None,
+                capability_status: None,
             },
             _ => {
                 unreachable!("ExitStatus::from_wait_status");
@@ -81,6 +133,116 @@ impl ExitStatus {
     }
 }
 
+/// A syscall of the internal process blocked by the seccomp filter.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BlockedSyscall {
+    /// The syscall number.
+    pub syscall: i32,
+
+    /// The syscall name, or empty if it is unknown to libseccomp.
+    pub sysname: String,
+
+    /// The architecture of the syscall, e.g. `x86_64`.
+    pub arch: String,
+
+    /// The address of the instruction that triggered the syscall.
+    pub instruction_pointer: u64,
+}
+
+/// A syscall made by the internal process, recorded in learning mode.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedSyscall {
+    /// The syscall number.
+    pub syscall: i32,
+
+    /// The syscall name.
+    pub sysname: String,
+
+    /// The architecture of the syscall, e.g. `x86_64`.
+    pub arch: String,
+
+    /// The distinct values of the argument which selects the behavior of
+    /// the syscall, as `(index, value)`, e.g. the address family of
+    /// `socket`. Empty for the other syscalls.
+    pub args: Vec<(u32, u64)>,
+}
+
+/// A directory accessed by the internal process, recorded in learning mode.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedFsAccess {
+    /// The directory, or the one containing the accessed file.
+    pub path: String,
+
+    /// The access to the directory and the files beneath it, e.g. `r-x`.
+    pub access: String,
+}
+
+/// The enforcement status of the landlock ruleset in the internal process.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LandlockStatus {
+    /// How much of the ruleset is enforced by the running kernel.
+    pub ruleset: LandlockRulesetStatus,
+
+    /// The Landlock ABI version of the running kernel, or 0 if Landlock is
+    /// not supported.
+    pub abi: i32,
+
+    /// The restricted resources handled by the running kernel, with the
+    /// FS access rights actually handled for [Resource::FS], e.g.
+    /// `(Resource::FS, Some(FsAccess::R | FsAccess::W | FsAccess::X))`,
+    /// `(Resource::NET_TCP_BIND, None)`. Unsupported resources are absent.
+    ///
+    /// [Resource::FS]: crate::landlock::Resource::FS
+    #[cfg(feature = "landlock")]
+    pub handled: Vec<(crate::landlock::Resource, Option<crate::landlock::FsAccess>)>,
+}
+
+/// The capability sets of the internal process, read back right before it
+/// executes the program.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CapabilityStatus {
+    /// The effective set.
+    pub effective: Vec<Capability>,
+
+    /// The permitted set.
+    pub permitted: Vec<Capability>,
+
+    /// The inheritable set.
+    pub inheritable: Vec<Capability>,
+
+    /// The bounding set.
+    pub bounding: Vec<Capability>,
+
+    /// The ambient set.
+    pub ambient: Vec<Capability>,
+}
+
+/// How much of a landlock ruleset is enforced, from the lowest level to the
+/// highest.
+#[derive(Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
+pub enum LandlockRulesetStatus {
+    /// The running kernel does not support Landlock, or none of the
+    /// requested restrictions.
+    NotEnforced,
+
+    /// Some of the requested restrictions are enforced.
+    PartiallyEnforced,
+
+    /// All of the requested restrictions are enforced.
+    FullyEnforced,
+}
+
+impl fmt::Display for LandlockRulesetStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Self::NotEnforced => "not enforced",
+            Self::PartiallyEnforced => "partially enforced",
+            Self::FullyEnforced => "fully enforced",
+        };
+        write!(f, "{s}")
+    }
+}
+
 /// The output of a finished process.
 pub struct Output {
     /// The status of the child process.
@@ -122,15 +284,26 @@ impl fmt::Debug for Output {
 ///
 /// [Command::spawn]: crate::Command::spawn
 /// [std::process::Child]: https://doc.rust-lang.org/std/process/struct.Child.html
+///
+/// The child process is referred to by a [pidfd], which can be used to
+/// integrate into external event loops, it becomes readable when the child
+/// process exits.
+///
+/// [pidfd]: https://man7.org/linux/man-pages/man2/pidfd_open.2.html
 pub struct Child {
     pid: Pid,
-    status: Option<ExitStatus>,
-    status_reader: Option<PipeReader>,
+    pub(crate) pidfd: OwnedFd,
+    pub(crate) status: Option<ExitStatus>,
+    pub(crate) status_reader: Option<PipeReader>,
     status_reader_noleading: bool,
     tmpdir: Option<TempDir>,
+    cgroup: Option<CgroupLeaf>,
+    tracer: Option<JoinHandle<()>>,
+    pub(crate) tracer_done: Option<PipeReader>,
     pub stdin: Option<PipeWriter>,
     pub stdout: Option<PipeReader>,
     pub stderr: Option<PipeReader>,
+    pub pty: Option<File>,
 }
 
 impl Child {
@@ -138,6 +311,7 @@ impl Child {
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         pid: Pid,
+        pidfd: OwnedFd,
         stdin: Option<PipeWriter>,
         stdout: Option<PipeReader>,
         stderr: Option<PipeReader>,
@@ -145,9 +319,14 @@ impl Child {
         status_reader_noleading: bool,
         status: Option<ExitStatus>,
         tmpdir: Option<TempDir>,
+        cgroup: Option<CgroupLeaf>,
+        pty: Option<File>,
+        tracer: Option<(JoinHandle<()>, PipeReader)>,
     ) -> Self {
+        let (tracer, tracer_done) = tracer.unzip();
         Self {
             pid,
+            pidfd,
             stdin,
             stdout,
             stderr,
@@ -155,6 +334,10 @@ impl Child {
             status_reader_noleading,
             status,
             tmpdir,
+            cgroup,
+            pty,
+            tracer,
+            tracer_done,
         }
     }
 
@@ -165,15 +348,14 @@ impl Child {
 
     /// Forces the child process to exit.
     pub fn kill(&mut self) -> Result<()> {
-        // If we've already waited on this process then the pid can be recycled
-        // and used for another process, and we probably shouldn't be killing
-        // random processes, so return Ok because the process has exited already.
-        if self.status.is_some() {
-            return Ok(());
+        // The signal is sent through the pidfd, so it never hits a recycled
+        // pid. If the child process has exited already, just return Ok.
+        match pidfd::send_signal(self.pidfd.as_fd(), Signal::SIGKILL) {
+            Err(err) if err.raw_os_error() != Some(libc::ESRCH) => {
+                Err(ProcessErrorKind::StdIoError(err))?
+            }
+            _ => Ok(()),
         }
-
-        _ = signal::kill(self.pid, Signal::SIGKILL);
-        Ok(())
     }
 
     /// Attempts to collect the exit status of the child if it has already exited.
@@ -193,8 +375,9 @@ impl Child {
             return Ok(Some(status.clone()));
         }
 
-        let flags = Some(WaitPidFlag::WNOHANG);
-        let ws = wait::waitpid(self.pid, flags).map_err(ProcessErrorKind::NixError)?;
+        let flags = WaitPidFlag::WEXITED | WaitPidFlag::WNOHANG;
+        let ws = wait::waitid(Id::PIDFd(self.pidfd.as_fd()), flags)
+            .map_err(ProcessErrorKind::NixError)?;
         if let WaitStatus::StillAlive = ws {
             Ok(None)
         } else {
@@ -216,51 +399,78 @@ impl Child {
             return Ok(status.clone());
         }
 
-        let flags = None;
-        let ws = wait::waitpid(self.pid, flags).map_err(ProcessErrorKind::NixError)?;
+        let flags = WaitPidFlag::WEXITED;
+        let ws = wait::waitid(Id::PIDFd(self.pidfd.as_fd()), flags)
+            .map_err(ProcessErrorKind::NixError)?;
         self.retrieve_exit_status(ws)
     }
 
     /// Retrieve exit status.
     fn retrieve_exit_status(&mut self, ws: WaitStatus) -> Result<ExitStatus> {
+        let mut encoded = vec![];
+        if let Some(mut reader) = self.status_reader.take() {
+            reader
+                .read_to_end(&mut encoded)
+                .map_err(ProcessErrorKind::StdIoError)?;
+        }
+        self.decode_exit_status(ws, &encoded)?;
+        let cgroup_stats = self.take_resources().release();
+        self.finish_exit_status(cgroup_stats)
+    }
+
+    /// Decode the exit status from the data read from the status pipe.
+    pub(crate) fn decode_exit_status(&mut self, ws: WaitStatus, encoded: &[u8]) -> Result<()> {
         if let WaitStatus::Signaled(_, Signal::SIGKILL, _) = ws {
             let reason = "container received signal SIGKILL";
             self.status = Some(ExitStatus::new_failure(reason));
         }
 
         if self.status.is_none() {
-            self.retrieve_exit_status_internal_process()?;
+            self.retrieve_exit_status_internal_process(encoded)?;
         }
+        Ok(())
+    }
+
+    /// Takes the resources to release once the child process exits.
+    pub(crate) fn take_resources(&mut self) -> ChildResources {
+        ChildResources {
+            tmpdir: self.tmpdir.take(),
+            cgroup: self.cgroup.take(),
+            tracer: self.tracer.take(),
+            tracer_done: self.tracer_done.take(),
+        }
+    }
 
+    /// Completes the decoded exit status with the cgroup stats.
+    pub(crate) fn finish_exit_status(
+        &mut self,
+        cgroup_stats: Option<CgroupStats>,
+    ) -> Result<ExitStatus> {
+        if let Some(status) = &mut self.status
+            && cgroup_stats.is_some()
+        {
+            status.cgroup_stats = cgroup_stats;
+        }
         self.logging();
-        drop(self.tmpdir.take());
 
         let s = self.status.clone();
         s.ok_or(Error::ProcessError(ProcessErrorKind::ChildExitStatusGone))
     }
 
-    /// Retrieve the exit status of the internal process from a pipe whose
-    /// write end has been closed.
-    fn retrieve_exit_status_internal_process(&mut self) -> Result<()> {
-        if let Some(mut reader) = self.status_reader.take() {
-            if !self.status_reader_noleading {
-                let mut request = [0];
-                reader
-                    .read_exact(&mut request)
-                    .map_err(ProcessErrorKind::StdIoError)?;
-            }
-
-            let mut encoded = vec![];
-            reader
-                .read_to_end(&mut encoded)
-                .map_err(ProcessErrorKind::StdIoError)?;
-            drop(reader);
+    /// Retrieve the exit status of the internal process from the data of
+    /// a pipe whose write end has been closed.
+    fn retrieve_exit_status_internal_process(&mut self, encoded: &[u8]) -> Result<()> {
+        let encoded = if self.status_reader_noleading {
+            encoded
+        } else {
+            let eof = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+            encoded.get(1..).ok_or(ProcessErrorKind::StdIoError(eof))?
+        };
 
-            let config = bincode::config::standard();
-            let (status, _) = bincode::serde::decode_from_slice(&encoded[..], config)
-                .map_err(ProcessErrorKind::BincodeDecodeError)?;
-            self.status = Some(status);
-        }
+        let config = bincode::config::standard();
+        let (status, _) = bincode::serde::decode_from_slice(encoded, config)
+            .map_err(ProcessErrorKind::BincodeDecodeError)?;
+        self.status = Some(status);
         Ok(())
     }
 
@@ -309,6 +519,42 @@ impl Child {
                 log::debug!("Metric:       RssFile: {:>12} kB", r.rssfile);
                 log::debug!("Metric:      RssShmem: {:>12} kB", r.rssshmem);
             }
+
+            if let Some(r) = &status.cgroup_stats {
+                log::debug!("Metric:    MemoryPeak: {:>12} B", r.memory_peak);
+                log::debug!("Metric:      PidsPeak: {:>12}", r.pids_peak);
+                log::debug!("Metric:      CpuUsage: {:>12} usec", r.cpu_usage_usec);
+                log::debug!("Metric:       CpuUser: {:>12} usec", r.cpu_user_usec);
+                log::debug!("Metric:     CpuSystem: {:>12} usec", r.cpu_system_usec);
+                log::debug!("Metric:       OomKill: {:>12}", r.oom_kill);
+            }
+
+            if let Some(r) = &status.landlock_status {
+                log::debug!("Landlock: {} (ABI {})", r.ruleset, r.abi);
+                #[cfg(feature = "landlock")]
+                for (resource, access) in &r.handled {
+                    match access {
+                        Some(access) => log::debug!("Landlock: {resource:>14}: {access}"),
+                        None => log::debug!("Landlock: {resource:>14}: {resource}"),
+                    }
+                }
+            }
+
+            if let Some(r) = &status.capability_status {
+                for (set, caps) in [
+                    ("  effective", &r.effective),
+                    ("  permitted", &r.permitted),
+                    ("inheritable", &r.inheritable),
+                    ("   bounding", &r.bounding),
+                    ("    ambient", &r.ambient),
+                ] {
+                    let caps = caps.iter().map(|cap| cap.to_string()).collect::<Vec<_>>();
+                    match caps.is_empty() {
+                        true => log::debug!("Capabilities: {set}: -"),
+                        false => log::debug!("Capabilities: {set}: {}", caps.join(", ")),
+                    }
+                }
+            }
         } else {
             log::debug!("================================");
             log::debug!("Exited: NULL");
@@ -322,10 +568,17 @@ impl Child {
     /// waiting. This helps avoid deadlock: it ensures that the child does not
     /// block waiting for input from the parent, while the parent waits for
     /// the child to exit.
+    ///
+    /// If a pseudo-terminal is allocated, the output on it is collected as
+    /// stdout.
     pub fn wait_with_output(&mut self) -> Result<Output> {
         drop(self.stdin.take());
 
         let (mut stdout, mut stderr) = (vec![], vec![]);
+        if let Some(pty) = self.pty.take() {
+            self.read_pty(pty, &mut stdout)?;
+        }
+
         match (self.stdout.take(), self.stderr.take()) {
             (None, None) => {}
             (Some(mut out), None) => {
@@ -351,6 +604,17 @@ impl Child {
         })
     }
 
+    fn read_pty(&mut self, mut pty: File, stdout: &mut Vec<u8>) -> Result<()> {
+        // Reading from the master returns EIO once all the slave sides are
+        // closed, which means the program has exited.
+        match pty.read_to_end(stdout) {
+            Err(err) if err.raw_os_error() != Some(libc::EIO) => {
+                Err(ProcessErrorKind::StdIoError(err))?
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn read2(
         &mut self,
         out: &mut PipeReader,
@@ -378,3 +642,35 @@ impl Child {
         Ok(())
     }
 }
+
+/// The resources of a child process, which are released once it exits.
+pub(crate) struct ChildResources {
+    tmpdir: Option<TempDir>,
+    cgroup: Option<CgroupLeaf>,
+    tracer: Option<JoinHandle<()>>,
+    tracer_done: Option<PipeReader>,
+}
+
+impl ChildResources {
+    /// Releases the resources, returning the stats of the cgroup.
+    ///
+    /// It blocks until the tracer thread exits, and the processes left in the
+    /// cgroup are killed.
+    pub(crate) fn release(mut self) -> Option<CgroupStats> {
+        let cgroup_stats = self.cgroup.take().map(|cgroup| cgroup.stats());
+
+        // The traced syscalls are all sent once the child process exits.
+        if let Some(tracer) = self.tracer.take() {
+            _ = tracer.join();
+        }
+        drop(self.tracer_done.take());
+        drop(self.tmpdir.take());
+        cgroup_stats
+    }
+}
+
+impl AsFd for Child {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.pidfd.as_fd()
+    }
+}
@@ -1,13 +1,22 @@
 use nix::sys::signal::{self, Signal};
 use nix::unistd::{self, ForkResult, Pid};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::fs;
+use std::fs::File;
 use std::io::prelude::*;
 use std::io::{PipeReader, PipeWriter, pipe};
+use std::os::fd::AsFd;
+use std::os::unix::net::UnixStream;
 use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
 use tempfile::TempDir;
 
-use crate::{Child, Container, ExitStatus, Namespace, Output, Stdio, error::*, runc};
+use crate::cgroup::CgroupLeaf;
+use crate::{
+    Child, Container, EnvFile, ExitStatus, Mount, Namespace, Output, Stdio, error::*, pidfd, runc,
+    scm_rights,
+};
 
 /// Process builder, providing fine-grained control over how a new process
 /// should be spawned.
@@ -22,8 +31,11 @@ pub struct Command {
     program: String,
     args: Vec<String>,
     envs: HashMap<String, String>,
+    envs_inherit: Vec<String>,
+    envs_remove: HashSet<String>,
     dir: Option<PathBuf>,
     stdin: Option<Stdio>,
+    stdin_bytes: Option<Vec<u8>>,
     stdout: Option<Stdio>,
     stderr: Option<Stdio>,
     pub(crate) wait_timeout: Option<u64>,
@@ -38,8 +50,11 @@ impl Command {
             program: program.to_string(),
             args: vec![],
             envs: HashMap::new(),
+            envs_inherit: vec![],
+            envs_remove: HashSet::new(),
             dir: None,
             stdin: None,
+            stdin_bytes: None,
             stdout: None,
             stderr: None,
             wait_timeout: None,
@@ -64,9 +79,15 @@ impl Command {
         self
     }
 
+    /// The environment variables inherited by [env_inherit_default].
+    ///
+    /// [env_inherit_default]: Command::env_inherit_default
+    pub const DEFAULT_ENVS_INHERIT: [&str; 4] = ["PATH", "TERM", "LANG", "LC_*"];
+
     /// Inserts or updates an explicit environment variable mapping.
     pub fn env(&mut self, key: &str, val: &str) -> &mut Self {
         self.envs.insert(key.to_string(), val.to_string());
+        self.envs_remove.remove(key);
         self
     }
 
@@ -83,6 +104,57 @@ impl Command {
         self
     }
 
+    /// Inherits the environment variables of the current process whose names
+    /// match any of the glob `patterns`, e.g. `LC_*`. Explicit mappings take
+    /// precedence over inherited ones.
+    ///
+    /// By default, no environment variables are inherited.
+    pub fn env_inherit<I, S>(&mut self, patterns: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for pattern in patterns {
+            self.envs_inherit.push(pattern.as_ref().to_string());
+        }
+        self
+    }
+
+    /// Inherits a default set of environment variables that are safe to pass
+    /// through, see [Command::DEFAULT_ENVS_INHERIT].
+    pub fn env_inherit_default(&mut self) -> &mut Self {
+        self.env_inherit(Self::DEFAULT_ENVS_INHERIT)
+    }
+
+    /// Removes an explicit environment variable mapping, and prevents it from
+    /// being inherited.
+    pub fn env_remove(&mut self, key: &str) -> &mut Self {
+        self.envs.remove(key);
+        self.envs_remove.insert(key.to_string());
+        self
+    }
+
+    /// Clears all explicit environment variable mappings and inheritance
+    /// patterns.
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.envs.clear();
+        self.envs_inherit.clear();
+        self.envs_remove.clear();
+        self
+    }
+
+    /// Inserts or updates the environment variable mappings read from a
+    /// dotenv-style file, which contains `NAME=VALUE` lines. Blank lines,
+    /// lines starting with `#` and the `export` keyword are ignored, and the
+    /// value may be single or double quoted.
+    pub fn env_file<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self> {
+        let path = path.as_ref().to_string_lossy();
+        for entry in EnvFile::new(&path).entries()? {
+            self.env(&entry.name, &entry.value);
+        }
+        Ok(self)
+    }
+
     /// Sets the working directory for the child process.
     pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
         self.dir = Some(dir.as_ref().to_path_buf());
@@ -95,6 +167,19 @@ impl Command {
         self
     }
 
+    /// Allocates a pseudo-terminal for the child process, it will be the
+    /// controlling terminal and the stdin/stdout/stderr of the program, and
+    /// the master is returned as [Child::pty].
+    ///
+    /// The pseudo-terminal is allocated from the devpts of the devfs mounted
+    /// on `/dev`, see [Container::devfsmount].
+    ///
+    /// [Child::pty]: crate::Child::pty
+    pub fn pty(&mut self, pty: bool) -> &mut Self {
+        self.container.pty = pty;
+        self
+    }
+
     /// Configuration for the child process’s standard input (stdin) handle.
     ///
     /// Defaults to [inherit] when used with [spawn] or [status], and defaults
@@ -107,6 +192,21 @@ impl Command {
     /// [output]: Command::output
     pub fn stdin(&mut self, cfg: Stdio) -> &mut Self {
         self.stdin = Some(cfg);
+        self.stdin_bytes = None;
+        self
+    }
+
+    /// Feeds `bytes` to the child process’s standard input (stdin), overrides
+    /// the [stdin] configuration.
+    ///
+    /// The data is stored in an in-memory file rather than written to a pipe,
+    /// so it never blocks the parent process, and the child process reads EOF
+    /// once all of it is consumed.
+    ///
+    /// [stdin]: Command::stdin
+    pub fn stdin_bytes(&mut self, bytes: Vec<u8>) -> &mut Self {
+        self.stdin = None;
+        self.stdin_bytes = Some(bytes);
         self
     }
 
@@ -145,9 +245,45 @@ impl Command {
         self.spawn_imp(Stdio::Inherit)
     }
 
+    /// Executes the command as a child process asynchronously, returning
+    /// a handle to it.
+    ///
+    /// This method must be called within the context of a Tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn spawn_async(&mut self) -> Result<crate::AsyncChild> {
+        let child = self.spawn_imp(Stdio::Inherit)?;
+        crate::AsyncChild::new(child)
+    }
+
     /// Command#spawn IMP.
     fn spawn_imp(&mut self, default: Stdio) -> Result<Child> {
-        let tmpdir = if let Some(dir) = &self.container.rootdir {
+        self.container.check_runctl_features()?;
+        #[cfg(feature = "seccomp")]
+        self.container.compile_seccomp_filter()?;
+
+        let tmpdir = if let Some(dir) = &self.container.rootdir
+            && self.container.rootdir_overlay
+        {
+            // The upper layer and the mount point live in the tmpdir.
+            let dir = fs::canonicalize(dir).map_err(ProcessErrorKind::StdIoError)?;
+            let tmpdir = TempDir::with_prefix("hakoniwa-").map_err(ProcessErrorKind::StdIoError)?;
+            let (upperdir, workdir, rootdir) = (
+                tmpdir.path().join("upper"),
+                tmpdir.path().join("work"),
+                tmpdir.path().join("rootfs"),
+            );
+            for dir in [&upperdir, &workdir, &rootdir] {
+                fs::create_dir(dir).map_err(ProcessErrorKind::StdIoError)?;
+            }
+            self.container.rootdir_overlaymount = Some(Mount::overlay(
+                &[dir.to_string_lossy()],
+                &upperdir.to_string_lossy(),
+                &workdir.to_string_lossy(),
+                "/".to_string(),
+            )?);
+            self.container.rootdir_abspath = rootdir;
+            Some(tmpdir)
+        } else if let Some(dir) = &self.container.rootdir {
             let dir = fs::canonicalize(dir).map_err(ProcessErrorKind::StdIoError)?;
             self.container.rootdir_abspath = dir;
             None
@@ -157,13 +293,33 @@ impl Command {
             Some(dir)
         };
 
+        let cgroup = if let Some(cgroup) = &self.container.cgroup {
+            let leaf = CgroupLeaf::create(cgroup)?;
+            self.container.cgroupdir_abspath = Some(leaf.path().to_path_buf());
+            Some(leaf)
+        } else {
+            None
+        };
+
         self.logging();
 
-        let (stdin_reader, stdin_writer) = Stdio::make_pipe(self.stdin.unwrap_or(default))?;
-        let (stdout_reader, stdout_writer) = Stdio::make_pipe(self.stdout.unwrap_or(default))?;
-        let (stderr_reader, stderr_writer) = Stdio::make_pipe(self.stderr.unwrap_or(default))?;
+        let (stdin_reader, stdin_writer) = match &self.stdin_bytes {
+            Some(bytes) => (Some(Stdio::make_bytes(bytes)?), None),
+            None => Stdio::make_stdin(self.stdin.as_ref().unwrap_or(&default))?,
+        };
+        let (stdout_reader, stdout_writer) =
+            Stdio::make_output(self.stdout.as_ref().unwrap_or(&default))?;
+        let (stderr_reader, stderr_writer) =
+            Stdio::make_output(self.stderr.as_ref().unwrap_or(&default))?;
         let mut pipe_a = pipe().map_err(ProcessErrorKind::StdIoError)?;
         let mut pipe_z = pipe().map_err(ProcessErrorKind::StdIoError)?;
+        let socket = if self.container.pty || self.container.needs_mainp_seccomp_supervisor() {
+            let pair = UnixStream::pair().map_err(ProcessErrorKind::StdIoError)?;
+            (Some(pair.0), Some(pair.1))
+        } else {
+            (None, None)
+        };
+        let (tracer, tracer_writer) = self.mainp_tracer()?;
 
         match unsafe { unistd::fork() } {
             Ok(ForkResult::Parent { child, .. }) => {
@@ -172,15 +328,31 @@ impl Command {
                 drop(stderr_writer);
                 drop(pipe_a.1);
                 drop(pipe_z.0);
+                drop(socket.1);
+                drop(tracer_writer);
+
+                let pidfd = match pidfd::open(child) {
+                    Ok(pidfd) => pidfd,
+                    Err(err) => {
+                        _ = signal::kill(child, Signal::SIGKILL);
+                        _ = nix::sys::wait::waitpid(child, None);
+                        Err(ProcessErrorKind::StdIoError(err))?
+                    }
+                };
 
                 let mut noleading = false;
                 let mut status = None;
+                let mut pty = None;
                 let r = self.mainp_setup(&mut pipe_a.0, &mut pipe_z.1, child);
                 match r {
                     // All goes well.
-                    Ok(0) => {
-                        // ;
-                    }
+                    Ok(0) => match self.mainp_recv_fds(socket.0) {
+                        Ok(fd) => pty = fd,
+                        Err(e) => {
+                            _ = pidfd::send_signal(pidfd.as_fd(), Signal::SIGKILL);
+                            status = Some(ExitStatus::new_failure(&e.to_string()));
+                        }
+                    },
                     // The child process exited early due to some errors.
                     Ok(1) => {
                         noleading = true;
@@ -195,7 +367,7 @@ impl Command {
                     //  - SetupUGidmapFailed: uid range not allowed, etc.
                     //  - StdIoError: failed to write to pipe cuz the child process force killed by taskmgr.
                     Err(e) => {
-                        _ = signal::kill(child, Signal::SIGKILL);
+                        _ = pidfd::send_signal(pidfd.as_fd(), Signal::SIGKILL);
                         status = Some(ExitStatus::new_failure(&e.to_string()));
                     }
                 };
@@ -203,6 +375,7 @@ impl Command {
                 drop(pipe_z.1);
                 Ok(Child::new(
                     child,
+                    pidfd,
                     stdin_writer,
                     stdout_reader,
                     stderr_reader,
@@ -210,15 +383,22 @@ impl Command {
                     noleading,
                     status,
                     tmpdir,
+                    cgroup,
+                    pty,
+                    tracer,
                 ))
             }
             Ok(ForkResult::Child) => {
                 tmpdir.map(|dir| dir.keep());
+                if let Some(leaf) = cgroup {
+                    std::mem::forget(leaf);
+                }
                 drop(stdin_writer);
                 drop(stdout_reader);
                 drop(stderr_reader);
                 drop(pipe_a.0);
                 drop(pipe_z.1);
+                drop(socket.0);
                 runc::exec(
                     self,
                     &self.container,
@@ -227,6 +407,8 @@ impl Command {
                     stderr_writer,
                     pipe_z.0,
                     pipe_a.1,
+                    socket.1,
+                    tracer_writer,
                 );
                 unreachable!("Command::spawn_imp");
             }
@@ -252,11 +434,25 @@ impl Command {
             log::debug!("Unshare namespaces: {}", namespaces.join(", "));
         }
 
+        let mut joined_namespaces = self
+            .container
+            .joined_namespaces
+            .iter()
+            .map(|(k, v)| format!("{k}: {v}"))
+            .collect::<Vec<_>>();
+        joined_namespaces.sort();
+        for ns in joined_namespaces {
+            log::debug!("Join namespace: {ns}");
+        }
+
         if self.container.namespaces.contains(&Namespace::Mount) {
             log::debug!(
                 "Mount:    root: {}",
                 self.container.rootdir_abspath.to_string_lossy(),
             );
+            if let Some(mount) = &self.container.rootdir_overlaymount {
+                log::debug!("Mount: {mount}");
+            }
             for mount in self.container.get_mounts() {
                 log::debug!("Mount: {mount}");
             }
@@ -282,12 +478,21 @@ impl Command {
             }
         }
 
+        if let Some(cgroup) = &self.container.cgroup {
+            if let Some(dir) = &self.container.cgroupdir_abspath {
+                log::debug!("Cgroup: {}", dir.to_string_lossy());
+            }
+            for (file, val) in cgroup.get_limits() {
+                log::debug!("Cgroup: {file}: {val}");
+            }
+        }
+
         for (k, v) in self.get_envs() {
             log::debug!("Env: {k}={v}")
         }
 
         #[cfg(feature = "landlock")]
-        if let Some(ruleset) = &self.container.landlock_ruleset {
+        if let Some(ruleset) = &self.container.get_landlock_ruleset() {
             use crate::landlock::*;
 
             if !ruleset.restrictions.is_empty() {
@@ -322,6 +527,8 @@ impl Command {
 
         #[cfg(feature = "seccomp")]
         if let Some(filter) = &self.container.seccomp_filter {
+            let caps = self.container.get_capabilities();
+            let rules = filter.active_rules(caps.as_ref());
             let mut arches = filter
                 .architectures
                 .iter()
@@ -330,12 +537,12 @@ impl Command {
             arches.sort();
             log::debug!(
                 "Seccomp: Load {} rules for architectures({})",
-                filter.rules.len() + 1,
+                rules.len() + 1,
                 arches.join(", ")
             );
 
             log::trace!("Seccomp rule: ... -> {:?}", filter.default_action);
-            for rule in &filter.rules {
+            for rule in rules {
                 log::trace!("Seccomp rule: {rule}");
             }
         }
@@ -396,6 +603,55 @@ impl Command {
         crate::unshare::mainp_setup_ugidmap(&self.container, child)
     }
 
+    /// Receive the master of the pseudo-terminal, and the seccomp notification
+    /// fd, in the order they are sent.
+    fn mainp_recv_fds(&self, socket: Option<UnixStream>) -> Result<Option<File>> {
+        let Some(socket) = socket else {
+            return Ok(None);
+        };
+
+        let pty = match self.container.pty {
+            true => scm_rights::recv_fd(&socket).map_err(ProcessErrorKind::StdIoError)?,
+            false => None,
+        };
+        #[cfg(feature = "seccomp")]
+        if self.container.needs_mainp_seccomp_supervisor() {
+            self.mainp_seccomp_supervisor(&socket)?;
+        }
+        Ok(pty.map(File::from))
+    }
+
+    /// Receive the seccomp notification fd, and spawn a supervisor thread.
+    #[cfg(feature = "seccomp")]
+    fn mainp_seccomp_supervisor(&self, socket: &UnixStream) -> Result<()> {
+        let fd = scm_rights::recv_fd(socket).map_err(ProcessErrorKind::StdIoError)?;
+        if let (Some(fd), Some(notifier)) = (fd, &self.container.seccomp_notifier) {
+            notifier
+                .supervise(fd)
+                .map_err(ProcessErrorKind::StdIoError)?;
+        }
+        Ok(())
+    }
+
+    /// Spawn a thread which sends the syscalls traced by the child process to
+    /// the sink, returns ((the thread, its completion pipe), the write end of
+    /// the pipe).
+    #[allow(clippy::type_complexity)]
+    fn mainp_tracer(&self) -> Result<(Option<(JoinHandle<()>, PipeReader)>, Option<PipeWriter>)> {
+        #[cfg(feature = "seccomp")]
+        if self
+            .container
+            .runctl
+            .contains(&crate::Runctl::TraceSyscalls)
+        {
+            let (reader, writer) = pipe().map_err(ProcessErrorKind::StdIoError)?;
+            let sink = self.container.trace_sink.clone().unwrap_or_default();
+            let tracer = sink.drain(reader).map_err(ProcessErrorKind::StdIoError)?;
+            return Ok((Some(tracer), Some(writer)));
+        }
+        Ok((None, None))
+    }
+
     /// Setup network.
     fn mainp_setup_network(&self, child: Pid) -> Result<()> {
         crate::unshare::mainp_setup_network(&self.container, child)
@@ -425,9 +681,23 @@ impl Command {
         self.args.clone()
     }
 
-    /// Returns the environment variables explicitly set for the child process.
+    /// Returns the environment variables for the child process, including
+    /// the inherited ones.
     pub fn get_envs(&self) -> HashMap<String, String> {
-        self.envs.clone()
+        let mut envs = HashMap::new();
+        if !self.envs_inherit.is_empty() {
+            for (key, val) in env::vars_os() {
+                let (Some(key), Some(val)) = (key.to_str(), val.to_str()) else {
+                    continue;
+                };
+                if self.envs_inherit.iter().any(|p| glob_match(p, key)) {
+                    envs.insert(key.to_string(), val.to_string());
+                }
+            }
+        }
+        envs.retain(|key, _| !self.envs_remove.contains(key));
+        envs.extend(self.envs.clone());
+        envs
     }
 
     /// Returns the working directory for the child process.
@@ -435,3 +705,32 @@ impl Command {
         self.dir.as_deref()
     }
 }
+
+/// Returns true if `name` matches the glob `pattern`, which supports the `*`
+/// and `?` wildcards.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bn)) => {
+                    backtrack = Some((bp, bn + 1));
+                    p = bp + 1;
+                    n = bn + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
@@ -5,7 +5,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::{
-    Command, FsOperation, IdMap, Mount, MountOptions, Namespace, Network, Rlimit, Runctl, error::*,
+    Capability, Cgroup, Command, FsOperation, IdMap, Mount, MountOptions, Namespace, NamespaceRef,
+    Network, Rlimit, Runctl, error::*,
 };
 
 /// Safe and isolated environment for executing command.
@@ -43,8 +44,12 @@ use crate::{
 #[derive(Clone, Debug)]
 pub struct Container {
     pub(crate) namespaces: HashSet<Namespace>,
+    pub(crate) joined_namespaces: HashMap<Namespace, NamespaceRef>,
     pub(crate) rootdir: Option<PathBuf>,
     pub(crate) rootdir_abspath: PathBuf,
+    pub(crate) rootdir_overlay: bool,
+    pub(crate) rootdir_overlaymount: Option<Mount>,
+    pub(crate) idmapped_owner: Option<(IdMap, IdMap)>,
     mounts: HashMap<String, Mount>,
     fs_operations: HashMap<String, FsOperation>,
     pub(crate) uidmaps: Option<Vec<IdMap>>,
@@ -55,10 +60,23 @@ pub struct Container {
     pub(crate) hostname: Option<String>,
     pub(crate) network: Option<Network>,
     pub(crate) rlimits: HashMap<Rlimit, (u64, u64)>,
+    pub(crate) cgroup: Option<Cgroup>,
+    pub(crate) cgroupdir_abspath: Option<PathBuf>,
+    pub(crate) cap_drop_all: bool,
+    pub(crate) cap_drop: HashSet<Capability>,
+    pub(crate) cap_add: HashSet<Capability>,
+    pub(crate) pty: bool,
     #[cfg(feature = "landlock")]
     pub(crate) landlock_ruleset: Option<crate::landlock::Ruleset>,
     #[cfg(feature = "seccomp")]
     pub(crate) seccomp_filter: Option<crate::seccomp::Filter>,
+    #[cfg(feature = "seccomp")]
+    pub(crate) seccomp_compiled:
+        std::sync::Arc<std::sync::OnceLock<crate::seccomp::CompiledFilter>>,
+    #[cfg(feature = "seccomp")]
+    pub(crate) seccomp_notifier: Option<crate::seccomp::Notifier>,
+    #[cfg(feature = "seccomp")]
+    pub(crate) trace_sink: Option<crate::TraceSink>,
     pub(crate) runctl: HashSet<Runctl>,
 }
 
@@ -106,8 +124,12 @@ impl Container {
     pub fn empty() -> Self {
         Self {
             namespaces: HashSet::new(),
+            joined_namespaces: HashMap::new(),
             rootdir: None,
             rootdir_abspath: PathBuf::new(),
+            rootdir_overlay: false,
+            rootdir_overlaymount: None,
+            idmapped_owner: None,
             mounts: HashMap::new(),
             fs_operations: HashMap::new(),
             uidmaps: None,
@@ -118,10 +140,22 @@ impl Container {
             hostname: None,
             network: None,
             rlimits: HashMap::new(),
+            cgroup: None,
+            cgroupdir_abspath: None,
+            cap_drop_all: false,
+            cap_drop: HashSet::new(),
+            cap_add: HashSet::new(),
+            pty: false,
             #[cfg(feature = "landlock")]
             landlock_ruleset: None,
             #[cfg(feature = "seccomp")]
             seccomp_filter: None,
+            #[cfg(feature = "seccomp")]
+            seccomp_compiled: Default::default(),
+            #[cfg(feature = "seccomp")]
+            seccomp_notifier: None,
+            #[cfg(feature = "seccomp")]
+            trace_sink: None,
             runctl: HashSet::new(),
         }
     }
@@ -130,12 +164,38 @@ impl Container {
     #[doc(hidden)]
     pub fn share(&mut self, namespace: Namespace) -> &mut Self {
         self.namespaces.remove(&namespace);
+        self.joined_namespaces.remove(&namespace);
         self
     }
 
     /// Create a new namespace.
     pub fn unshare(&mut self, namespace: Namespace) -> &mut Self {
         self.namespaces.insert(namespace);
+        self.joined_namespaces.remove(&namespace);
+        self
+    }
+
+    /// Join an existing namespace referred to by `ns`, e.g. `/proc/<pid>/ns/net`,
+    /// a bind-mounted ns file, or a pidfd, instead of creating a new one.
+    ///
+    /// This is useful when two containers need to talk to each other, e.g.
+    /// share the NETWORK namespace of a running container to connect to a
+    /// server over loopback.
+    ///
+    /// The USER namespace, if joined, is entered first. The other namespaces
+    /// are joined before creating the new ones, so the caller needs the
+    /// privileges over their owning user namespace at that time.
+    ///
+    /// # Caveats
+    ///
+    /// When joining a USER namespace, the [uidmap] and [gidmap] are ignored
+    /// because the mappings are already set up by its creator.
+    ///
+    /// [uidmap]: Container::uidmap
+    /// [gidmap]: Container::gidmap
+    pub fn join<T: Into<NamespaceRef>>(&mut self, namespace: Namespace, ns: T) -> &mut Self {
+        self.namespaces.remove(&namespace);
+        self.joined_namespaces.insert(namespace, ns.into());
         self
     }
 
@@ -153,6 +213,23 @@ impl Container {
     /// may be left behind even when the last process exits.
     pub fn rootdir<P: AsRef<Path>>(&mut self, host_path: P) -> &mut Self {
         self.rootdir = Some(PathBuf::from(host_path.as_ref()));
+        self.rootdir_overlay = false;
+        self
+    }
+
+    /// Use a copy-on-write view of `host_path` as the container root fs.
+    ///
+    /// An overlayfs is mounted with `host_path` as the lower layer, and a
+    /// throwaway upper layer in a tmpdir. The container root fs is writable,
+    /// but all changes are discarded when the last process exits, leaving
+    /// `host_path` untouched.
+    ///
+    /// # Caveats
+    ///
+    /// See [overlaymount][Container::overlaymount].
+    pub fn rootdir_overlay<P: AsRef<Path>>(&mut self, host_path: P) -> &mut Self {
+        self.rootdir = Some(PathBuf::from(host_path.as_ref()));
+        self.rootdir_overlay = true;
         self
     }
 
@@ -173,7 +250,7 @@ impl Container {
     fn rootfs_imp<P: AsRef<Path>>(&mut self, dir: P) -> std::result::Result<(), std::io::Error> {
         let dir = fs::canonicalize(&dir)?;
         let mut entries = vec![];
-        if dir == PathBuf::from("/") {
+        if dir == Path::new("/") {
             for entry in ["/bin", "/etc", "/lib", "/lib64", "/lib32", "/sbin", "/usr"] {
                 entries.push(PathBuf::from(entry));
             }
@@ -222,6 +299,46 @@ impl Container {
         self.mount(host_path, container_path, "", flags)
     }
 
+    /// Bind mount the `host_path` on `container_path` with read-only access
+    /// in new MOUNT namespace, which is id-mapped, see [Container::idmapped_owner].
+    pub fn bindmount_ro_idmapped(&mut self, host_path: &str, container_path: &str) -> &mut Self {
+        let flags = MountOptions::BIND
+            | MountOptions::REC
+            | MountOptions::NOSUID
+            | MountOptions::RDONLY
+            | MountOptions::IDMAP;
+        self.mount(host_path, container_path, "", flags)
+    }
+
+    /// Bind mount the `host_path` on `container_path` with read-write access
+    /// in new MOUNT namespace, which is id-mapped, see [Container::idmapped_owner].
+    pub fn bindmount_rw_idmapped(&mut self, host_path: &str, container_path: &str) -> &mut Self {
+        let flags =
+            MountOptions::BIND | MountOptions::REC | MountOptions::NOSUID | MountOptions::IDMAP;
+        self.mount(host_path, container_path, "", flags)
+    }
+
+    /// Bind mount the `host_path` on `container_path` with `options` in new MOUNT namespace.
+    ///
+    /// [MountOptions::BIND] is always implied, add [MountOptions::REC] to
+    /// also bind mount all of its submounts. At most one propagation type,
+    /// e.g. [MountOptions::RSLAVE], can be set.
+    ///
+    /// # Caveats
+    ///
+    /// In a new USER namespace, a mount that has submounts can only be bind
+    /// mounted recursively, and the flags locked by the kernel, e.g. atime
+    /// flags, can not be changed unless [Runctl::MountFallback] is set.
+    pub fn bindmount(
+        &mut self,
+        host_path: &str,
+        container_path: &str,
+        options: MountOptions,
+    ) -> Result<&mut Self> {
+        options.validate()?;
+        Ok(self.mount(host_path, container_path, "", options | MountOptions::BIND))
+    }
+
     /// Mount new devfs on `container_path` in new MOUNT namespace.
     ///
     /// # Caveats
@@ -245,6 +362,35 @@ impl Container {
         self.mount("proc", container_path, "proc", flags)
     }
 
+    /// Mount new overlayfs on `container_path` in new MOUNT namespace, which
+    /// merges `lowerdirs` (the uppermost one first) with `upperdir`.
+    ///
+    /// All changes go to `upperdir`, and `workdir` must be an empty directory
+    /// on the same filesystem as `upperdir`.
+    ///
+    /// # Caveats
+    ///
+    /// Mounting overlayfs in a new USER namespace requires kernel 5.11+. With
+    /// [Runctl::MountFallback], the [fuse-overlayfs] program will be used when
+    /// the kernel refuses to mount it.
+    ///
+    /// The `lowerdirs` must not be empty, and the paths must not contain
+    /// `\`, `,` or `:`.
+    ///
+    /// [fuse-overlayfs]: https://github.com/containers/fuse-overlayfs
+    pub fn overlaymount(
+        &mut self,
+        lowerdirs: &[&str],
+        upperdir: &str,
+        workdir: &str,
+        container_path: &str,
+    ) -> Result<&mut Self> {
+        let target = container_path.to_string();
+        let mount = Mount::overlay(lowerdirs, upperdir, workdir, target.clone())?;
+        self.mounts.insert(target, mount);
+        Ok(self)
+    }
+
     /// Mount.
     #[doc(hidden)]
     pub fn mount(
@@ -264,6 +410,7 @@ impl Container {
                 target,
                 fstype,
                 options,
+                data: String::new(),
             },
         );
         self
@@ -320,6 +467,41 @@ impl Container {
         self
     }
 
+    /// Files owned by the current user on the host appear owned by `uid`:`gid`
+    /// in id-mapped bind mounts, i.e. mounts with [MountOptions::IDMAP] such as
+    /// [Container::bindmount_rw_idmapped], and
+    /// files created by `uid`:`gid` are owned by the current user on the host.
+    ///
+    /// By default, id-mapped bind mounts use the same mappings as the new
+    /// USER namespace, that is to say files owned by a uid on the host appear
+    /// owned by the same uid in the container, regardless of the [uidmaps].
+    ///
+    /// # Caveats
+    ///
+    /// Id-mapped mounts require kernel 5.12+, and the mount source must be on
+    /// a filesystem that supports them, which is owned by the initial USER
+    /// namespace (e.g. ext4, btrfs, xfs), thus requires the CAP_SYS_ADMIN
+    /// capability on the host. The `uid`:`gid` must be mapped in the [uidmaps]
+    /// and [gidmaps].
+    ///
+    /// [gidmaps]: Container::gidmaps
+    ///
+    /// [uidmaps]: Container::uidmaps
+    pub fn idmapped_owner(&mut self, uid: u32, gid: u32) -> &mut Self {
+        let uidmap = IdMap {
+            container_id: uid,
+            host_id: Uid::current().as_raw(),
+            size: 1,
+        };
+        let gidmap = IdMap {
+            container_id: gid,
+            host_id: Gid::current().as_raw(),
+            size: 1,
+        };
+        self.idmapped_owner = Some((uidmap, gidmap));
+        self
+    }
+
     /// Create new UID maps in new USER namespace.
     pub fn uidmaps(&mut self, idmaps: &[(u32, u32, u32)]) -> &Self {
         self.uidmaps = Self::idmaps(idmaps);
@@ -385,6 +567,52 @@ impl Container {
         self
     }
 
+    /// Set cgroup v2 resource limits.
+    pub fn cgroup(&mut self, cgroup: Cgroup) -> &mut Self {
+        self.cgroup = Some(cgroup);
+        self
+    }
+
+    /// Drop the capability from the bounding, permitted, effective,
+    /// inheritable and ambient sets of the internal process.
+    ///
+    /// Once any capability is dropped, the securebits are locked, so that
+    /// the process can not change how capabilities are handled on execve and
+    /// setuid.
+    pub fn cap_drop(&mut self, cap: Capability) -> &mut Self {
+        self.cap_drop.insert(cap);
+        self.reset_seccomp_compiled();
+        self
+    }
+
+    /// Keep the capability even if it is dropped by [cap_drop] or
+    /// [cap_drop_all], and raise it in the inheritable and ambient sets, so
+    /// that it is retained by a non-root user across execve.
+    ///
+    /// ```no_run
+    /// use hakoniwa::{Capability, Container};
+    ///
+    /// // Keep only CAP_NET_BIND_SERVICE.
+    /// let mut container = Container::new();
+    /// container.cap_drop_all()
+    ///     .cap_add(Capability::CAP_NET_BIND_SERVICE);
+    /// ```
+    ///
+    /// [cap_drop]: Container::cap_drop
+    /// [cap_drop_all]: Container::cap_drop_all
+    pub fn cap_add(&mut self, cap: Capability) -> &mut Self {
+        self.cap_add.insert(cap);
+        self.reset_seccomp_compiled();
+        self
+    }
+
+    /// Drop all capabilities, see [Container::cap_drop].
+    pub fn cap_drop_all(&mut self) -> &mut Self {
+        self.cap_drop_all = true;
+        self.reset_seccomp_compiled();
+        self
+    }
+
     /// Set landlock ruleset.
     #[cfg(feature = "landlock")]
     pub fn landlock_ruleset(&mut self, ruleset: crate::landlock::Ruleset) -> &mut Self {
@@ -396,6 +624,69 @@ impl Container {
     #[cfg(feature = "seccomp")]
     pub fn seccomp_filter(&mut self, filter: crate::seccomp::Filter) -> &mut Self {
         self.seccomp_filter = Some(filter);
+        self.reset_seccomp_compiled();
+        self
+    }
+
+    /// Drops the compiled seccomp filter, it is compiled again for the rules
+    /// and the capabilities at the next spawn.
+    fn reset_seccomp_compiled(&mut self) {
+        #[cfg(feature = "seccomp")]
+        {
+            self.seccomp_compiled = Default::default();
+        }
+    }
+
+    /// Set seccomp user-notification handler.
+    ///
+    /// Syscalls matching a rule with [Action::Notify] in the seccomp filter
+    /// are suspended, and `handler` is invoked in a supervisor thread of the
+    /// main process to decide the response.
+    ///
+    /// The notification fd is sent to the main process with `sendmsg`, so
+    /// the filter must not notify it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hakoniwa::Container;
+    /// use hakoniwa::seccomp::{Action, Filter, Notification, Response};
+    ///
+    /// let mut filter = Filter::new(Action::Allow);
+    /// filter.add_rule(Action::Notify, "mkdir");
+    ///
+    /// let mut container = Container::new();
+    /// container
+    ///     .seccomp_filter(filter)
+    ///     .seccomp_notifier(|_: &Notification| Response::Errno(libc::EPERM));
+    /// ```
+    ///
+    /// [Action::Notify]: crate::seccomp::Action::Notify
+    #[cfg(feature = "seccomp")]
+    pub fn seccomp_notifier<H: crate::seccomp::SeccompHandler>(&mut self, handler: H) -> &mut Self {
+        self.seccomp_notifier = Some(crate::seccomp::Notifier::new(handler));
+        self
+    }
+
+    /// Set where the syscalls traced by [Runctl::TraceSyscalls] go.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use hakoniwa::{Container, Runctl, TraceEvent, TraceSink};
+    ///
+    /// let mut container = Container::new();
+    /// container
+    ///     .runctl(Runctl::TraceSyscalls)
+    ///     .trace_sink(TraceSink::handler(|event: &TraceEvent| {
+    ///         if event.errno().is_some() {
+    ///             eprintln!("{event}");
+    ///         }
+    ///     }));
+    /// ```
+    #[cfg(feature = "seccomp")]
+    pub fn trace_sink<T: Into<crate::TraceSink>>(&mut self, sink: T) -> &mut Self {
+        self.trace_sink = Some(sink.into());
         self
     }
 
@@ -444,6 +735,64 @@ impl Container {
             .collect()
     }
 
+    /// Returns the landlock ruleset, merged into the one generated from the
+    /// mounts if [Runctl::LandlockFromMounts] is set.
+    #[cfg(feature = "landlock")]
+    pub(crate) fn get_landlock_ruleset(&self) -> Option<crate::landlock::Ruleset> {
+        if !self.runctl.contains(&Runctl::LandlockFromMounts) {
+            return self.landlock_ruleset.clone();
+        }
+
+        let mut ruleset = crate::landlock::Ruleset::from_container(self);
+        if let Some(other) = &self.landlock_ruleset {
+            ruleset.merge(other);
+        }
+        Some(ruleset)
+    }
+
+    /// Returns an error if a [Runctl] is set which requires a feature not
+    /// enabled in this build.
+    pub(crate) fn check_runctl_features(&self) -> Result<()> {
+        #[cfg(not(feature = "landlock"))]
+        for runctl in &self.runctl {
+            if let Runctl::LandlockFromMounts | Runctl::LandlockRequire(_) = runctl {
+                let runctl = format!("{runctl:?}");
+                Err(ProcessErrorKind::FeatureNotEnabled(
+                    runctl,
+                    "landlock".to_string(),
+                ))?
+            }
+        }
+        #[cfg(not(feature = "seccomp"))]
+        for runctl in &self.runctl {
+            if let Runctl::RecordSyscalls | Runctl::RecordFsAccess | Runctl::TraceSyscalls = runctl
+            {
+                let runctl = format!("{runctl:?}");
+                Err(ProcessErrorKind::FeatureNotEnabled(
+                    runctl,
+                    "seccomp".to_string(),
+                ))?
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the capabilities kept in the bounding set, or None if the
+    /// capabilities are left as is.
+    pub(crate) fn get_capabilities(&self) -> Option<HashSet<Capability>> {
+        if !self.cap_drop_all && self.cap_drop.is_empty() && self.cap_add.is_empty() {
+            return None;
+        }
+
+        let mut caps = match self.cap_drop_all {
+            true => HashSet::new(),
+            false => caps::all(),
+        };
+        caps.retain(|cap| !self.cap_drop.contains(cap));
+        caps.extend(&self.cap_add);
+        Some(caps)
+    }
+
     /// Returns setup operations in bit flags.
     pub(crate) fn get_mainp_setup_operations(&self) -> u8 {
         let mut operations = 0;
@@ -476,10 +825,104 @@ impl Container {
         self.network.is_some()
     }
 
+    /// Compiles the seccomp filter once, the result is shared with the
+    /// commands created by this container.
+    ///
+    /// The filter is checked on every call, since [Runctl::SeccompStrict]
+    /// may be set after the filter was compiled.
+    #[cfg(feature = "seccomp")]
+    pub(crate) fn compile_seccomp_filter(&self) -> Result<()> {
+        let filter = match &self.seccomp_filter {
+            Some(filter) => filter,
+            None => return Ok(()),
+        };
+
+        let caps = self.get_capabilities();
+        let strict = self.runctl.contains(&Runctl::SeccompStrict);
+        if strict || log::log_enabled!(log::Level::Debug) {
+            let check = crate::seccomp::FilterCheck::new(filter, caps.as_ref());
+            for line in check.to_string().lines() {
+                log::debug!("Seccomp: {line}");
+            }
+            if strict && !check.is_ok() {
+                let err = check.errors().join(", ");
+                Err(Error::SeccompError(err))?
+            }
+        }
+
+        if self.seccomp_compiled.get().is_none() {
+            let compiled = crate::seccomp::CompiledFilter::compile(filter, caps.as_ref())?;
+            _ = self.seccomp_compiled.set(compiled);
+        }
+        Ok(())
+    }
+
+    /// Returns true if the container needs the main process to supervise
+    /// the seccomp user-notifications.
+    pub(crate) fn needs_mainp_seccomp_supervisor(&self) -> bool {
+        #[cfg(feature = "seccomp")]
+        return self.seccomp_filter.is_some() && self.seccomp_notifier.is_some();
+        #[cfg(not(feature = "seccomp"))]
+        return false;
+    }
+
+    /// Returns true if the container needs the internal process to report
+    /// the enforcement status of the landlock ruleset.
+    pub(crate) fn needs_childp_landlock_status(&self) -> bool {
+        #[cfg(feature = "landlock")]
+        return self
+            .get_landlock_ruleset()
+            .is_some_and(|ruleset| !ruleset.restrictions.is_empty());
+        #[cfg(not(feature = "landlock"))]
+        return false;
+    }
+
+    /// Returns true if the container needs the internal process to report
+    /// its capability sets.
+    pub(crate) fn needs_childp_capability_status(&self) -> bool {
+        self.get_capabilities().is_some()
+    }
+
     /// Returns true if the container needs the child process to stop
     /// the internal process at exit.
     pub(crate) fn needs_childp_traceexit(&self) -> bool {
         self.runctl.contains(&Runctl::GetProcPidSmapsRollup)
             || self.runctl.contains(&Runctl::GetProcPidStatus)
+            || self.needs_childp_tracesigsys()
+            || self.needs_childp_tracesyscalls()
+    }
+
+    /// Returns true if the container needs the child process to catch the
+    /// SIGSYS raised by the seccomp filter in the internal process and its
+    /// descendants.
+    pub(crate) fn needs_childp_tracesigsys(&self) -> bool {
+        #[cfg(feature = "seccomp")]
+        return self.runctl.contains(&Runctl::ReportBlockedSyscall)
+            && self
+                .seccomp_filter
+                .as_ref()
+                .is_some_and(|filter| filter.raises_sigsys());
+        #[cfg(not(feature = "seccomp"))]
+        return false;
+    }
+
+    /// Returns true if the container needs to trace every syscall of the
+    /// child process and its descendants.
+    pub(crate) fn needs_childp_tracesyscalls(&self) -> bool {
+        #[cfg(feature = "seccomp")]
+        return self.runctl.contains(&Runctl::RecordSyscalls)
+            || self.runctl.contains(&Runctl::TraceSyscalls)
+            || self.needs_childp_tracefs();
+        #[cfg(not(feature = "seccomp"))]
+        return false;
+    }
+
+    /// Returns true if the container needs to trace the filesystem syscalls
+    /// of the child process and its descendants.
+    pub(crate) fn needs_childp_tracefs(&self) -> bool {
+        #[cfg(feature = "seccomp")]
+        return self.runctl.contains(&Runctl::RecordFsAccess);
+        #[cfg(not(feature = "seccomp"))]
+        return false;
     }
 }
@@ -36,14 +36,22 @@ pub enum ProcessErrorKind {
     SetupUGidmapFailed(String),
     #[error("configure the new network namespace failed: {0}")]
     SetupNetworkFailed(String),
+    #[error("configure the cgroup failed: {0}")]
+    SetupCgroupFailed(String),
     #[error("child exit status gone")]
     ChildExitStatusGone,
+    #[error("{0} requires the `{1}` feature")]
+    FeatureNotEnabled(String, String),
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum UnErrorKind {
     #[error(transparent)]
     StdIoError(#[from] std::io::Error),
+    #[error("invalid overlay mount: {0}")]
+    InvalidOverlayMount(String),
+    #[error("invalid mount options: {0}")]
+    InvalidMountOptions(String),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -52,6 +60,8 @@ pub enum EtcfsErrorKind {
     InvalidLine { line: String, errmsg: String },
     #[error("not enough parts")]
     NotEnoughParts,
+    #[error("invalid name `{0}`")]
+    InvalidName(String),
     #[error(transparent)]
     StdIoError(#[from] std::io::Error),
     #[error(transparent)]
@@ -1,6 +1,8 @@
+mod envfile;
 mod group;
 mod passwd;
 mod typeparser;
 
+pub(crate) use envfile::EnvFile;
 pub(crate) use group::GroupFile;
 pub(crate) use passwd::PasswdFile;
@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::PathBuf;
+
+use super::typeparser::*;
+use crate::error::*;
+
+#[derive(Debug, Clone)]
+pub(crate) struct EnvEntry {
+    pub(crate) name: String,
+    pub(crate) value: String,
+}
+
+impl EnvEntry {
+    fn from_line(line: &str) -> Result<Self> {
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let mut parts = line.splitn(2, '=');
+        let name = to_string(parts.next())?.trim().to_string();
+        let value = to_string(parts.next())?.trim().to_string();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            Err(EtcfsErrorKind::InvalidName(name.clone()))?;
+        }
+        let value = Self::unquote(&value);
+        Ok(Self { name, value })
+    }
+
+    // Single-quoted values are taken literally, double-quoted values support
+    // the `\n`, `\"` and `\\` escapes, and unquoted values end at ` #`.
+    fn unquote(value: &str) -> String {
+        if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+            return value[1..value.len() - 1].to_string();
+        }
+
+        if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            let mut unquoted = String::new();
+            let mut chars = value[1..value.len() - 1].chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('\\', Some('n')) => unquoted.push('\n'),
+                    ('\\', Some(c @ ('"' | '\\'))) => unquoted.push(c),
+                    _ => {
+                        unquoted.push(c);
+                        continue;
+                    }
+                }
+                chars.next();
+            }
+            return unquoted;
+        }
+
+        match value.find(" #") {
+            Some(pos) => value[..pos].trim_end().to_string(),
+            None => value.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct EnvFile {
+    path: PathBuf,
+}
+
+impl EnvFile {
+    pub(crate) fn new(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        Self { path }
+    }
+
+    pub(crate) fn entries(&self) -> Result<Vec<EnvEntry>> {
+        let mut entries = vec![];
+        let content = fs::read_to_string(&self.path).map_err(EtcfsErrorKind::StdIoError)?;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            entries.push(
+                EnvEntry::from_line(line).map_err(|err| EtcfsErrorKind::InvalidLine {
+                    line: line.chars().take(8).collect(),
+                    errmsg: err.to_string(),
+                })?,
+            );
+        }
+        Ok(entries)
+    }
+}
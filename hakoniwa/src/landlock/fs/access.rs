@@ -1,44 +1,140 @@
 bitflags::bitflags! {
     /// FS access flags.
-    #[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+    ///
+    /// The fine-grained flags map one-to-one to the [Landlock access rights]
+    /// for files and directories, `R`, `W` and `X` are shorthands for a set
+    /// of them. Rights not supported by the running kernel are ignored.
+    ///
+    /// [Landlock access rights]: https://docs.kernel.org/userspace-api/landlock.html#filesystem-flags
+    #[derive(serde::Serialize, serde::Deserialize, Hash, Eq, PartialEq, Clone, Copy, Debug)]
     pub struct Access: u64 {
-        const R = 1;
-        const W = 1 << 1;
-        const X = 1 << 2;
+        /// Execute a file.
+        const EXECUTE = 1;
+        /// Open a file with write access.
+        const WRITE_FILE = 1 << 1;
+        /// Open a file with read access.
+        const READ_FILE = 1 << 2;
+        /// Open a directory or list its content.
+        const READ_DIR = 1 << 3;
+        /// Remove an empty directory or rename one.
+        const REMOVE_DIR = 1 << 4;
+        /// Unlink (or rename) a file.
+        const REMOVE_FILE = 1 << 5;
+        /// Create (or rename or link) a character device.
+        const MAKE_CHAR = 1 << 6;
+        /// Create (or rename) a directory.
+        const MAKE_DIR = 1 << 7;
+        /// Create (or rename or link) a regular file.
+        const MAKE_REG = 1 << 8;
+        /// Create (or rename or link) a UNIX domain socket.
+        const MAKE_SOCK = 1 << 9;
+        /// Create (or rename or link) a named pipe.
+        const MAKE_FIFO = 1 << 10;
+        /// Create (or rename or link) a block device.
+        const MAKE_BLOCK = 1 << 11;
+        /// Create (or rename or link) a symbolic link.
+        const MAKE_SYM = 1 << 12;
+        /// Link or rename a file from or to a different directory, since ABI v2.
+        const REFER = 1 << 13;
+        /// Truncate a file, since ABI v3.
+        const TRUNCATE = 1 << 14;
+        /// Invoke `ioctl(2)` on an opened character or block device, since ABI v5.
+        const IOCTL_DEV = 1 << 15;
+
+        /// Read files and list directories.
+        const R = Self::READ_FILE.bits() | Self::READ_DIR.bits();
+        /// Write and truncate files, create, remove and rename entries.
+        const W = Self::WRITE_FILE.bits()
+            | Self::REMOVE_DIR.bits()
+            | Self::REMOVE_FILE.bits()
+            | Self::MAKE_CHAR.bits()
+            | Self::MAKE_DIR.bits()
+            | Self::MAKE_REG.bits()
+            | Self::MAKE_SOCK.bits()
+            | Self::MAKE_FIFO.bits()
+            | Self::MAKE_BLOCK.bits()
+            | Self::MAKE_SYM.bits()
+            | Self::REFER.bits()
+            | Self::TRUNCATE.bits()
+            | Self::IOCTL_DEV.bits();
+        /// Execute files.
+        const X = Self::EXECUTE.bits();
     }
 }
 
+const NAMES: &[(Access, &str)] = &[
+    (Access::EXECUTE, "execute"),
+    (Access::WRITE_FILE, "write_file"),
+    (Access::READ_FILE, "read_file"),
+    (Access::READ_DIR, "read_dir"),
+    (Access::REMOVE_DIR, "remove_dir"),
+    (Access::REMOVE_FILE, "remove_file"),
+    (Access::MAKE_CHAR, "make_char"),
+    (Access::MAKE_DIR, "make_dir"),
+    (Access::MAKE_REG, "make_reg"),
+    (Access::MAKE_SOCK, "make_sock"),
+    (Access::MAKE_FIFO, "make_fifo"),
+    (Access::MAKE_BLOCK, "make_block"),
+    (Access::MAKE_SYM, "make_sym"),
+    (Access::REFER, "refer"),
+    (Access::TRUNCATE, "truncate"),
+    (Access::IOCTL_DEV, "ioctl_dev"),
+];
+
 impl std::fmt::Display for Access {
+    /// Formats the access as `rwx`, followed by the names of the flags not
+    /// covered by the shorthands, e.g. `r--`, `r--,make_reg`, `read_file`.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut shorthands = Self::empty();
         let mut str = "".to_string();
-        for e in [Self::R, Self::W, Self::X] {
-            str.push(match *self & e {
-                Self::R => 'r',
-                Self::W => 'w',
-                Self::X => 'x',
-                _ => '-',
-            });
+        for (e, chr) in [(Self::R, 'r'), (Self::W, 'w'), (Self::X, 'x')] {
+            if self.contains(e) {
+                shorthands |= e;
+                str.push(chr);
+            } else {
+                str.push('-');
+            }
+        }
+
+        let mut parts = vec![];
+        if self.is_empty() || !shorthands.is_empty() {
+            parts.push(str);
+        }
+        for (e, name) in NAMES {
+            if self.contains(*e) && !shorthands.contains(*e) {
+                parts.push(name.to_string());
+            }
         }
-        write!(f, "{str}")
+        write!(f, "{}", parts.join(","))
     }
 }
 
 impl std::str::FromStr for Access {
     type Err = crate::Error;
 
+    /// Parses a comma-separated list of `rwx` shorthands and flag names,
+    /// e.g. `r-x`, `r--,make_reg,remove_file`.
     fn from_str(str: &str) -> Result<Self, Self::Err> {
         let mut access = Self::empty();
-        for e in str.chars().collect::<Vec<char>>() {
-            match e.to_lowercase().to_string().as_ref() {
-                "r" => access |= Self::R,
-                "w" => access |= Self::W,
-                "x" => access |= Self::X,
-                "-" => (),
-                chr => {
-                    let err = format!("unknown access {chr:?}");
-                    Err(Self::Err::LandlockError(err))?
-                }
-            };
+        for part in str.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let part = part.to_lowercase();
+            if let Some((e, _)) = NAMES.iter().find(|(_, name)| *name == part) {
+                access |= *e;
+                continue;
+            }
+
+            for e in part.chars().collect::<Vec<char>>() {
+                match e.to_string().as_ref() {
+                    "r" => access |= Self::R,
+                    "w" => access |= Self::W,
+                    "x" => access |= Self::X,
+                    "-" => (),
+                    chr => {
+                        let err = format!("unknown access {chr:?}");
+                        Err(Self::Err::LandlockError(err))?
+                    }
+                };
+            }
         }
         Ok(access)
     }
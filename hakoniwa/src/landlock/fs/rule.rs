@@ -5,6 +5,18 @@ pub struct Rule {
     pub(crate) mode: super::Access,
 }
 
+impl Rule {
+    /// Returns the path of the rule.
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the access of the rule.
+    pub fn get_mode(&self) -> super::Access {
+        self.mode
+    }
+}
+
 impl std::fmt::Display for Rule {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} {}", self.mode, self.path)
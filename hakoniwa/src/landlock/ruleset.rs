@@ -1,15 +1,27 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
 use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 
 use super::{FsAccess, FsRule, NetAccess, NetRule};
+use crate::error::Result;
+use crate::unshare::{FsOperation, MountOptions};
+use crate::{Container, RecordedFsAccess, Runctl};
+
+// Directories with the same access are merged into their parent when there
+// are at least this many of them, keep the doc of from_recorded_fs_accesses
+// in sync.
+const MERGE_THRESHOLD: usize = 4;
 
 /// Resource type.
 #[allow(non_camel_case_types)]
-#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
 pub enum Resource {
     FS,
     NET_TCP_BIND,
     NET_TCP_CONNECT,
+    SCOPE_ABSTRACT_UNIX_SOCKET,
+    SCOPE_SIGNAL,
 }
 
 impl std::fmt::Display for Resource {
@@ -18,6 +30,8 @@ impl std::fmt::Display for Resource {
             Self::FS => "fs",
             Self::NET_TCP_BIND => "tcp.bind",
             Self::NET_TCP_CONNECT => "tcp.connect",
+            Self::SCOPE_ABSTRACT_UNIX_SOCKET => "scope.abstract",
+            Self::SCOPE_SIGNAL => "scope.signal",
         };
         write!(f, "{r}")
     }
@@ -39,6 +53,154 @@ pub struct Ruleset {
 }
 
 impl Ruleset {
+    /// Creates a ruleset which restricts the filesystem to the directories
+    /// recorded with [Runctl::RecordFsAccess].
+    ///
+    /// When at least 4 directories with the same access share a parent,
+    /// except `/`, they are merged into the parent, so the ruleset may grant
+    /// access to more directories than recorded. Rules already covered by
+    /// an ancestor are dropped.
+    ///
+    /// [Runctl::RecordFsAccess]: crate::Runctl::RecordFsAccess
+    pub fn from_recorded_fs_accesses(accesses: &[RecordedFsAccess]) -> Result<Self> {
+        let mut rules = BTreeMap::new();
+        for e in accesses {
+            let access = e.access.parse::<FsAccess>()?;
+            *rules.entry(e.path.clone()).or_insert(FsAccess::empty()) |= access;
+        }
+
+        // Merge siblings with the same access into their parent.
+        loop {
+            let mut siblings: BTreeMap<(String, u64), Vec<String>> = BTreeMap::new();
+            for (path, access) in &rules {
+                if let Some(parent) = Path::new(path).parent()
+                    && parent != Path::new("/")
+                {
+                    let parent = parent.to_string_lossy().to_string();
+                    siblings
+                        .entry((parent, access.bits()))
+                        .or_default()
+                        .push(path.clone());
+                }
+            }
+
+            let merged = siblings
+                .into_iter()
+                .filter(|(_, paths)| paths.len() >= MERGE_THRESHOLD)
+                .collect::<Vec<_>>();
+            if merged.is_empty() {
+                break;
+            }
+            for ((parent, access), paths) in merged {
+                for path in paths {
+                    rules.remove(&path);
+                }
+                let access = FsAccess::from_bits_truncate(access);
+                *rules.entry(parent).or_insert(FsAccess::empty()) |= access;
+            }
+        }
+
+        // Drop rules covered by an ancestor.
+        let covered = rules
+            .iter()
+            .filter(|(path, access)| {
+                Path::new(path).ancestors().skip(1).any(|ancestor| {
+                    let ancestor = ancestor.to_string_lossy();
+                    rules
+                        .get(ancestor.as_ref())
+                        .is_some_and(|a| a.contains(**access))
+                })
+            })
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        for path in covered {
+            rules.remove(&path);
+        }
+
+        let mut ruleset = Self::default();
+        ruleset.restrict(Resource::FS, CompatMode::Enforce);
+        for (path, access) in rules {
+            ruleset.add_fs_rule(&path, access);
+        }
+        Ok(ruleset)
+    }
+
+    /// Creates a ruleset which restricts the filesystem to the mounts and
+    /// the files created in the container, so the landlock domain matches
+    /// the MOUNT namespace.
+    ///
+    /// - read-only bind mounts get `r-x`
+    /// - read-write bind mounts, overlayfs, tmpfs and procfs get `rw-`
+    /// - devfs gets `r--,write_file,ioctl_dev`, and its `shm` dir gets `rw-`
+    ///   for the POSIX shared memory
+    /// - files and dirs created with [Container::file] and [Container::dir]
+    ///   get `r--`
+    /// - the root dir set by [Container::rootdir] gets `r-x`, or `rwx` with
+    ///   [Runctl::RootdirRW]
+    ///
+    /// Execute access is not granted on mounts with [MountOptions::NOEXEC].
+    pub fn from_container(container: &Container) -> Self {
+        let mut ruleset = Self::default();
+        ruleset.restrict(Resource::FS, CompatMode::Enforce);
+
+        if container.rootdir.is_some() {
+            let mut access = FsAccess::R | FsAccess::X;
+            if container.rootdir_overlay || container.runctl.contains(&Runctl::RootdirRW) {
+                access |= FsAccess::W;
+            }
+            ruleset.add_fs_rule("/", access);
+        }
+
+        for mount in container.get_mounts() {
+            let mut access = match mount.fstype.as_ref() {
+                "devfs" => FsAccess::R | FsAccess::WRITE_FILE | FsAccess::IOCTL_DEV,
+                "tmpfs" | "proc" | "overlay" => FsAccess::R | FsAccess::W,
+                _ if mount.options.contains(MountOptions::RDONLY) => FsAccess::R | FsAccess::X,
+                _ => FsAccess::R | FsAccess::W,
+            };
+            if mount.options.contains(MountOptions::NOEXEC) {
+                access.remove(FsAccess::X);
+            }
+            ruleset.add_fs_rule(&mount.target, access);
+            if mount.fstype == "devfs" {
+                let shm = format!("{}/shm", mount.target.trim_end_matches('/'));
+                ruleset.add_fs_rule(&shm, FsAccess::R | FsAccess::W);
+            }
+        }
+
+        // Symbolic links are resolved to their targets, which are covered
+        // by the rules above.
+        for op in container.get_fs_operations() {
+            let target = match op {
+                FsOperation::WriteFile(file) => &file.target,
+                FsOperation::MakeDir(dir) => &dir.target,
+                FsOperation::MakeSymlink(_) => continue,
+            };
+            ruleset.add_fs_rule(target, FsAccess::R);
+        }
+        ruleset
+    }
+
+    /// Merges the restrictions and rules of `other` into this ruleset, the
+    /// accesses of the FS rules on the same path are combined.
+    pub(crate) fn merge(&mut self, other: &Self) -> &mut Self {
+        self.restrictions.extend(&other.restrictions);
+        for rule in other.fs_rules.values() {
+            let access = match self.fs_rules.get(&rule.path) {
+                Some(e) => e.mode | rule.mode,
+                None => rule.mode,
+            };
+            self.add_fs_rule(&rule.path, access);
+        }
+        for (resource, rules) in &other.net_rules {
+            self.net_rules
+                .entry(*resource)
+                .or_default()
+                .extend(rules.iter().cloned());
+        }
+        self
+    }
+
     /// DONOT Impose restrictions on resource.
     #[doc(hidden)]
     pub fn unrestrict(&mut self, resource: Resource) -> &mut Self {
@@ -87,7 +249,8 @@ impl Ruleset {
     }
 
     /// Returns a list of fs rules.
-    pub(crate) fn get_fs_rules(&self) -> Vec<&FsRule> {
+    #[doc(hidden)]
+    pub fn get_fs_rules(&self) -> Vec<&FsRule> {
         let mut values: Vec<_> = self.fs_rules.values().collect();
         values.sort_by(|a, b| a.path.cmp(&b.path));
         values
@@ -23,30 +23,46 @@
 //! ```
 //! More details can be found in [repo](https://github.com/souk4711/hakoniwa/tree/main/hakoniwa).
 
+#[cfg(feature = "tokio")]
+mod async_child;
+mod cgroup;
 mod child;
 mod command;
 mod container;
 mod error;
 mod etcfs;
 mod metric;
+mod pidfd;
 mod rlimit;
 mod runc;
 mod runctl;
+mod scm_rights;
 mod stdio;
+#[cfg(feature = "seccomp")]
+mod trace;
 mod unshare;
 
-use etcfs::{GroupFile, PasswdFile};
+use etcfs::{EnvFile, GroupFile, PasswdFile};
 use unshare::{FsOperation, IdMap, Mount};
 
-pub use child::{Child, ExitStatus, Output};
+#[cfg(feature = "tokio")]
+pub use async_child::AsyncChild;
+pub use caps::Capability;
+pub use cgroup::Cgroup;
+pub use child::{
+    BlockedSyscall, CapabilityStatus, Child, ExitStatus, LandlockRulesetStatus, LandlockStatus,
+    Output, RecordedFsAccess, RecordedSyscall,
+};
 pub use command::Command;
 pub use container::Container;
 pub use error::{Error, Result};
-pub use metric::{ProcPidSmapsRollup, ProcPidStatus, Rusage};
+pub use metric::{CgroupStats, ProcPidSmapsRollup, ProcPidStatus, Rusage};
 pub use rlimit::Rlimit;
 pub use runctl::Runctl;
 pub use stdio::Stdio;
-pub use unshare::{MountOptions, Namespace, Network, Pasta};
+#[cfg(feature = "seccomp")]
+pub use trace::{TraceEvent, TraceHandler, TraceSink};
+pub use unshare::{MountOptions, Namespace, NamespaceRef, Network, Pasta};
 
 #[cfg(feature = "landlock")]
 pub mod landlock;
@@ -1,7 +1,9 @@
+mod cgroup_stats;
 mod proc_pid_smaps_rollup;
 mod proc_pid_status;
 mod rusage;
 
+pub use cgroup_stats::CgroupStats;
 pub use proc_pid_smaps_rollup::ProcPidSmapsRollup;
 pub use proc_pid_status::ProcPidStatus;
 pub use rusage::Rusage;
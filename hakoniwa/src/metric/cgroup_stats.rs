@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Peak/usage counters of the cgroup, 0 if the controller is not enabled
+/// or the counter is not supported by the kernel.
+///
+/// [cgroup-v2]: https://docs.kernel.org/admin-guide/cgroup-v2.html
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CgroupStats {
+    /// Max memory usage in bytes, aka `memory.peak`.
+    pub memory_peak: u64,
+
+    /// Max number of processes, aka `pids.peak`.
+    pub pids_peak: u64,
+
+    /// Total CPU time in microseconds, aka `usage_usec` in `cpu.stat`.
+    pub cpu_usage_usec: u64,
+
+    /// User CPU time in microseconds, aka `user_usec` in `cpu.stat`.
+    pub cpu_user_usec: u64,
+
+    /// System CPU time in microseconds, aka `system_usec` in `cpu.stat`.
+    pub cpu_system_usec: u64,
+
+    /// Number of processes killed by the OOM killer, aka `oom_kill` in `memory.events`.
+    pub oom_kill: u64,
+}
@@ -0,0 +1,35 @@
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+
+/// Obtains a file descriptor that refers to the process `pid`, aka [pidfd_open].
+///
+/// [pidfd_open]: https://man7.org/linux/man-pages/man2/pidfd_open.2.html
+pub(crate) fn open(pid: Pid) -> io::Result<OwnedFd> {
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid.as_raw(), 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+/// Sends the signal `signal` to the process referred to by `pidfd`, aka
+/// [pidfd_send_signal].
+///
+/// [pidfd_send_signal]: https://man7.org/linux/man-pages/man2/pidfd_send_signal.2.html
+pub(crate) fn send_signal(pidfd: BorrowedFd<'_>, signal: Signal) -> io::Result<()> {
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_pidfd_send_signal,
+            pidfd.as_raw_fd(),
+            signal as libc::c_int,
+            std::ptr::null::<libc::siginfo_t>(),
+            0,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
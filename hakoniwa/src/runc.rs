@@ -1,25 +1,39 @@
+mod capability;
+mod cgroup;
 mod error;
+mod pty;
 mod rlimit;
 mod sys;
-mod timeout;
 mod unshare;
+mod waiter;
 
 #[cfg(feature = "landlock")]
 mod landlock;
 
+#[cfg(feature = "seccomp")]
+mod recorder;
 #[cfg(feature = "seccomp")]
 mod seccomp;
+#[cfg(feature = "seccomp")]
+mod tracer;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
 use std::io::prelude::*;
 use std::io::{PipeReader, PipeWriter};
+use std::os::fd::OwnedFd;
+use std::os::unix::net::UnixStream;
 use std::process;
 use std::time::Instant;
 
 use crate::runc::error::*;
+use crate::runc::pty::PtySlave;
 use crate::runc::sys::{ForkResult, Pid, PtraceEvent, Signal, UsageWho, WaitStatus};
-use crate::{Command, Container, ExitStatus, ProcPidSmapsRollup, ProcPidStatus, Runctl, Rusage};
+use crate::runc::waiter::Waiter;
+use crate::{
+    BlockedSyscall, Command, Container, ExitStatus, ProcPidSmapsRollup, ProcPidStatus, Runctl,
+    Rusage,
+};
 
 macro_rules! process_exit {
     ($err:ident) => {{
@@ -35,14 +49,17 @@ pub(crate) const FIN: u8 = 0;
 pub(crate) const SETUP_UGIDMAP: u8 = 1;
 pub(crate) const SETUP_NETWORK: u8 = 1 << 1;
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn exec(
     command: &Command,
     container: &Container,
-    mut stdin: Option<PipeReader>,
-    mut stdout: Option<PipeWriter>,
-    mut stderr: Option<PipeWriter>,
+    mut stdin: Option<OwnedFd>,
+    mut stdout: Option<OwnedFd>,
+    mut stderr: Option<OwnedFd>,
     mut reader: PipeReader,
     mut writer: PipeWriter,
+    mut socket: Option<UnixStream>,
+    mut tracer: Option<PipeWriter>,
 ) {
     let status = match exec_imp(
         command,
@@ -52,6 +69,8 @@ pub(crate) fn exec(
         &mut stderr,
         &mut reader,
         &mut writer,
+        &mut socket,
+        &mut tracer,
     ) {
         Ok(val) => val,
         Err(err) => ExitStatus::new_failure(&err.to_string()),
@@ -78,14 +97,17 @@ pub(crate) fn exec(
     process::exit(status.code);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn exec_imp(
     command: &Command,
     container: &Container,
-    stdin: &mut Option<PipeReader>,
-    stdout: &mut Option<PipeWriter>,
-    stderr: &mut Option<PipeWriter>,
+    stdin: &mut Option<OwnedFd>,
+    stdout: &mut Option<OwnedFd>,
+    stderr: &mut Option<OwnedFd>,
     reader: &mut PipeReader,
     writer: &mut PipeWriter,
+    socket: &mut Option<UnixStream>,
+    tracer: &mut Option<PipeWriter>,
 ) -> Result<ExitStatus> {
     // Redirect standard I/O stream.
     if let Some(stdin) = stdin.take() {
@@ -104,6 +126,12 @@ fn exec_imp(
     // Die with parent.
     sys::set_pdeathsig(Signal::SIGKILL)?;
 
+    // Move into the cgroup, the internal process inherits it.
+    cgroup::enter(container)?;
+
+    // Create id-mapped mounts before entering the new USER namespace.
+    let idmapped = unshare::idmapped(container)?;
+
     // Unshare namespaces, setup [ug]idmap.
     unshare::newuser(container)?;
 
@@ -121,30 +149,115 @@ fn exec_imp(
         }
     }
 
+    // Open the procfs before mounting rootfs, it is used to inspect the
+    // tracees when recording filesystem accesses or reporting the blocked
+    // syscall.
+    let procfs = match container.needs_childp_tracefs() || container.needs_childp_tracesigsys() {
+        true => Some(sys::open_rdonly("/proc")?),
+        false => None,
+    };
+
     // Mount rootfs.
-    unshare::newns(container)?;
+    unshare::newns(container, idmapped)?;
+
+    // Allocate a pseudo-terminal, the master is sent to the main process.
+    let socket = socket.take();
+    let pty = match &socket {
+        Some(socket) if container.pty => Some(pty::openpty(container, socket)?),
+        _ => None,
+    };
+
+    // Open a pipe, the internal process reports the enforcement status of
+    // the landlock ruleset through it.
+    let (landlock_reader, landlock_writer) = match container.needs_childp_landlock_status() {
+        true => {
+            let (reader, writer) = std::io::pipe()?;
+            (Some(reader), Some(writer))
+        }
+        false => (None, None),
+    };
+
+    // Open a pipe, the internal process reports its capability sets through
+    // it.
+    let (capability_reader, capability_writer) = match container.needs_childp_capability_status() {
+        true => {
+            let (reader, writer) = std::io::pipe()?;
+            (Some(reader), Some(writer))
+        }
+        false => (None, None),
+    };
 
     // Fork the specified program as a child process rather than running it
     // directly. This is useful when creating a new PID namespace.
     match sys::fork()? {
-        ForkResult::Parent { child, .. } => reap(child, command, container),
-        ForkResult::Child => match spawn(command, container) {
-            Ok(_) => unreachable!("runc::exec_imp"),
-            Err(err) => process_exit!(err),
-        },
+        ForkResult::Parent { child, .. } => {
+            drop(pty);
+            drop(socket);
+            drop(landlock_writer);
+            drop(capability_writer);
+            reap(
+                child,
+                command,
+                container,
+                procfs,
+                tracer.take(),
+                landlock_reader,
+                capability_reader,
+            )
+        }
+        ForkResult::Child => {
+            drop(landlock_reader);
+            drop(capability_reader);
+            let pipes = (landlock_writer, capability_writer);
+            match spawn(command, container, pty, socket, pipes) {
+                Ok(_) => unreachable!("runc::exec_imp"),
+                Err(err) => process_exit!(err),
+            }
+        }
     }
 }
 
-fn reap(child: Pid, command: &Command, container: &Container) -> Result<ExitStatus> {
-    // Set PTRACE_O_TRACEEXIT option for the internal process.
+#[cfg_attr(not(feature = "seccomp"), allow(unused_variables))]
+fn reap(
+    child: Pid,
+    command: &Command,
+    container: &Container,
+    procfs: Option<OwnedFd>,
+    tracer: Option<PipeWriter>,
+    landlock: Option<PipeReader>,
+    capability: Option<PipeReader>,
+) -> Result<ExitStatus> {
+    let mut waiter = Waiter::new(child)?;
+
+    // Resume a tracee, stop it at the next syscall when recording syscalls.
+    let tracesyscalls = container.needs_childp_tracesyscalls();
+    let resume = |pid, signal| match tracesyscalls {
+        true => sys::ptrace_syscall(pid, signal),
+        false => sys::ptrace_cont(pid, signal),
+    };
+
+    // Set PTRACE_O_TRACEEXIT option for the internal process. When recording
+    // syscalls or reporting the blocked syscall, its descendants are traced
+    // as well.
+    let tracesigsys = container.needs_childp_tracesigsys();
     if container.needs_childp_traceexit() {
-        let ws = sys::waitpid(child)?;
+        let ws = waiter.wait()?;
         match ws {
             WaitStatus::Exited(..) => return Ok(ExitStatus::from_wait_status(&ws, command)),
             WaitStatus::Signaled(..) => return Ok(ExitStatus::from_wait_status(&ws, command)),
+            WaitStatus::Stopped(pid, Signal::SIGSTOP) if pid == child && tracesyscalls => {
+                sys::ptrace_tracesyscalls(pid)?;
+                waiter.descendants();
+                resume(pid, None)?;
+            }
+            WaitStatus::Stopped(pid, Signal::SIGSTOP) if pid == child && tracesigsys => {
+                sys::ptrace_tracedescendants(pid)?;
+                waiter.descendants();
+                resume(pid, None)?;
+            }
             WaitStatus::Stopped(pid, Signal::SIGSTOP) if pid == child => {
                 sys::ptrace_traceexit(pid)?;
-                sys::ptrace_cont(pid, None)?;
+                resume(pid, None)?;
             }
             _ => return Ok(ExitStatus::new_failure(&format!("waitpid(..) => {ws:?}"))),
         }
@@ -152,25 +265,84 @@ fn reap(child: Pid, command: &Command, container: &Container) -> Result<ExitStat
 
     // Set a time limit for the internal process.
     if let Some(timeout) = command.wait_timeout {
-        timeout::timeout(child, timeout)?;
+        waiter.timeout(timeout);
     }
 
     // Wait for the internal process to finish.
     let mut proc_pid_smaps_rollup = None;
     let mut proc_pid_status = None;
+    let mut blocked_syscall = None;
+    let mut sigsys_fatal = false;
+    #[cfg(feature = "seccomp")]
+    let mut recorder = {
+        let procfs = procfs.as_ref().map(OwnedFd::try_clone).transpose()?;
+        recorder::Recorder::new(container, procfs)
+    };
+    #[cfg(feature = "seccomp")]
+    let mut tracer = tracer.map(tracer::Tracer::new);
+    let mut tracees = HashSet::from([child]);
     let started_at = Instant::now();
     let status = loop {
-        let ws = sys::waitpid(child)?;
+        let ws = waiter.wait()?;
         match ws {
-            WaitStatus::Exited(..) => break ExitStatus::from_wait_status(&ws, command),
-            WaitStatus::Signaled(..) => break ExitStatus::from_wait_status(&ws, command),
+            WaitStatus::Exited(pid, ..) if pid == child => {
+                break ExitStatus::from_wait_status(&ws, command);
+            }
+            WaitStatus::Signaled(pid, Signal::SIGKILL, _) if pid == child && sigsys_fatal => {
+                let ws = WaitStatus::Signaled(pid, Signal::SIGSYS, false);
+                break ExitStatus::from_wait_status(&ws, command);
+            }
+            WaitStatus::Signaled(pid, ..) if pid == child => {
+                break ExitStatus::from_wait_status(&ws, command);
+            }
+            WaitStatus::Exited(pid, ..) | WaitStatus::Signaled(pid, ..) => {
+                #[cfg(feature = "seccomp")]
+                if let Some(tracer) = &mut tracer {
+                    tracer.exited(pid);
+                }
+                tracees.remove(&pid);
+            }
             WaitStatus::PtraceEvent(pid, Signal::SIGTRAP, PTRACE_EVENT_EXIT) if pid == child => {
                 proc_pid_smaps_rollup = reap_proc_smaps_rollup(pid, container)?;
                 proc_pid_status = reap_proc_status(pid, container)?;
-                sys::ptrace_cont(pid, None)?
+                blocked_syscall = blocked_syscall.or_else(|| reap_blocked_syscall(pid, &procfs));
+                resume(pid, None)?
+            }
+            WaitStatus::PtraceEvent(pid, Signal::SIGTRAP, PTRACE_EVENT_EXIT) => {
+                blocked_syscall = blocked_syscall.or_else(|| reap_blocked_syscall(pid, &procfs));
+                resume(pid, None)?
+            }
+            WaitStatus::PtraceEvent(pid, ..) => resume(pid, None)?,
+            #[cfg(feature = "seccomp")]
+            WaitStatus::PtraceSyscall(pid) => {
+                if let Ok(info) = sys::ptrace_get_syscall_info(pid) {
+                    recorder.record(pid, &info);
+                    if let Some(tracer) = &mut tracer {
+                        tracer.trace(pid, &info);
+                    }
+                }
+                resume(pid, None)?
             }
-            WaitStatus::Stopped(pid, Signal::SIGTRAP) => sys::ptrace_cont(pid, None)?,
-            WaitStatus::Stopped(pid, signal) => sys::ptrace_cont(pid, Some(signal))?,
+            WaitStatus::Stopped(pid, Signal::SIGSYS) => {
+                let trapped = reap_trapped_syscall(pid, &procfs);
+                let fatal = trapped.as_ref().is_some_and(|(_, fatal)| *fatal);
+                if let Some((syscall, _)) = trapped {
+                    blocked_syscall.get_or_insert(syscall);
+                }
+                match fatal && pid == child {
+                    // The kernel drops a SIGSYS with the default action for a
+                    // traced container init, kill it like an untraced one.
+                    true => {
+                        sigsys_fatal = true;
+                        waiter.kill()?
+                    }
+                    false => resume(pid, Some(Signal::SIGSYS))?,
+                }
+            }
+            // A new tracee starts with SIGSTOP, which is not delivered.
+            WaitStatus::Stopped(pid, Signal::SIGSTOP) if tracees.insert(pid) => resume(pid, None)?,
+            WaitStatus::Stopped(pid, Signal::SIGTRAP) => resume(pid, None)?,
+            WaitStatus::Stopped(pid, signal) => resume(pid, Some(signal))?,
             _ => break ExitStatus::new_failure(&format!("waitpid(..) => {ws:?}")),
         };
     };
@@ -179,6 +351,24 @@ fn reap(child: Pid, command: &Command, container: &Container) -> Result<ExitStat
     let real_time = started_at.elapsed();
     let rusage = sys::getrusage(UsageWho::RUSAGE_CHILDREN)?;
 
+    // Get the recorded syscalls and filesystem accesses.
+    #[cfg(feature = "seccomp")]
+    let (recorded_syscalls, recorded_fs_accesses) = recorder.finish();
+    #[cfg(not(feature = "seccomp"))]
+    let (recorded_syscalls, recorded_fs_accesses) = (None, None);
+
+    // Get the enforcement status of the landlock ruleset.
+    #[cfg(feature = "landlock")]
+    let landlock_status = landlock::reap(landlock);
+    #[cfg(not(feature = "landlock"))]
+    let landlock_status = {
+        drop(landlock);
+        None
+    };
+
+    // Get the capability sets of the internal process.
+    let capability_status = capability::reap(capability);
+
     // Build the exit status of the internal process.
     Ok(ExitStatus {
         code: status.code,
@@ -187,6 +377,12 @@ fn reap(child: Pid, command: &Command, container: &Container) -> Result<ExitStat
         rusage: Rusage::from_nix_rusage(rusage, real_time),
         proc_pid_smaps_rollup,
         proc_pid_status,
+        cgroup_stats: None,
+        blocked_syscall,
+        recorded_syscalls,
+        recorded_fs_accesses,
+        landlock_status,
+        capability_status,
     })
 }
 
@@ -195,13 +391,7 @@ fn reap_proc_smaps_rollup(pid: Pid, container: &Container) -> Result<Option<Proc
         return Ok(None);
     }
 
-    let mount = container.get_mount_newproc();
-    let root = if let Some(mount) = mount {
-        format!("{}/1", mount.target)
-    } else {
-        format!("/proc/{pid}")
-    };
-
+    let root = proc_pid_root(pid, container);
     let process = procfs::process::Process::new_with_root(root.into())?;
     let smaps = process.smaps_rollup()?;
     Ok(ProcPidSmapsRollup::from_procfs_smaps_rollup(smaps))
@@ -212,22 +402,52 @@ fn reap_proc_status(pid: Pid, container: &Container) -> Result<Option<ProcPidSta
         return Ok(None);
     }
 
+    let root = proc_pid_root(pid, container);
+    let process = procfs::process::Process::new_with_root(root.into())?;
+    let status = process.status()?;
+    Ok(ProcPidStatus::from_procfs_status(status))
+}
+
+#[cfg_attr(not(feature = "seccomp"), allow(unused_variables))]
+fn reap_blocked_syscall(pid: Pid, procfs: &Option<OwnedFd>) -> Option<BlockedSyscall> {
+    #[cfg(feature = "seccomp")]
+    return seccomp::reap_killed_syscall(pid, procfs.as_ref()?);
+    #[cfg(not(feature = "seccomp"))]
+    return None;
+}
+
+#[cfg_attr(not(feature = "seccomp"), allow(unused_variables))]
+fn reap_trapped_syscall(pid: Pid, procfs: &Option<OwnedFd>) -> Option<(BlockedSyscall, bool)> {
+    #[cfg(feature = "seccomp")]
+    return seccomp::reap_trapped_syscall(pid, procfs.as_ref()?);
+    #[cfg(not(feature = "seccomp"))]
+    return None;
+}
+
+fn proc_pid_root(pid: Pid, container: &Container) -> String {
     let mount = container.get_mount_newproc();
-    let root = if let Some(mount) = mount {
+    if let Some(mount) = mount {
         format!("{}/1", mount.target)
     } else {
         format!("/proc/{pid}")
-    };
-
-    let process = procfs::process::Process::new_with_root(root.into())?;
-    let status = process.status()?;
-    Ok(ProcPidStatus::from_procfs_status(status))
+    }
 }
 
-fn spawn(command: &Command, container: &Container) -> Result<()> {
+fn spawn(
+    command: &Command,
+    container: &Container,
+    pty: Option<PtySlave>,
+    socket: Option<UnixStream>,
+    (landlock, mut capability): (Option<PipeWriter>, Option<PipeWriter>),
+) -> Result<()> {
     // Die with parent.
     sys::set_pdeathsig(Signal::SIGKILL)?;
 
+    // Set the controlling terminal.
+    if let Some(slave) = pty {
+        pty::login(slave)?;
+    }
+
     // Mount procfs, etc.
     unshare::tidyup(container)?;
 
@@ -248,18 +468,33 @@ fn spawn(command: &Command, container: &Container) -> Result<()> {
     // Set resource limit.
     rlimit::setrlimit(container)?;
 
+    // Drop capabilities before loading the landlock ruleset or seccomp
+    // filter, so that the filter does not need to allow capset(2) and
+    // prctl(2). With the no_new_privs bit set, neither of them requires the
+    // CAP_SYS_ADMIN capability.
+    let nnp = !container.runctl.contains(&Runctl::AllowNewPrivs);
+    if nnp {
+        sys::set_no_new_privs()?;
+        capability::apply(container, capability.take())?;
+    }
+
     // Restrict ambient rights (e.g. global filesystem access).
     #[cfg(feature = "landlock")]
-    landlock::load(container)?;
+    landlock::load(container, landlock)?;
+    #[cfg(not(feature = "landlock"))]
+    drop(landlock);
 
-    // Restrict syscalls.
+    // Restrict syscalls, the notification fd is sent to the main process.
     #[cfg(feature = "seccomp")]
-    seccomp::load(container)?;
-
-    // Set the no_new_privs bit.
+    seccomp::load(container, socket)?;
     #[cfg(not(feature = "seccomp"))]
-    if !container.runctl.contains(&Runctl::AllowNewPrivs) {
-        sys::set_no_new_privs()?
+    drop(socket);
+
+    // Drop capabilities. Without the no_new_privs bit, it is done at last,
+    // since loading the landlock ruleset or seccomp filter requires the
+    // CAP_SYS_ADMIN capability.
+    if !nnp {
+        capability::apply(container, capability.take())?;
     }
 
     // Execve.
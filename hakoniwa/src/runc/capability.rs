@@ -0,0 +1,91 @@
+use caps::{CapSet, Capability, CapsHashSet};
+use std::io::prelude::*;
+use std::io::{PipeReader, PipeWriter};
+
+use super::{error::*, sys};
+use crate::{CapabilityStatus, Container};
+
+// [securebits.h]: https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/securebits.h
+const SECBIT_NOROOT_LOCKED: libc::c_ulong = 1 << 1;
+const SECBIT_NO_SETUID_FIXUP_LOCKED: libc::c_ulong = 1 << 3;
+const SECBIT_KEEP_CAPS_LOCKED: libc::c_ulong = 1 << 5;
+const SECBIT_NO_CAP_AMBIENT_RAISE_LOCKED: libc::c_ulong = 1 << 7;
+
+// Drop/Add capabilities.
+//
+// [capabilities]: https://man7.org/linux/man-pages/man7/capabilities.7.html
+pub(crate) fn apply(container: &Container, writer: Option<PipeWriter>) -> Result<()> {
+    let caps = match container.get_capabilities() {
+        Some(caps) => caps,
+        None => return Ok(()),
+    };
+
+    // Lock the securebits with their current values when dropping
+    // capabilities, so they can not be regained, which requires the
+    // CAP_SETPCAP capability, as well as dropping the bounding set.
+    if container.cap_drop_all || !container.cap_drop.is_empty() {
+        sys::set_securebits(
+            sys::get_securebits()?
+                | SECBIT_NOROOT_LOCKED
+                | SECBIT_NO_SETUID_FIXUP_LOCKED
+                | SECBIT_KEEP_CAPS_LOCKED
+                | SECBIT_NO_CAP_AMBIENT_RAISE_LOCKED,
+        )?;
+    }
+    for cap in caps::read(None, CapSet::Bounding)? {
+        if !caps.contains(&cap) {
+            caps::drop(None, CapSet::Bounding, cap)?;
+        }
+    }
+
+    // Ambient capabilities must be both permitted and inheritable.
+    let permitted = caps::read(None, CapSet::Permitted)?;
+    let kept = caps
+        .intersection(&permitted)
+        .copied()
+        .collect::<CapsHashSet>();
+    let ambient = container
+        .cap_add
+        .intersection(&kept)
+        .copied()
+        .collect::<CapsHashSet>();
+    caps::set(None, CapSet::Inheritable, &ambient)?;
+    caps::set(None, CapSet::Effective, &kept)?;
+    caps::set(None, CapSet::Permitted, &kept)?;
+    caps::clear(None, CapSet::Ambient)?;
+    for cap in ambient {
+        caps::raise(None, CapSet::Ambient, cap)?;
+    }
+
+    // Report the final sets to the child process.
+    if let Some(mut writer) = writer {
+        let status = CapabilityStatus {
+            effective: read(CapSet::Effective)?,
+            permitted: read(CapSet::Permitted)?,
+            inheritable: read(CapSet::Inheritable)?,
+            bounding: read(CapSet::Bounding)?,
+            ambient: read(CapSet::Ambient)?,
+        };
+        let config = bincode::config::standard();
+        if let Ok(encoded) = bincode::serde::encode_to_vec(&status, config) {
+            writer.write_all(&encoded)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads the status reported by the internal process, after it exits.
+pub(crate) fn reap(reader: Option<PipeReader>) -> Option<CapabilityStatus> {
+    let mut encoded = vec![];
+    reader?.read_to_end(&mut encoded).ok()?;
+    let config = bincode::config::standard();
+    bincode::serde::decode_from_slice(&encoded, config)
+        .ok()
+        .map(|(status, _)| status)
+}
+
+fn read(set: CapSet) -> Result<Vec<Capability>> {
+    let mut caps = caps::read(None, set)?.into_iter().collect::<Vec<_>>();
+    caps.sort_by_key(|cap| cap.index());
+    Ok(caps)
+}
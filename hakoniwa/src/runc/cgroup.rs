@@ -0,0 +1,10 @@
+use super::error::*;
+use super::sys;
+use crate::Container;
+
+pub(crate) fn enter(container: &Container) -> Result<()> {
+    if let Some(dir) = &container.cgroupdir_abspath {
+        sys::fwrite(dir.join("cgroup.procs"), "0")?;
+    }
+    Ok(())
+}
@@ -19,6 +19,8 @@ pub(crate) enum Error {
     MountTargetPathMustBeAbsolute(String),
     #[error("mount procfs requires a new PID namespace")]
     MountProcfsEPERM,
+    #[error("id-mapped mount owner is not mapped in the USER namespace: {0}")]
+    IdmappedOwnerNotMapped(u32),
     #[error("{0}")]
     SetUserFailed(String),
     #[error(transparent)]
@@ -29,6 +31,9 @@ pub(crate) enum Error {
     #[error("landlock `{0}` feature requires minimum kernel version {1}: {2}")]
     LandlockFeatureUnsupported(String, String, String),
     #[cfg(feature = "landlock")]
+    #[error("landlock ruleset is {0}, but {1} is required")]
+    LandlockNotEnforced(String, String),
+    #[cfg(feature = "landlock")]
     #[error("landlock path must be exist: {0}")]
     LandlockPathMustBeAbsolute(String),
     #[cfg(feature = "landlock")]
@@ -37,7 +42,4 @@ pub(crate) enum Error {
     #[cfg(feature = "landlock")]
     #[error(transparent)]
     LandlockRulesetError(#[from] landlock::RulesetError),
-    #[cfg(feature = "seccomp")]
-    #[error(transparent)]
-    SeccompError(#[from] libseccomp::error::SeccompError),
 }
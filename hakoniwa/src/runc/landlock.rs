@@ -1,19 +1,73 @@
 use landlock::*;
+use std::io::prelude::*;
+use std::io::{PipeReader, PipeWriter};
 
-use super::error::*;
-use crate::{Container, Runctl, landlock as ll};
+use super::{error::*, sys};
+use crate::{Container, LandlockRulesetStatus, LandlockStatus, Runctl, landlock as ll};
 
-pub(crate) fn load(container: &Container) -> Result<()> {
+const FS_ACCESSES: [(ll::FsAccess, AccessFs); 16] = [
+    (ll::FsAccess::EXECUTE, AccessFs::Execute),
+    (ll::FsAccess::WRITE_FILE, AccessFs::WriteFile),
+    (ll::FsAccess::READ_FILE, AccessFs::ReadFile),
+    (ll::FsAccess::READ_DIR, AccessFs::ReadDir),
+    (ll::FsAccess::REMOVE_DIR, AccessFs::RemoveDir),
+    (ll::FsAccess::REMOVE_FILE, AccessFs::RemoveFile),
+    (ll::FsAccess::MAKE_CHAR, AccessFs::MakeChar),
+    (ll::FsAccess::MAKE_DIR, AccessFs::MakeDir),
+    (ll::FsAccess::MAKE_REG, AccessFs::MakeReg),
+    (ll::FsAccess::MAKE_SOCK, AccessFs::MakeSock),
+    (ll::FsAccess::MAKE_FIFO, AccessFs::MakeFifo),
+    (ll::FsAccess::MAKE_BLOCK, AccessFs::MakeBlock),
+    (ll::FsAccess::MAKE_SYM, AccessFs::MakeSym),
+    (ll::FsAccess::REFER, AccessFs::Refer),
+    (ll::FsAccess::TRUNCATE, AccessFs::Truncate),
+    (ll::FsAccess::IOCTL_DEV, AccessFs::IoctlDev),
+];
+
+pub(crate) fn load(container: &Container, writer: Option<PipeWriter>) -> Result<()> {
     let nnp = !container.runctl.contains(&Runctl::AllowNewPrivs);
-    match &container.landlock_ruleset {
-        Some(ruleset) => load_imp(ruleset, nnp),
-        None => Ok(()),
+    let ruleset = container.get_landlock_ruleset().unwrap_or_default();
+    let status = match load_imp(&ruleset, nnp)? {
+        Some(status) => translate_restriction_status(&ruleset, status),
+        None => return check_required(container, LandlockRulesetStatus::NotEnforced),
+    };
+
+    // Report the status to the child process first, so it is available even
+    // if it does not meet the required level.
+    if let Some(mut writer) = writer {
+        let config = bincode::config::standard();
+        if let Ok(encoded) = bincode::serde::encode_to_vec(&status, config) {
+            writer.write_all(&encoded)?;
+        }
+    }
+    check_required(container, status.ruleset)
+}
+
+fn check_required(container: &Container, status: LandlockRulesetStatus) -> Result<()> {
+    for runctl in &container.runctl {
+        if let Runctl::LandlockRequire(required) = runctl
+            && status < *required
+        {
+            let (status, required) = (status.to_string(), required.to_string());
+            Err(Error::LandlockNotEnforced(status, required))?
+        }
     }
+    Ok(())
 }
 
-fn load_imp(ruleset: &ll::Ruleset, nnp: bool) -> Result<()> {
+/// Reads the status reported by the internal process, after it exits.
+pub(crate) fn reap(reader: Option<PipeReader>) -> Option<LandlockStatus> {
+    let mut encoded = vec![];
+    reader?.read_to_end(&mut encoded).ok()?;
+    let config = bincode::config::standard();
+    bincode::serde::decode_from_slice(&encoded, config)
+        .ok()
+        .map(|(status, _)| status)
+}
+
+fn load_imp(ruleset: &ll::Ruleset, nnp: bool) -> Result<Option<RestrictionStatus>> {
     if ruleset.restrictions.is_empty() {
-        return Ok(());
+        return Ok(None);
     }
 
     let abi = ABI::V5;
@@ -24,21 +78,24 @@ fn load_imp(ruleset: &ll::Ruleset, nnp: bool) -> Result<()> {
             ll::Resource::FS => handle_access_fs(ctx, abi)?,
             ll::Resource::NET_TCP_BIND => handle_access_net(ctx, resource, mode)?,
             ll::Resource::NET_TCP_CONNECT => handle_access_net(ctx, resource, mode)?,
+            ll::Resource::SCOPE_ABSTRACT_UNIX_SOCKET => handle_scope(ctx, resource, mode)?,
+            ll::Resource::SCOPE_SIGNAL => handle_scope(ctx, resource, mode)?,
         }
     }
 
     let mut ctx = ctx.set_compatibility(CompatLevel::default()).create()?;
     for (resource, _) in ruleset.restrictions.iter() {
         ctx = match resource {
-            ll::Resource::FS => add_rules_fs(ctx, abi, ruleset)?,
+            ll::Resource::FS => add_rules_fs(ctx, ruleset)?,
             ll::Resource::NET_TCP_BIND => add_rules_net(ctx, ruleset, resource)?,
             ll::Resource::NET_TCP_CONNECT => add_rules_net(ctx, ruleset, resource)?,
+            ll::Resource::SCOPE_ABSTRACT_UNIX_SOCKET => ctx,
+            ll::Resource::SCOPE_SIGNAL => ctx,
         }
     }
 
     ctx = ctx.set_no_new_privs(nnp);
-    ctx.restrict_self()?;
-    Ok(())
+    Ok(Some(ctx.restrict_self()?))
 }
 
 fn handle_access_fs(mut ctx: Ruleset, abi: ABI) -> Result<Ruleset> {
@@ -65,24 +122,23 @@ fn handle_access_net(
     Ok(ctx)
 }
 
-fn add_rules_fs(
-    mut ctx: RulesetCreated,
-    abi: ABI,
-    ruleset: &ll::Ruleset,
-) -> Result<RulesetCreated> {
-    let r = translate_fs_access(abi, ll::FsAccess::R);
-    let w = translate_fs_access(abi, ll::FsAccess::W);
-    let x = translate_fs_access(abi, ll::FsAccess::X);
+fn handle_scope(
+    mut ctx: Ruleset,
+    resource: &ll::Resource,
+    mode: &ll::CompatMode,
+) -> Result<Ruleset> {
+    let compatibility = translate_compat_mode(*mode);
+    let scope = translate_scope_resource(*resource);
+    ctx = ctx
+        .set_compatibility(compatibility)
+        .scope(scope)
+        .map_err(|e| translate_landlock_ruleset_error(*resource, e))?;
+    Ok(ctx)
+}
+
+fn add_rules_fs(mut ctx: RulesetCreated, ruleset: &ll::Ruleset) -> Result<RulesetCreated> {
     for rule in ruleset.get_fs_rules() {
-        let mut access = BitFlags::empty();
-        for e in [ll::FsAccess::R, ll::FsAccess::W, ll::FsAccess::X] {
-            match rule.mode & e {
-                ll::FsAccess::R => access |= r,
-                ll::FsAccess::W => access |= w,
-                ll::FsAccess::X => access |= x,
-                _ => {}
-            }
-        }
+        let access = translate_fs_access(rule.mode);
         let path = std::fs::canonicalize(rule.path.clone())
             .map_err(|_| Error::LandlockPathMustBeAbsolute(rule.path.clone()))?;
         ctx = ctx.add_rules(path_beneath_rules([path], access))?;
@@ -119,12 +175,67 @@ fn translate_net_resource(resource: ll::Resource) -> AccessNet {
     }
 }
 
-fn translate_fs_access(abi: ABI, access: ll::FsAccess) -> BitFlags<AccessFs> {
-    match access {
-        ll::FsAccess::R => AccessFs::from_read(abi) & !AccessFs::Execute,
-        ll::FsAccess::W => AccessFs::from_write(abi),
-        ll::FsAccess::X => AccessFs::Execute.into(),
-        _ => unreachable!("runc::landlock::translate_fs_access"),
+fn translate_scope_resource(resource: ll::Resource) -> Scope {
+    match resource {
+        ll::Resource::SCOPE_ABSTRACT_UNIX_SOCKET => Scope::AbstractUnixSocket,
+        ll::Resource::SCOPE_SIGNAL => Scope::Signal,
+        _ => unreachable!("runc::landlock::translate_scope_resource"),
+    }
+}
+
+fn translate_fs_access(access: ll::FsAccess) -> BitFlags<AccessFs> {
+    let mut flags = BitFlags::empty();
+    for (e, f) in FS_ACCESSES {
+        if access.contains(e) {
+            flags |= f;
+        }
+    }
+    flags
+}
+
+fn translate_restriction_status(
+    ruleset: &ll::Ruleset,
+    status: RestrictionStatus,
+) -> LandlockStatus {
+    let abi = sys::landlock_abi_version();
+    let mut handled = vec![];
+    for resource in ruleset.restrictions.keys() {
+        match resource {
+            ll::Resource::FS => {
+                let access = AccessFs::from_all(ABI::from(abi));
+                let access = FS_ACCESSES
+                    .iter()
+                    .filter(|(_, f)| access.contains(*f))
+                    .fold(ll::FsAccess::empty(), |acc, (e, _)| acc | *e);
+                if !access.is_empty() {
+                    handled.push((*resource, Some(access)));
+                }
+            }
+            ll::Resource::NET_TCP_BIND | ll::Resource::NET_TCP_CONNECT => {
+                let access = translate_net_resource(*resource);
+                if AccessNet::from_all(ABI::from(abi)).contains(access) {
+                    handled.push((*resource, None));
+                }
+            }
+            ll::Resource::SCOPE_ABSTRACT_UNIX_SOCKET | ll::Resource::SCOPE_SIGNAL => {
+                let scope = translate_scope_resource(*resource);
+                if Scope::from_all(ABI::from(abi)).contains(scope) {
+                    handled.push((*resource, None));
+                }
+            }
+        }
+    }
+    handled.sort_by_key(|(resource, _)| *resource);
+
+    let ruleset = match status.ruleset {
+        RulesetStatus::FullyEnforced => LandlockRulesetStatus::FullyEnforced,
+        RulesetStatus::PartiallyEnforced => LandlockRulesetStatus::PartiallyEnforced,
+        RulesetStatus::NotEnforced => LandlockRulesetStatus::NotEnforced,
+    };
+    LandlockStatus {
+        ruleset,
+        abi,
+        handled,
     }
 }
 
@@ -142,6 +253,8 @@ fn translate_landlock_ruleset_error(resource: ll::Resource, e: landlock::Ruleset
         ll::Resource::FS => ("Filesystem restrictions", "5.13"),
         ll::Resource::NET_TCP_BIND => ("Network TCP restrictions", "6.7"),
         ll::Resource::NET_TCP_CONNECT => ("Network TCP restrictions", "6.7"),
+        ll::Resource::SCOPE_ABSTRACT_UNIX_SOCKET => ("Scoping restrictions", "6.12"),
+        ll::Resource::SCOPE_SIGNAL => ("Scoping restrictions", "6.12"),
     };
     Error::LandlockFeatureUnsupported(f.to_string(), m.to_string(), e.to_string())
 }
@@ -0,0 +1,52 @@
+use std::os::fd::{AsFd, OwnedFd};
+use std::os::unix::net::UnixStream;
+
+use super::error::*;
+use super::sys::{self, MsFlags};
+use crate::Container;
+
+/// The slave side of the pseudo-terminal, which will become the controlling
+/// terminal of the internal process.
+pub(crate) struct PtySlave {
+    fd: OwnedFd,
+}
+
+// Allocate a new pseudo-terminal from the devpts mounted in the container,
+// and send the master to the main process.
+pub(crate) fn openpty(container: &Container, socket: &UnixStream) -> Result<PtySlave> {
+    let master = sys::posix_openpt()?;
+    sys::grantpt(&master)?;
+    sys::unlockpt(&master)?;
+    let slave_path = sys::ptsname(&master)?;
+    let slave = sys::open_tty(&slave_path)?;
+
+    // Use the same window size as the caller's terminal.
+    if sys::isatty_stdin()? {
+        let winsize = sys::tcgetwinsize(std::io::stdin().as_fd())?;
+        sys::tcsetwinsize(master.as_fd(), &winsize)?;
+    }
+
+    // Use it as the console instead of the caller's terminal.
+    for mount in container.get_mounts() {
+        if mount.fstype == "devfs" {
+            let target = format!("{}/console", mount.target.trim_end_matches('/'));
+            let flags = MsFlags::MS_BIND | MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC;
+            sys::touch(&target)?;
+            sys::mount(&slave_path, target, flags)?;
+        }
+    }
+
+    sys::send_fd(socket, master.as_fd())?;
+    Ok(PtySlave { fd: slave })
+}
+
+// Make the pseudo-terminal the controlling terminal and the standard I/O
+// stream of the internal process.
+pub(crate) fn login(slave: PtySlave) -> Result<()> {
+    sys::setsid()?;
+    sys::set_controlling_tty(slave.fd.as_fd())?;
+    sys::dup2_stdin(&slave.fd)?;
+    sys::dup2_stdout(&slave.fd)?;
+    sys::dup2_stderr(&slave.fd)?;
+    Ok(())
+}
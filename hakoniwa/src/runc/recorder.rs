@@ -0,0 +1,377 @@
+use libseccomp::ScmpSyscall;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::prelude::*;
+use std::os::fd::{AsFd, OwnedFd};
+use std::path::{Path, PathBuf};
+
+use super::sys::{self, Pid, PtraceSyscallInfo};
+use crate::seccomp::{translate_arch, translate_audit_arch};
+use crate::{Container, RecordedFsAccess, RecordedSyscall, Runctl};
+
+// The syscalls whose behavior is selected by an argument, and the index of
+// the argument whose values are recorded.
+const RECORDED_ARGS: &[(&str, u32)] = &[("personality", 0), ("socket", 0)];
+
+// The access to a directory, in the order of `rwx`.
+const R: u8 = 1;
+const W: u8 = 1 << 1;
+const X: u8 = 1 << 2;
+
+// What a filesystem syscall does with a path.
+#[derive(Clone, Copy)]
+enum FsOp {
+    // Opens the file or directory with the access.
+    Open(u8),
+    // Executes the file.
+    Exec,
+    // Creates, removes or renames the path in its parent directory.
+    Change,
+    // Creates the directory.
+    MakeDir,
+}
+
+/// Records the syscalls of the traced processes, when they are stopped at
+/// the entry or exit of a syscall.
+pub(crate) struct Recorder {
+    // The procfs of the main process, the tracees are inspected through it
+    // since they may not be visible in the procfs of the container.
+    procfs: Option<OwnedFd>,
+    // Syscalls keyed by the audit architecture and the syscall number, the
+    // ones unknown to libseccomp are kept as None.
+    syscalls: HashMap<(u32, u64), Option<RecordedSyscall>>,
+    fs_accesses: HashMap<PathBuf, u8>,
+    fs_created_dirs: HashSet<PathBuf>,
+    fs_pending: HashMap<Pid, Vec<(PathBuf, FsOp)>>,
+    record_syscalls: bool,
+    record_fs: bool,
+    started: bool,
+}
+
+impl Recorder {
+    pub(crate) fn new(container: &Container, procfs: Option<OwnedFd>) -> Self {
+        Self {
+            procfs,
+            syscalls: HashMap::new(),
+            fs_accesses: HashMap::new(),
+            fs_created_dirs: HashSet::new(),
+            fs_pending: HashMap::new(),
+            record_syscalls: container.runctl.contains(&Runctl::RecordSyscalls),
+            record_fs: container.needs_childp_tracefs(),
+            started: false,
+        }
+    }
+
+    /// Records the syscall the process is stopped at.
+    pub(crate) fn record(&mut self, pid: Pid, info: &PtraceSyscallInfo) {
+        if !self.record_syscalls && !self.record_fs {
+            return;
+        }
+
+        // The syscalls made by the internal process before the seccomp
+        // filter is loaded are not checked by it, they are skipped until the
+        // filter is loaded, or the program is executed if there is none.
+        if !self.started {
+            if info.op != PtraceSyscallInfo::OP_ENTRY {
+                return;
+            }
+            match info.nr as libc::c_long {
+                libc::SYS_seccomp if info.args[0] == libc::SECCOMP_SET_MODE_FILTER as u64 => {
+                    self.started = true;
+                    return;
+                }
+                libc::SYS_execve => self.started = true,
+                _ => return,
+            }
+        }
+
+        match info.op {
+            PtraceSyscallInfo::OP_ENTRY => self.record_entry(pid, info),
+            PtraceSyscallInfo::OP_EXIT => self.record_exit(pid, info),
+            _ => {}
+        }
+    }
+
+    /// Returns the recorded syscalls and filesystem accesses.
+    pub(crate) fn finish(self) -> (Option<Vec<RecordedSyscall>>, Option<Vec<RecordedFsAccess>>) {
+        let syscalls = self.record_syscalls.then(|| {
+            let mut syscalls = self.syscalls.into_values().flatten().collect::<Vec<_>>();
+            syscalls.sort_by(|a, b| (&a.arch, &a.sysname).cmp(&(&b.arch, &b.sysname)));
+            syscalls
+        });
+
+        // Directories created by the tracees do not exist before they run,
+        // their accesses are moved to the nearest existing ancestor.
+        let fs_accesses = self.record_fs.then(|| {
+            let mut accesses: HashMap<PathBuf, u8> = HashMap::new();
+            for (mut path, access) in self.fs_accesses {
+                while self.fs_created_dirs.contains(&path) {
+                    match path.parent() {
+                        Some(parent) => path = parent.to_path_buf(),
+                        None => break,
+                    }
+                }
+                *accesses.entry(path).or_default() |= access;
+            }
+
+            let mut accesses = accesses
+                .into_iter()
+                .map(|(path, access)| RecordedFsAccess {
+                    path: path.to_string_lossy().to_string(),
+                    access: access_to_string(access),
+                })
+                .collect::<Vec<_>>();
+            accesses.sort_by(|a, b| a.path.cmp(&b.path));
+            accesses
+        });
+
+        (syscalls, fs_accesses)
+    }
+
+    fn record_entry(&mut self, pid: Pid, info: &PtraceSyscallInfo) {
+        let syscall = self
+            .syscalls
+            .entry((info.arch, info.nr))
+            .or_insert_with(|| recorded_syscall(info.arch, info.nr as i32));
+        let Some(syscall) = syscall else {
+            return;
+        };
+
+        if let Some((_, index)) = RECORDED_ARGS.iter().find(|(s, _)| *s == syscall.sysname) {
+            let arg = (*index, info.args[*index as usize]);
+            if !syscall.args.contains(&arg) {
+                syscall.args.push(arg);
+            }
+        }
+
+        if self.record_fs {
+            let sysname = syscall.sysname.clone();
+            let ops = self.decode_fs_syscall(pid, &sysname, &info.args);
+            if !ops.is_empty() {
+                self.fs_pending.insert(pid, ops);
+            }
+        }
+    }
+
+    fn record_exit(&mut self, pid: Pid, info: &PtraceSyscallInfo) {
+        let Some(ops) = self.fs_pending.remove(&pid) else {
+            return;
+        };
+        if info.is_error() {
+            return;
+        }
+
+        for (path, op) in ops {
+            match op {
+                FsOp::Open(access) => self.add_fs_access(&path, access),
+                FsOp::Exec => {
+                    self.add_fs_access(&path, R | X);
+                    for path in self.executable_mappings(pid) {
+                        self.add_fs_access(&path, R | X);
+                    }
+                }
+                FsOp::Change => {
+                    if let Some(parent) = path.parent() {
+                        self.add_fs_access(parent, W);
+                    }
+                }
+                FsOp::MakeDir => {
+                    if let Some(parent) = path.parent() {
+                        self.add_fs_access(parent, W);
+                    }
+                    if let Ok(path) = fs::canonicalize(path) {
+                        self.fs_created_dirs.insert(path);
+                    }
+                }
+            }
+        }
+    }
+
+    // Decodes the paths of a filesystem syscall at its entry, the memory of
+    // the process may be gone at its exit, e.g. execve.
+    fn decode_fs_syscall(&self, pid: Pid, sysname: &str, args: &[u64; 6]) -> Vec<(PathBuf, FsOp)> {
+        let path = |index: usize| self.resolve_path(pid, None, args[index]);
+        let path_at = |dirfd: usize, index: usize| {
+            self.resolve_path(pid, Some(args[dirfd] as i32), args[index])
+        };
+
+        let ops = match sysname {
+            "open" => vec![(path(0), FsOp::Open(open_access(args[1])))],
+            "openat" => vec![(path_at(0, 1), FsOp::Open(open_access(args[2])))],
+            "openat2" => {
+                let flags = read_u64(pid, args[2] as usize).unwrap_or_default();
+                vec![(path_at(0, 1), FsOp::Open(open_access(flags)))]
+            }
+            "creat" | "truncate" => vec![(path(0), FsOp::Open(W))],
+            "execve" => vec![(path(0), FsOp::Exec)],
+            "execveat" => vec![(path_at(0, 1), FsOp::Exec)],
+            "mkdir" => vec![(path(0), FsOp::MakeDir)],
+            "mkdirat" => vec![(path_at(0, 1), FsOp::MakeDir)],
+            "mknod" | "rmdir" | "unlink" => vec![(path(0), FsOp::Change)],
+            "mknodat" | "unlinkat" => vec![(path_at(0, 1), FsOp::Change)],
+            "rename" | "link" => vec![(path(0), FsOp::Change), (path(1), FsOp::Change)],
+            "renameat" | "renameat2" | "linkat" => {
+                vec![(path_at(0, 1), FsOp::Change), (path_at(2, 3), FsOp::Change)]
+            }
+            "symlink" => vec![(path(1), FsOp::Change)],
+            "symlinkat" => vec![(path_at(1, 2), FsOp::Change)],
+            _ => vec![],
+        };
+
+        ops.into_iter()
+            .filter_map(|(path, op)| path.map(|path| (path, op)))
+            .collect()
+    }
+
+    // Resolves a path argument of the process, relative to the directory
+    // referred to by `dirfd`, or the working directory.
+    fn resolve_path(&self, pid: Pid, dirfd: Option<i32>, addr: u64) -> Option<PathBuf> {
+        let path = PathBuf::from(read_cstr(pid, addr as usize)?);
+        if path.is_absolute() {
+            return Some(path);
+        }
+
+        let procfs = self.procfs.as_ref()?;
+        let dir = match dirfd {
+            Some(libc::AT_FDCWD) | None => format!("{pid}/cwd"),
+            Some(fd) => format!("{pid}/fd/{fd}"),
+        };
+        let dir = sys::readlinkat(procfs.as_fd(), &dir).ok()?;
+        match path.as_os_str().is_empty() {
+            true => Some(dir),
+            false => Some(dir.join(path)),
+        }
+    }
+
+    // Returns the files mapped as executable into the process, e.g. the
+    // program interpreter which is loaded by execve.
+    fn executable_mappings(&self, pid: Pid) -> Vec<PathBuf> {
+        let Some(procfs) = &self.procfs else {
+            return vec![];
+        };
+
+        let mut maps = String::new();
+        match sys::openat_rdonly(procfs.as_fd(), &format!("{pid}/maps")) {
+            Ok(mut file) => _ = file.read_to_string(&mut maps),
+            Err(_) => return vec![],
+        }
+
+        let mut paths = vec![];
+        for line in maps.lines() {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if let [_, perms, _, _, _, path] = fields[..]
+                && perms.contains('x')
+                && path.starts_with('/')
+            {
+                let path = PathBuf::from(path);
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+
+    // Adds the access to the directory, or the one containing the file. A
+    // path which cannot be resolved by the reaper, e.g. `/proc/self`, falls
+    // back to its nearest resolvable ancestor.
+    fn add_fs_access(&mut self, path: &Path, access: u8) {
+        if access == 0 {
+            return;
+        }
+
+        let mut path = path;
+        let dir = loop {
+            if let Ok(canonical) = fs::canonicalize(path) {
+                match canonical.is_dir() {
+                    true => break canonical,
+                    false => match canonical.parent() {
+                        Some(parent) => break parent.to_path_buf(),
+                        None => return,
+                    },
+                }
+            }
+            match path.parent() {
+                Some(parent) => path = parent,
+                None => return,
+            }
+        };
+        *self.fs_accesses.entry(dir).or_default() |= access;
+    }
+}
+
+fn recorded_syscall(arch: u32, syscall: i32) -> Option<RecordedSyscall> {
+    let arch = translate_audit_arch(arch, syscall)?;
+    let sysname = ScmpSyscall::from_raw_syscall(syscall)
+        .get_name_by_arch(translate_arch(arch))
+        .ok()?;
+    Some(RecordedSyscall {
+        syscall,
+        sysname,
+        arch: arch.to_string(),
+        args: vec![],
+    })
+}
+
+fn open_access(flags: u64) -> u8 {
+    let flags = flags as libc::c_int;
+    if flags & libc::O_PATH != 0 {
+        return 0;
+    }
+
+    let mut access = match flags & libc::O_ACCMODE {
+        libc::O_WRONLY => W,
+        libc::O_RDWR => R | W,
+        _ => R,
+    };
+    if flags & (libc::O_CREAT | libc::O_TRUNC) != 0 {
+        access |= W;
+    }
+    access
+}
+
+fn access_to_string(access: u8) -> String {
+    [(R, 'r'), (W, 'w'), (X, 'x')]
+        .iter()
+        .map(|&(e, c)| if access & e != 0 { c } else { '-' })
+        .collect()
+}
+
+// Reads a NUL-terminated string from the memory of the process. The memory
+// is read page by page, since the string may end right before an unmapped
+// page.
+pub(super) fn read_cstr(pid: Pid, mut addr: usize) -> Option<String> {
+    const PAGE_SIZE: usize = 4096;
+
+    let mut str = vec![];
+    while str.len() < libc::PATH_MAX as usize {
+        let mut buf = vec![0; PAGE_SIZE - addr % PAGE_SIZE];
+        let n = sys::process_vm_readv(pid, &mut buf, addr).ok()?;
+        if n == 0 {
+            return None;
+        }
+        if let Some(end) = buf[..n].iter().position(|&c| c == 0) {
+            str.extend_from_slice(&buf[..end]);
+            return String::from_utf8(str).ok();
+        }
+        str.extend_from_slice(&buf[..n]);
+        addr += n;
+    }
+    None
+}
+
+pub(super) fn read_u32(pid: Pid, addr: usize) -> Option<u32> {
+    let mut buf = [0; 4];
+    match sys::process_vm_readv(pid, &mut buf, addr).ok()? {
+        4 => Some(u32::from_ne_bytes(buf)),
+        _ => None,
+    }
+}
+
+pub(super) fn read_u64(pid: Pid, addr: usize) -> Option<u64> {
+    let mut buf = [0; 8];
+    match sys::process_vm_readv(pid, &mut buf, addr).ok()? {
+        8 => Some(u64::from_ne_bytes(buf)),
+        _ => None,
+    }
+}
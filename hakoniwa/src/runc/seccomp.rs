@@ -1,123 +1,112 @@
-use libseccomp::*;
+use libseccomp::ScmpSyscall;
+use procfs::FromRead;
+use procfs::process::Status;
+use std::io::Read;
+use std::os::fd::{AsFd, OwnedFd};
+use std::os::unix::net::UnixStream;
 
-use super::{error::*, sys};
-use crate::{Container, Runctl, seccomp::*};
+use super::error::*;
+use super::sys::{self, Pid};
+use crate::seccomp::{translate_arch, translate_audit_arch};
+use crate::{BlockedSyscall, Container, Runctl, scm_rights};
 
-pub(crate) fn load(container: &Container) -> Result<()> {
-    let nnp = !container.runctl.contains(&Runctl::AllowNewPrivs);
-    match &container.seccomp_filter {
-        Some(filter) => load_imp(filter, nnp),
-        None => match nnp {
-            true => sys::set_no_new_privs(),
-            _ => Ok(()),
-        },
-    }
-}
+// The si_code of SIGSYS raised by seccomp.
+const SYS_SECCOMP: i32 = 1;
 
-fn load_imp(filter: &Filter, nnp: bool) -> Result<()> {
-    // Create a new filter context.
-    let default_scmp_action = translate_action(filter.default_action);
-    let mut ctx = ScmpFilterContext::new(default_scmp_action)?;
-    ctx.set_ctl_nnp(nnp)?;
+// The SIGSYS fields of siginfo_t.
+#[repr(C)]
+struct SigSys {
+    si_signo: libc::c_int,
+    si_errno: libc::c_int,
+    si_code: libc::c_int,
+    call_addr: usize,
+    syscall: libc::c_int,
+    arch: libc::c_uint,
+}
 
-    // Add architectures.
-    for arch in &filter.architectures {
-        let scmp_arch = translate_arch(*arch);
-        ctx.add_arch(scmp_arch)?;
+pub(crate) fn load(container: &Container, socket: Option<UnixStream>) -> Result<()> {
+    let nnp = !container.runctl.contains(&Runctl::AllowNewPrivs);
+    if nnp {
+        sys::set_no_new_privs()?;
     }
 
-    // Add rules.
-    for rule in &filter.rules {
-        let (action, sysname, argcmps) = (rule.action, &rule.sysname, &rule.argcmps);
-
-        // If the action is the same as the default action, the rule is
-        // redundant, skip it.
-        let scmp_action = translate_action(action);
-        if scmp_action == default_scmp_action {
-            continue;
-        }
+    // The filter has been compiled in the main process.
+    let compiled = match container.seccomp_compiled.get() {
+        Some(compiled) if container.seccomp_filter.is_some() => compiled,
+        _ => return Ok(()),
+    };
 
-        // If the syscall is not supported by the kernel, skip it.
-        let scmp_syscall = match ScmpSyscall::from_name(sysname) {
-            Ok(syscall) => syscall,
-            Err(_) => continue,
+    // Load the filters, request a notification fd for the one that has any
+    // Notify rules.
+    let mut notify_fd = None;
+    for program in compiled.programs.iter() {
+        let flags = match program.notify {
+            true => libc::SECCOMP_FILTER_FLAG_NEW_LISTENER,
+            false => 0,
         };
-
-        // Adds a single rule for an unconditional action on a syscall.
-        if argcmps.is_empty() {
-            ctx.add_rule(scmp_action, scmp_syscall)?;
-            continue;
+        if let Some(fd) = sys::seccomp_set_mode_filter(&program.insns, flags)? {
+            notify_fd = Some(fd);
         }
-
-        // Adds a single rule for a conditional action on a syscall.
-        let scmp_argcmps = translate_argcmps(argcmps);
-        ctx.add_rule_conditional(scmp_action, scmp_syscall, &scmp_argcmps)?;
     }
 
-    // Load the filter.
-    Ok(ctx.load()?)
+    // Send the notification fd to the main process.
+    match (notify_fd, socket) {
+        (Some(fd), Some(socket)) if container.seccomp_notifier.is_some() => {
+            Ok(scm_rights::send_fd(&socket, fd.as_fd())?)
+        }
+        _ => Ok(()),
+    }
 }
 
-fn translate_action(action: Action) -> ScmpAction {
-    match action {
-        Action::Allow => ScmpAction::Allow,
-        Action::Errno(v) => ScmpAction::Errno(v),
-        Action::KillProcess => ScmpAction::KillProcess,
-        Action::KillThread => ScmpAction::KillThread,
-        Action::Log => ScmpAction::Log,
-        Action::Notify => ScmpAction::Notify,
-        Action::Trace(v) => ScmpAction::Trace(v),
-        Action::Trap => ScmpAction::Trap,
+/// Gets the syscall trapped by a `Trap` rule, when a tracee is stopped by
+/// SIGSYS. Also returns true if the tracee does not catch SIGSYS, i.e. the
+/// signal is fatal.
+pub(crate) fn reap_trapped_syscall(pid: Pid, procfs: &OwnedFd) -> Option<(BlockedSyscall, bool)> {
+    let info = sys::ptrace_getsiginfo(pid).ok()?;
+    if info.si_code != SYS_SECCOMP {
+        return None;
     }
+
+    let sigsys = unsafe { &*(&info as *const libc::siginfo_t as *const SigSys) };
+    let blocked = blocked_syscall(sigsys.arch, sigsys.syscall, sigsys.call_addr as u64)?;
+    let caught = sys::openat_rdonly(procfs.as_fd(), &format!("{pid}/status"))
+        .ok()
+        .and_then(|file| Status::from_read(file).ok())
+        .map(|status| status.sigcgt & (1 << (libc::SIGSYS - 1)) != 0)
+        .unwrap_or(true);
+    Some((blocked, !caught))
 }
 
-fn translate_arch(arch: Arch) -> ScmpArch {
-    match arch {
-        Arch::Native => ScmpArch::Native,
-        Arch::X86 => ScmpArch::X86,
-        Arch::X8664 => ScmpArch::X8664,
-        Arch::X32 => ScmpArch::X32,
-        Arch::Arm => ScmpArch::Arm,
-        Arch::Aarch64 => ScmpArch::Aarch64,
-        Arch::Loongarch64 => ScmpArch::Loongarch64,
-        Arch::M68k => ScmpArch::M68k,
-        Arch::Mips => ScmpArch::Mips,
-        Arch::Mips64 => ScmpArch::Mips64,
-        Arch::Mips64n32 => ScmpArch::Mips64N32,
-        Arch::Mipsel => ScmpArch::Mipsel,
-        Arch::Mipsel64 => ScmpArch::Mipsel64,
-        Arch::Mipsel64n32 => ScmpArch::Mipsel64N32,
-        Arch::Ppc => ScmpArch::Ppc,
-        Arch::Ppc64 => ScmpArch::Ppc64,
-        Arch::Ppc64le => ScmpArch::Ppc64Le,
-        Arch::S390 => ScmpArch::S390,
-        Arch::S390x => ScmpArch::S390X,
-        Arch::Parisc => ScmpArch::Parisc,
-        Arch::Parisc64 => ScmpArch::Parisc64,
-        Arch::Riscv64 => ScmpArch::Riscv64,
-        Arch::Sheb => ScmpArch::Sheb,
-        Arch::Sh => ScmpArch::Sh,
+/// Gets the syscall killed by a `KillProcess` or `KillThread` rule, when a
+/// tracee is stopped at exit.
+///
+/// The kernel rolls back the registers before killing the tracee, so the
+/// syscall is still visible in `/proc/<pid>/syscall`.
+pub(crate) fn reap_killed_syscall(pid: Pid, procfs: &OwnedFd) -> Option<BlockedSyscall> {
+    let status = sys::ptrace_getevent(pid).ok()? as libc::c_int;
+    if !libc::WIFSIGNALED(status) || libc::WTERMSIG(status) != libc::SIGSYS {
+        return None;
     }
+
+    let info = sys::ptrace_get_syscall_info(pid).ok()?;
+    let mut syscall = String::new();
+    sys::openat_rdonly(procfs.as_fd(), &format!("{pid}/syscall"))
+        .ok()?
+        .read_to_string(&mut syscall)
+        .ok()?;
+    let syscall = syscall.split_whitespace().next()?.parse().ok()?;
+    blocked_syscall(info.arch, syscall, info.instruction_pointer)
 }
 
-fn translate_argcmps(argcmps: &[ArgCmp]) -> Vec<ScmpArgCompare> {
-    argcmps
-        .iter()
-        .map(|cmp| {
-            let mut datum = cmp.datum_a;
-            let op = match cmp.op {
-                ArgCmpOp::Ne => ScmpCompareOp::NotEqual,
-                ArgCmpOp::Lt => ScmpCompareOp::Less,
-                ArgCmpOp::Le => ScmpCompareOp::LessOrEqual,
-                ArgCmpOp::Eq => ScmpCompareOp::Equal,
-                ArgCmpOp::Gt => ScmpCompareOp::Greater,
-                ArgCmpOp::Ge => ScmpCompareOp::GreaterEqual,
-                ArgCmpOp::MaskedEq => {
-                    datum = cmp.datum_b;
-                    ScmpCompareOp::MaskedEqual(cmp.datum_a)
-                }
-            };
-            ScmpArgCompare::new(cmp.arg, op, datum)
-        })
-        .collect()
+fn blocked_syscall(arch: u32, syscall: i32, instruction_pointer: u64) -> Option<BlockedSyscall> {
+    let arch = translate_audit_arch(arch, syscall)?;
+    let sysname = ScmpSyscall::from_raw_syscall(syscall)
+        .get_name_by_arch(translate_arch(arch))
+        .unwrap_or_default();
+    Some(BlockedSyscall {
+        syscall,
+        sysname,
+        arch: arch.to_string(),
+        instruction_pointer,
+    })
 }
@@ -1,20 +1,34 @@
+use nix::errno::Errno;
+use nix::fcntl::{self, OFlag};
 use nix::mount;
+use nix::poll;
+use nix::pty;
 use nix::sched;
+use nix::sys::wait::Id;
 use nix::sys::{prctl, ptrace, resource, signal, statfs, wait};
-use nix::unistd::{self, alarm};
+use nix::unistd;
 use std::ffi::CStr;
 use std::fmt::Debug;
 use std::fs;
 use std::fs::{File, Metadata};
 use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
 use std::os::unix::fs as unix_fs;
 use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::process;
+use std::thread;
+use std::time::Duration;
 
 pub(crate) use nix::mount::{MntFlags, MsFlags};
+pub(crate) use nix::poll::{PollFd, PollFlags, PollTimeout};
+pub(crate) use nix::pty::{PtyMaster, Winsize};
 pub(crate) use nix::sched::CloneFlags;
 pub(crate) use nix::sys::ptrace::Event as PtraceEvent;
 pub(crate) use nix::sys::resource::{Resource, Usage, UsageWho};
-pub(crate) use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal};
+pub(crate) use nix::sys::signal::{SigHandler, SigSet, SigmaskHow, Signal};
+pub(crate) use nix::sys::signalfd::{SfdFlags, SignalFd};
 pub(crate) use nix::sys::statfs::Statfs;
 pub(crate) use nix::sys::statvfs::FsFlags;
 pub(crate) use nix::sys::wait::{WaitPidFlag, WaitStatus};
@@ -22,9 +36,17 @@ pub(crate) use nix::unistd::{ForkResult, Pid};
 pub(crate) use std::path::{Path, PathBuf};
 
 use super::error::*;
+use crate::{pidfd, scm_rights};
 
 const NULL: Option<&'static Path> = None;
 
+// [mount.h]: https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/mount.h
+const MOVE_MOUNT_F_EMPTY_PATH: libc::c_uint = 0x00000004;
+const FSOPEN_CLOEXEC: libc::c_uint = 0x00000001;
+const FSCONFIG_SET_STRING: libc::c_uint = 1;
+const FSCONFIG_CMD_CREATE: libc::c_uint = 6;
+const FSMOUNT_CLOEXEC: libc::c_uint = 0x00000001;
+
 macro_rules! map_err {
     ($mod:ident :: $fn:ident ()) => {
         map_err!($mod::$fn(), "")
@@ -63,6 +85,10 @@ pub(crate) fn unshare(clone_flags: CloneFlags) -> Result<()> {
     map_err!(sched::unshare(clone_flags))
 }
 
+pub(crate) fn setns(fd: BorrowedFd<'_>, nstype: CloneFlags) -> Result<()> {
+    map_err!(sched::setns(fd, nstype))
+}
+
 pub(crate) fn fork() -> Result<ForkResult> {
     unsafe { unistd::fork() }.map_err(|err| {
         let err = format!("fork() => {err}");
@@ -70,6 +96,14 @@ pub(crate) fn fork() -> Result<ForkResult> {
     })
 }
 
+pub(crate) fn waitpid(pid: Pid) -> Result<WaitStatus> {
+    map_err!(wait::waitpid(pid, None::<WaitPidFlag>))
+}
+
+pub(crate) fn waitpid_any(flags: WaitPidFlag) -> Result<WaitStatus> {
+    map_err!(wait::waitpid(None::<Pid>, Some(flags)))
+}
+
 pub(crate) fn execve<S1: AsRef<CStr> + Debug, S2: AsRef<CStr> + Debug>(
     prog: &CStr,
     argv: &[S1],
@@ -79,18 +113,127 @@ pub(crate) fn execve<S1: AsRef<CStr> + Debug, S2: AsRef<CStr> + Debug>(
     Ok(())
 }
 
-pub(crate) fn waitpid(pid: Pid) -> Result<WaitStatus> {
-    map_err!(wait::waitpid(pid, None::<WaitPidFlag>))
+pub(crate) fn waitid(pidfd: BorrowedFd<'_>, flags: WaitPidFlag) -> Result<WaitStatus> {
+    wait::waitid(Id::PIDFd(pidfd), flags).map_err(|err| {
+        let err = format!("waitid(P_PIDFD, {pidfd:?}, {flags:?}) => {err}");
+        Error::SysError(err)
+    })
+}
+
+pub(crate) fn pidfd_open(pid: Pid) -> Result<OwnedFd> {
+    map_err!(pidfd::open(pid))
+}
+
+pub(crate) fn pidfd_send_signal(pidfd: BorrowedFd<'_>, sig: Signal) -> Result<()> {
+    map_err!(pidfd::send_signal(pidfd, sig))
+}
+
+pub(crate) fn poll(fds: &mut [PollFd], timeout: PollTimeout) -> Result<i32> {
+    match poll::poll(fds, timeout) {
+        Err(Errno::EINTR) => Ok(0),
+        Err(err) => Err(Error::SysError(format!("poll(.., {timeout:?}) => {err}"))),
+        Ok(n) => Ok(n),
+    }
 }
 
 pub(crate) fn ptrace_traceexit(pid: Pid) -> Result<()> {
     map_err!(ptrace::setoptions(pid, ptrace::Options::PTRACE_O_TRACEEXIT))
 }
 
+pub(crate) fn ptrace_tracedescendants(pid: Pid) -> Result<()> {
+    let options = ptrace::Options::PTRACE_O_TRACEEXIT
+        | ptrace::Options::PTRACE_O_TRACEFORK
+        | ptrace::Options::PTRACE_O_TRACEVFORK
+        | ptrace::Options::PTRACE_O_TRACECLONE;
+    map_err!(ptrace::setoptions(pid, options))
+}
+
+pub(crate) fn ptrace_tracesyscalls(pid: Pid) -> Result<()> {
+    let options = ptrace::Options::PTRACE_O_TRACEEXIT
+        | ptrace::Options::PTRACE_O_TRACESYSGOOD
+        | ptrace::Options::PTRACE_O_TRACEFORK
+        | ptrace::Options::PTRACE_O_TRACEVFORK
+        | ptrace::Options::PTRACE_O_TRACECLONE;
+    map_err!(ptrace::setoptions(pid, options))
+}
+
 pub(crate) fn ptrace_cont(pid: Pid, signal: Option<Signal>) -> Result<()> {
     map_err!(ptrace::cont(pid, signal))
 }
 
+pub(crate) fn ptrace_syscall(pid: Pid, signal: Option<Signal>) -> Result<()> {
+    map_err!(ptrace::syscall(pid, signal))
+}
+
+#[cfg(feature = "seccomp")]
+pub(crate) fn ptrace_getevent(pid: Pid) -> Result<libc::c_long> {
+    map_err!(ptrace::getevent(pid))
+}
+
+#[cfg(feature = "seccomp")]
+pub(crate) fn ptrace_getsiginfo(pid: Pid) -> Result<libc::siginfo_t> {
+    map_err!(ptrace::getsiginfo(pid))
+}
+
+/// struct ptrace_syscall_info, the data of a syscall exit shares the space
+/// of `nr` and `args`.
+#[cfg(feature = "seccomp")]
+#[repr(C)]
+#[derive(Default)]
+pub(crate) struct PtraceSyscallInfo {
+    pub(crate) op: u8,
+    pad: [u8; 3],
+    pub(crate) arch: u32,
+    pub(crate) instruction_pointer: u64,
+    stack_pointer: u64,
+    pub(crate) nr: u64,
+    pub(crate) args: [u64; 6],
+    data: [u64; 1],
+}
+
+#[cfg(feature = "seccomp")]
+impl PtraceSyscallInfo {
+    pub(crate) const OP_ENTRY: u8 = 1;
+    pub(crate) const OP_EXIT: u8 = 2;
+
+    /// Returns true if the syscall failed, at a syscall exit.
+    pub(crate) fn is_error(&self) -> bool {
+        self.args[0] as u8 != 0
+    }
+
+    /// Returns the return value, or the negated errno, at a syscall exit.
+    pub(crate) fn rval(&self) -> i64 {
+        self.nr as i64
+    }
+}
+
+// PTRACE_GET_SYSCALL_INFO is not defined by libc on musl.
+#[cfg(feature = "seccomp")]
+const PTRACE_GET_SYSCALL_INFO: libc::c_uint = 0x420e;
+
+#[cfg(feature = "seccomp")]
+pub(crate) fn ptrace_get_syscall_info(pid: Pid) -> Result<PtraceSyscallInfo> {
+    let mut info = PtraceSyscallInfo::default();
+    let size = size_of::<PtraceSyscallInfo>();
+    let res = unsafe { libc::ptrace(PTRACE_GET_SYSCALL_INFO as _, pid.as_raw(), size, &mut info) };
+    Errno::result(res)
+        .map(|_| info)
+        .map_err(|err| format!("ptrace(PTRACE_GET_SYSCALL_INFO, {pid}, ..) => {err}"))
+        .map_err(Error::SysError)
+}
+
+/// Reads the memory of the tracee at `addr`, returns the number of bytes read.
+#[cfg(feature = "seccomp")]
+pub(crate) fn process_vm_readv(pid: Pid, buf: &mut [u8], addr: usize) -> Result<usize> {
+    let len = buf.len();
+    let mut local = [io::IoSliceMut::new(buf)];
+    let remote = [nix::sys::uio::RemoteIoVec { base: addr, len }];
+    nix::sys::uio::process_vm_readv(pid, &mut local, &remote).map_err(|err| {
+        let err = format!("process_vm_readv({pid}, .., {addr:#x}) => {err}");
+        Error::SysError(err)
+    })
+}
+
 pub(crate) fn traceme() -> Result<()> {
     map_err!(ptrace::traceme())
 }
@@ -111,15 +254,74 @@ pub(crate) fn set_no_new_privs() -> Result<()> {
     map_err!(prctl::set_no_new_privs())
 }
 
+#[cfg(feature = "seccomp")]
+pub(crate) fn seccomp_set_mode_filter(
+    program: &[crate::seccomp::SockFilter],
+    flags: libc::c_ulong,
+) -> Result<Option<OwnedFd>> {
+    let prog = libc::sock_fprog {
+        len: program.len() as libc::c_ushort,
+        filter: program.as_ptr() as *mut libc::sock_filter,
+    };
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_seccomp,
+            libc::SECCOMP_SET_MODE_FILTER,
+            flags,
+            &prog as *const libc::sock_fprog,
+        )
+    };
+    if ret == -1 {
+        let err = Errno::last();
+        let err = format!("seccomp(SECCOMP_SET_MODE_FILTER, {flags:#x}, ..) => {err}");
+        Err(Error::SysError(err))
+    } else if flags & libc::SECCOMP_FILTER_FLAG_NEW_LISTENER != 0 {
+        Ok(Some(unsafe { OwnedFd::from_raw_fd(ret as RawFd) }))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "landlock")]
+pub(crate) fn landlock_abi_version() -> i32 {
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1;
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<libc::c_void>(),
+            0,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+    ret.max(0) as i32
+}
+
 pub(crate) fn set_keepcaps(attribute: bool) -> Result<()> {
     map_err!(prctl::set_keepcaps(attribute))
 }
 
-pub(crate) fn sigaction(signal: Signal, sigaction: &SigAction) -> Result<SigAction> {
-    unsafe { signal::sigaction(signal, sigaction) }.map_err(|err| {
-        let err = format!("sigaction({signal:?}, ..) => {err}");
-        Error::SysError(err)
-    })
+pub(crate) fn get_securebits() -> Result<libc::c_ulong> {
+    let res = unsafe { libc::prctl(libc::PR_GET_SECUREBITS, 0, 0, 0, 0) };
+    Errno::result(res)
+        .map(|bits| bits as libc::c_ulong)
+        .map_err(|err| format!("prctl(PR_GET_SECUREBITS) => {err}"))
+        .map_err(Error::SysError)
+}
+
+pub(crate) fn set_securebits(bits: libc::c_ulong) -> Result<()> {
+    let res = unsafe { libc::prctl(libc::PR_SET_SECUREBITS, bits, 0, 0, 0) };
+    Errno::result(res)
+        .map(drop)
+        .map_err(|err| format!("prctl(PR_SET_SECUREBITS, {bits:#x}) => {err}"))
+        .map_err(Error::SysError)
+}
+
+pub(crate) fn sigprocmask(how: SigmaskHow, set: &SigSet) -> Result<()> {
+    map_err!(signal::sigprocmask(how, Some(set), None::<&mut SigSet>))
+}
+
+pub(crate) fn signalfd(mask: &SigSet, flags: SfdFlags) -> Result<SignalFd> {
+    map_err!(SignalFd::with_flags(mask, flags))
 }
 
 pub(crate) fn sigraise(sig: Signal) -> Result<()> {
@@ -133,26 +335,25 @@ pub(crate) fn reset_sigpipe() -> Result<SigHandler> {
     })
 }
 
-pub(crate) fn setalarm(secs: u64) -> Result<()> {
-    alarm::set(secs as u32);
-    Ok(())
+pub(crate) fn dup(fd: BorrowedFd<'_>) -> Result<OwnedFd> {
+    map_err!(unistd::dup(fd))
 }
 
-pub(crate) fn dup2_stdin<Fd: std::os::fd::AsFd>(oldfd: Fd) -> Result<()> {
+pub(crate) fn dup2_stdin<Fd: AsFd>(oldfd: Fd) -> Result<()> {
     unistd::dup2_stdin(oldfd).map_err(|err| {
         let err = format!("dup2_stdin(..) => {err}");
         Error::SysError(err)
     })
 }
 
-pub(crate) fn dup2_stdout<Fd: std::os::fd::AsFd>(oldfd: Fd) -> Result<()> {
+pub(crate) fn dup2_stdout<Fd: AsFd>(oldfd: Fd) -> Result<()> {
     unistd::dup2_stdout(oldfd).map_err(|err| {
         let err = format!("dup2_stdout(..) => {err}");
         Error::SysError(err)
     })
 }
 
-pub(crate) fn dup2_stderr<Fd: std::os::fd::AsFd>(oldfd: Fd) -> Result<()> {
+pub(crate) fn dup2_stderr<Fd: AsFd>(oldfd: Fd) -> Result<()> {
     unistd::dup2_stderr(oldfd).map_err(|err| {
         let err = format!("dup2_stderr(..) => {err}");
         Error::SysError(err)
@@ -254,12 +455,267 @@ pub(crate) fn mount_filesystem<P1: AsRef<Path> + Debug, P2: AsRef<Path> + Debug>
     ))
 }
 
-pub(crate) fn mount_make_private<P: AsRef<Path> + Debug>(target: P) -> Result<()> {
+pub(crate) fn mount_devpts<P: AsRef<Path> + Debug>(target: P, flags: MsFlags) -> Result<()> {
+    let target = target.as_ref();
+    let data = "newinstance,ptmxmode=0666,mode=620";
+    map_err!(mount::mount(
+        Some("devpts"),
+        target,
+        Some("devpts"),
+        flags,
+        Some(data)
+    ))
+}
+
+pub(crate) fn mount_overlay<P: AsRef<Path> + Debug>(
+    target: P,
+    flags: MsFlags,
+    data: &str,
+) -> Result<()> {
+    let target = target.as_ref();
+    map_err!(mount::mount(
+        Some("overlay"),
+        target,
+        Some("overlay"),
+        flags,
+        Some(data)
+    ))
+}
+
+// Run fuse-overlayfs in the foreground, and wait until the filesystem is
+// mounted. It will be killed when the current process exits.
+pub(crate) fn fuse_overlayfs<P: AsRef<Path> + Debug>(target: P, data: &str) -> Result<()> {
+    let target = target.as_ref();
+    let errmsg = |err: String| {
+        let name = "fuse_overlayfs";
+        Error::SysError(format!("{name}({target:?}, {data:?}) => {err}"))
+    };
+
+    let mut command = process::Command::new("fuse-overlayfs");
+    command
+        .args(["-f", "-o", data])
+        .arg(target)
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null());
+    unsafe {
+        command.pre_exec(|| {
+            prctl::set_pdeathsig(Signal::SIGKILL)?;
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().map_err(|err| errmsg(err.to_string()))?;
+
+    for _ in 0..500 {
+        if let Ok(stat) = statfs::statfs(target)
+            && stat.filesystem_type() == statfs::FUSE_SUPER_MAGIC
+        {
+            return Ok(());
+        }
+        if let Ok(Some(status)) = child.try_wait() {
+            return Err(errmsg(status.to_string()));
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    _ = child.kill();
+    Err(errmsg("timed out".to_string()))
+}
+
+pub(crate) fn mount_propagation<P: AsRef<Path> + Debug>(target: P, flags: MsFlags) -> Result<()> {
     let target = target.as_ref();
-    let flags = MsFlags::MS_PRIVATE | MsFlags::MS_REC;
     map_err!(mount::mount(NULL, target, NULL, flags, NULL))
 }
 
+// Whether the new mount API is available, which requires kernel 5.12.
+pub(crate) fn mount_setattr_supported() -> bool {
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_mount_setattr,
+            -1,
+            std::ptr::null::<libc::c_char>(),
+            0,
+            std::ptr::null::<libc::mount_attr>(),
+            0,
+        )
+    };
+    !(res == -1 && Errno::last() == Errno::ENOSYS)
+}
+
+pub(crate) fn open_tree<P: AsRef<Path> + Debug>(path: P, recursive: bool) -> Result<OwnedFd> {
+    let path = path.as_ref();
+    let cpath = std::ffi::CString::new(path.as_os_str().as_encoded_bytes())?;
+    let mut flags = libc::OPEN_TREE_CLONE | libc::OPEN_TREE_CLOEXEC;
+    if recursive {
+        flags |= libc::AT_RECURSIVE as libc::c_uint;
+    }
+    let fd = unsafe { libc::syscall(libc::SYS_open_tree, libc::AT_FDCWD, cpath.as_ptr(), flags) };
+    if fd == -1 {
+        let err = Errno::last();
+        let err = format!("open_tree({path:?}, {recursive}) => {err}");
+        Err(Error::SysError(err))
+    } else {
+        Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+    }
+}
+
+pub(crate) fn mount_setattr_idmap(
+    fd: BorrowedFd<'_>,
+    userns: BorrowedFd<'_>,
+    recursive: bool,
+) -> Result<()> {
+    let attr = libc::mount_attr {
+        attr_set: libc::MOUNT_ATTR_IDMAP,
+        attr_clr: 0,
+        propagation: 0,
+        userns_fd: userns.as_raw_fd() as u64,
+    };
+    mount_setattr(fd, &attr, recursive)
+        .map_err(|err| format!("mount_setattr({fd:?}, MOUNT_ATTR_IDMAP, {recursive}) => {err}"))
+        .map_err(Error::SysError)
+}
+
+// Set the mount attributes like a bind remount does, i.e. the mount flags
+// which are not set are cleared, which fails with EPERM if they are locked.
+//
+// A bind remount only changes the top mount, so only the flags which are
+// set are applied to the submounts of a recursive mount.
+pub(crate) fn mount_setattr_flags(
+    fd: BorrowedFd<'_>,
+    flags: MsFlags,
+    recursive: bool,
+) -> Result<()> {
+    let mut attr = libc::mount_attr {
+        attr_set: 0,
+        attr_clr: libc::MOUNT_ATTR__ATIME,
+        propagation: 0,
+        userns_fd: 0,
+    };
+    for flag in flags {
+        match flag {
+            MsFlags::MS_RDONLY => attr.attr_set |= libc::MOUNT_ATTR_RDONLY,
+            MsFlags::MS_NOSUID => attr.attr_set |= libc::MOUNT_ATTR_NOSUID,
+            MsFlags::MS_NODEV => attr.attr_set |= libc::MOUNT_ATTR_NODEV,
+            MsFlags::MS_NOEXEC => attr.attr_set |= libc::MOUNT_ATTR_NOEXEC,
+            MsFlags::MS_NODIRATIME => attr.attr_set |= libc::MOUNT_ATTR_NODIRATIME,
+            MsFlags::MS_NOATIME => attr.attr_set |= libc::MOUNT_ATTR_NOATIME,
+            MsFlags::MS_RELATIME => attr.attr_set |= libc::MOUNT_ATTR_RELATIME,
+            _ if flag.bits() == libc::MS_NOSYMFOLLOW => {
+                attr.attr_set |= libc::MOUNT_ATTR_NOSYMFOLLOW
+            }
+            _ => {}
+        }
+    }
+    attr.attr_clr |= (libc::MOUNT_ATTR_RDONLY
+        | libc::MOUNT_ATTR_NOSUID
+        | libc::MOUNT_ATTR_NODEV
+        | libc::MOUNT_ATTR_NOEXEC
+        | libc::MOUNT_ATTR_NODIRATIME
+        | libc::MOUNT_ATTR_NOSYMFOLLOW)
+        & !attr.attr_set;
+    let errmsg = |err: Errno| format!("mount_setattr({fd:?}, {flags:?}, {recursive}) => {err}");
+    mount_setattr(fd, &attr, false)
+        .map_err(errmsg)
+        .map_err(Error::SysError)?;
+
+    if recursive {
+        // Changing the atime flags of a locked mount is not allowed.
+        attr.attr_set &= !libc::MOUNT_ATTR__ATIME;
+        attr.attr_clr = 0;
+        mount_setattr(fd, &attr, true)
+            .map_err(errmsg)
+            .map_err(Error::SysError)?;
+    }
+    Ok(())
+}
+
+fn mount_setattr(
+    fd: BorrowedFd<'_>,
+    attr: &libc::mount_attr,
+    recursive: bool,
+) -> std::result::Result<(), Errno> {
+    let mut flags = libc::AT_EMPTY_PATH as libc::c_uint;
+    if recursive {
+        flags |= libc::AT_RECURSIVE as libc::c_uint;
+    }
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_mount_setattr,
+            fd.as_raw_fd(),
+            c"".as_ptr(),
+            flags,
+            attr as *const libc::mount_attr,
+            std::mem::size_of::<libc::mount_attr>(),
+        )
+    };
+    Errno::result(res).map(drop)
+}
+
+// Create a new filesystem instance, and return a detached mount of it.
+pub(crate) fn fsmount(fstype: &str, source: &str, flags: MsFlags) -> Result<OwnedFd> {
+    let errmsg = |err: Errno| {
+        let name = "fsmount";
+        Error::SysError(format!(
+            "{name}({fstype:?}, {source:?}, {flags:?}) => {err}"
+        ))
+    };
+
+    let cfstype = std::ffi::CString::new(fstype)?;
+    let csource = std::ffi::CString::new(source)?;
+    let res = unsafe { libc::syscall(libc::SYS_fsopen, cfstype.as_ptr(), FSOPEN_CLOEXEC) };
+    let fsfd = Errno::result(res).map_err(errmsg)?;
+    let fsfd = unsafe { OwnedFd::from_raw_fd(fsfd as RawFd) };
+
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_fsconfig,
+            fsfd.as_raw_fd(),
+            FSCONFIG_SET_STRING,
+            c"source".as_ptr(),
+            csource.as_ptr(),
+            0,
+        )
+    };
+    Errno::result(res).map_err(errmsg)?;
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_fsconfig,
+            fsfd.as_raw_fd(),
+            FSCONFIG_CMD_CREATE,
+            std::ptr::null::<libc::c_char>(),
+            std::ptr::null::<libc::c_void>(),
+            0,
+        )
+    };
+    Errno::result(res).map_err(errmsg)?;
+
+    let res = unsafe { libc::syscall(libc::SYS_fsmount, fsfd.as_raw_fd(), FSMOUNT_CLOEXEC, 0) };
+    let fd = Errno::result(res).map_err(errmsg)?;
+    let fd = unsafe { OwnedFd::from_raw_fd(fd as RawFd) };
+    mount_setattr_flags(fd.as_fd(), flags, false)?;
+    Ok(fd)
+}
+
+pub(crate) fn move_mount<P: AsRef<Path> + Debug>(fd: BorrowedFd<'_>, target: P) -> Result<()> {
+    let target = target.as_ref();
+    let ctarget = std::ffi::CString::new(target.as_os_str().as_encoded_bytes())?;
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_move_mount,
+            fd.as_raw_fd(),
+            c"".as_ptr(),
+            libc::AT_FDCWD,
+            ctarget.as_ptr(),
+            MOVE_MOUNT_F_EMPTY_PATH,
+        )
+    };
+    if res == -1 {
+        let err = Errno::last();
+        let err = format!("move_mount({fd:?}, {target:?}) => {err}");
+        Err(Error::SysError(err))
+    } else {
+        Ok(())
+    }
+}
+
 pub(crate) fn unmount<P: AsRef<Path> + Debug>(target: P) -> Result<()> {
     let flags = MntFlags::MNT_DETACH;
     map_err!(mount::umount2(target.as_ref(), flags))
@@ -314,3 +770,90 @@ pub(crate) fn ttyname() -> Result<PathBuf> {
         Error::SysError(err)
     })
 }
+
+pub(crate) fn isatty_stdin() -> Result<bool> {
+    unistd::isatty(io::stdin()).map_err(|err| {
+        let err = format!("isatty(STDIN) => {err}");
+        Error::SysError(err)
+    })
+}
+
+pub(crate) fn posix_openpt() -> Result<PtyMaster> {
+    let flags = OFlag::O_RDWR | OFlag::O_NOCTTY | OFlag::O_CLOEXEC;
+    map_err!(pty::posix_openpt(flags))
+}
+
+pub(crate) fn grantpt(master: &PtyMaster) -> Result<()> {
+    map_err!(pty::grantpt(master))
+}
+
+pub(crate) fn unlockpt(master: &PtyMaster) -> Result<()> {
+    map_err!(pty::unlockpt(master))
+}
+
+pub(crate) fn ptsname(master: &PtyMaster) -> Result<String> {
+    map_err!(pty::ptsname_r(master))
+}
+
+pub(crate) fn open_rdonly<P: AsRef<Path> + Debug>(path: P) -> Result<OwnedFd> {
+    let flags = OFlag::O_RDONLY | OFlag::O_CLOEXEC;
+    let mode = nix::sys::stat::Mode::empty();
+    map_err!(fcntl::open(path.as_ref(), flags, mode))
+}
+
+#[cfg(feature = "seccomp")]
+pub(crate) fn openat_rdonly(dirfd: BorrowedFd<'_>, path: &str) -> Result<File> {
+    let flags = OFlag::O_RDONLY | OFlag::O_CLOEXEC;
+    let mode = nix::sys::stat::Mode::empty();
+    map_err!(fcntl::openat(dirfd, path, flags, mode)).map(File::from)
+}
+
+#[cfg(feature = "seccomp")]
+pub(crate) fn readlinkat(dirfd: BorrowedFd<'_>, path: &str) -> Result<PathBuf> {
+    map_err!(fcntl::readlinkat(dirfd, path)).map(PathBuf::from)
+}
+
+pub(crate) fn open_tty<P: AsRef<Path> + Debug>(path: P) -> Result<OwnedFd> {
+    let flags = OFlag::O_RDWR | OFlag::O_NOCTTY | OFlag::O_CLOEXEC;
+    let mode = nix::sys::stat::Mode::empty();
+    map_err!(fcntl::open(path.as_ref(), flags, mode))
+}
+
+pub(crate) fn tcgetwinsize(fd: BorrowedFd<'_>) -> Result<Winsize> {
+    let mut winsize = std::mem::MaybeUninit::<Winsize>::zeroed();
+    if unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCGWINSZ, winsize.as_mut_ptr()) } == -1 {
+        let err = nix::errno::Errno::last();
+        let err = format!("ioctl({fd:?}, TIOCGWINSZ) => {err}");
+        Err(Error::SysError(err))
+    } else {
+        Ok(unsafe { winsize.assume_init() })
+    }
+}
+
+pub(crate) fn tcsetwinsize(fd: BorrowedFd<'_>, winsize: &Winsize) -> Result<()> {
+    if unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCSWINSZ, winsize) } == -1 {
+        let err = nix::errno::Errno::last();
+        let err = format!("ioctl({fd:?}, TIOCSWINSZ) => {err}");
+        Err(Error::SysError(err))
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn setsid() -> Result<Pid> {
+    map_err!(unistd::setsid())
+}
+
+pub(crate) fn set_controlling_tty(fd: BorrowedFd<'_>) -> Result<()> {
+    if unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCSCTTY, 0) } == -1 {
+        let err = nix::errno::Errno::last();
+        let err = format!("ioctl({fd:?}, TIOCSCTTY) => {err}");
+        Err(Error::SysError(err))
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn send_fd(socket: &UnixStream, fd: BorrowedFd<'_>) -> Result<()> {
+    map_err!(scm_rights::send_fd(socket, fd))
+}
@@ -0,0 +1,352 @@
+use libseccomp::ScmpSyscall;
+use std::collections::HashMap;
+use std::io::PipeWriter;
+use std::io::prelude::*;
+
+use super::recorder::{read_cstr, read_u32, read_u64};
+use super::sys::{Pid, PtraceSyscallInfo};
+use crate::TraceEvent;
+use crate::seccomp::{translate_arch, translate_audit_arch};
+
+// Set in the AUDIT_ARCH_* values of the 64-bit architectures, see
+// linux/audit.h. Pointers are 4 bytes on the others, e.g. i386.
+const AUDIT_ARCH_64BIT: u32 = 0x8000_0000;
+
+// How an argument of a syscall is decoded.
+#[derive(Clone, Copy)]
+enum Arg {
+    Int,
+    Long,
+    Hex,
+    Ptr,
+    Fd,
+    DirFd,
+    Path,
+    Argv,
+    OpenFlags,
+    AtFlags,
+    Mode,
+}
+
+use Arg::*;
+
+// The arguments of the common syscalls, the others are shown as `...`.
+const SIGNATURES: &[(&str, &[Arg])] = &[
+    ("read", &[Fd, Ptr, Long]),
+    ("write", &[Fd, Ptr, Long]),
+    ("pread64", &[Fd, Ptr, Long, Long]),
+    ("pwrite64", &[Fd, Ptr, Long, Long]),
+    ("readv", &[Fd, Ptr, Int]),
+    ("writev", &[Fd, Ptr, Int]),
+    ("open", &[Path, OpenFlags, Mode]),
+    ("openat", &[DirFd, Path, OpenFlags, Mode]),
+    ("openat2", &[DirFd, Path, Ptr, Long]),
+    ("creat", &[Path, Mode]),
+    ("close", &[Fd]),
+    ("stat", &[Path, Ptr]),
+    ("lstat", &[Path, Ptr]),
+    ("fstat", &[Fd, Ptr]),
+    ("newfstatat", &[DirFd, Path, Ptr, AtFlags]),
+    ("statx", &[DirFd, Path, AtFlags, Hex, Ptr]),
+    ("access", &[Path, Int]),
+    ("faccessat", &[DirFd, Path, Int]),
+    ("faccessat2", &[DirFd, Path, Int, AtFlags]),
+    ("readlink", &[Path, Ptr, Long]),
+    ("readlinkat", &[DirFd, Path, Ptr, Long]),
+    ("execve", &[Path, Argv, Ptr]),
+    ("execveat", &[DirFd, Path, Argv, Ptr, AtFlags]),
+    ("chdir", &[Path]),
+    ("fchdir", &[Fd]),
+    ("getcwd", &[Ptr, Long]),
+    ("mkdir", &[Path, Mode]),
+    ("mkdirat", &[DirFd, Path, Mode]),
+    ("rmdir", &[Path]),
+    ("unlink", &[Path]),
+    ("unlinkat", &[DirFd, Path, AtFlags]),
+    ("rename", &[Path, Path]),
+    ("renameat", &[DirFd, Path, DirFd, Path]),
+    ("renameat2", &[DirFd, Path, DirFd, Path, Hex]),
+    ("link", &[Path, Path]),
+    ("linkat", &[DirFd, Path, DirFd, Path, AtFlags]),
+    ("symlink", &[Path, Path]),
+    ("symlinkat", &[Path, DirFd, Path]),
+    ("chmod", &[Path, Mode]),
+    ("fchmod", &[Fd, Mode]),
+    ("fchmodat", &[DirFd, Path, Mode]),
+    ("chown", &[Path, Int, Int]),
+    ("lchown", &[Path, Int, Int]),
+    ("fchown", &[Fd, Int, Int]),
+    ("fchownat", &[DirFd, Path, Int, Int, AtFlags]),
+    ("truncate", &[Path, Long]),
+    ("ftruncate", &[Fd, Long]),
+    ("dup", &[Fd]),
+    ("dup2", &[Fd, Fd]),
+    ("dup3", &[Fd, Fd, Hex]),
+    ("lseek", &[Fd, Long, Int]),
+    ("getdents64", &[Fd, Ptr, Long]),
+    ("ioctl", &[Fd, Hex, Ptr]),
+    ("fcntl", &[Fd, Int, Hex]),
+    ("mmap", &[Ptr, Long, Hex, Hex, Fd, Long]),
+    ("mprotect", &[Ptr, Long, Hex]),
+    ("munmap", &[Ptr, Long]),
+    ("brk", &[Ptr]),
+    ("pipe", &[Ptr]),
+    ("pipe2", &[Ptr, Hex]),
+    ("socket", &[Int, Int, Int]),
+    ("connect", &[Fd, Ptr, Int]),
+    ("bind", &[Fd, Ptr, Int]),
+    ("listen", &[Fd, Int]),
+    ("accept", &[Fd, Ptr, Ptr]),
+    ("accept4", &[Fd, Ptr, Ptr, Hex]),
+    ("mount", &[Path, Path, Path, Hex, Ptr]),
+    ("rt_sigaction", &[Int, Ptr, Ptr, Long]),
+    ("rt_sigprocmask", &[Int, Ptr, Ptr, Long]),
+    ("prctl", &[Int, Hex, Hex, Hex, Hex]),
+    ("arch_prctl", &[Hex, Ptr]),
+    ("set_tid_address", &[Ptr]),
+    ("getrandom", &[Ptr, Long, Hex]),
+    ("prlimit64", &[Int, Int, Ptr, Ptr]),
+    ("umount2", &[Path, Hex]),
+    ("clone", &[Hex, Ptr, Ptr, Ptr, Hex]),
+    ("clone3", &[Ptr, Long]),
+    ("wait4", &[Int, Ptr, Hex, Ptr]),
+    ("kill", &[Int, Int]),
+    ("exit", &[Int]),
+    ("exit_group", &[Int]),
+    ("fork", &[]),
+    ("vfork", &[]),
+    ("getpid", &[]),
+    ("getppid", &[]),
+    ("gettid", &[]),
+    ("getuid", &[]),
+    ("geteuid", &[]),
+    ("getgid", &[]),
+    ("getegid", &[]),
+];
+
+// The syscalls which do not return.
+const NORETURNS: &[&str] = &["exit", "exit_group"];
+
+const OPEN_FLAGS: &[(libc::c_int, &str)] = &[
+    (libc::O_CREAT, "O_CREAT"),
+    (libc::O_EXCL, "O_EXCL"),
+    (libc::O_NOCTTY, "O_NOCTTY"),
+    (libc::O_TRUNC, "O_TRUNC"),
+    (libc::O_APPEND, "O_APPEND"),
+    (libc::O_NONBLOCK, "O_NONBLOCK"),
+    (libc::O_DSYNC, "O_DSYNC"),
+    (libc::O_DIRECT, "O_DIRECT"),
+    (libc::O_TMPFILE, "O_TMPFILE"),
+    (libc::O_DIRECTORY, "O_DIRECTORY"),
+    (libc::O_NOFOLLOW, "O_NOFOLLOW"),
+    (libc::O_NOATIME, "O_NOATIME"),
+    (libc::O_CLOEXEC, "O_CLOEXEC"),
+    (libc::O_PATH, "O_PATH"),
+];
+
+const AT_FLAGS: &[(libc::c_int, &str)] = &[
+    (libc::AT_SYMLINK_NOFOLLOW, "AT_SYMLINK_NOFOLLOW"),
+    (libc::AT_REMOVEDIR, "AT_REMOVEDIR"),
+    (libc::AT_SYMLINK_FOLLOW, "AT_SYMLINK_FOLLOW"),
+    (libc::AT_NO_AUTOMOUNT, "AT_NO_AUTOMOUNT"),
+    (libc::AT_EMPTY_PATH, "AT_EMPTY_PATH"),
+];
+
+// The maximum number of the elements of an argv shown.
+const ARGV_MAX: usize = 32;
+
+/// Traces the syscalls of the traced processes, and sends them to the main
+/// process through a pipe.
+pub(crate) struct Tracer {
+    writer: PipeWriter,
+    // The syscalls made by the internal process to set up itself, before it
+    // executes the program, are not traced.
+    started: bool,
+    sysnames: HashMap<(u32, u64), String>,
+    // The syscalls which have entered but not yet exited, the arguments are
+    // decoded at the entry since the memory may be gone at the exit.
+    pending: HashMap<Pid, TraceEvent>,
+}
+
+impl Tracer {
+    pub(crate) fn new(writer: PipeWriter) -> Self {
+        Self {
+            writer,
+            started: false,
+            sysnames: HashMap::new(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Traces the syscall the process is stopped at.
+    pub(crate) fn trace(&mut self, pid: Pid, info: &PtraceSyscallInfo) {
+        match info.op {
+            PtraceSyscallInfo::OP_ENTRY => self.trace_entry(pid, info),
+            PtraceSyscallInfo::OP_EXIT => self.trace_exit(pid, info),
+            _ => {}
+        }
+    }
+
+    /// Sends the unfinished syscall of a process which is gone, e.g. killed
+    /// by a signal, without a return value.
+    pub(crate) fn exited(&mut self, pid: Pid) {
+        if let Some(event) = self.pending.remove(&pid) {
+            self.send(&event);
+        }
+    }
+
+    fn trace_entry(&mut self, pid: Pid, info: &PtraceSyscallInfo) {
+        let sysname = self
+            .sysnames
+            .entry((info.arch, info.nr))
+            .or_insert_with(|| sysname(info.arch, info.nr))
+            .clone();
+        self.started |= sysname == "execve";
+        if !self.started {
+            return;
+        }
+
+        let args = match SIGNATURES.iter().find(|(name, _)| *name == sysname) {
+            Some((_, sig)) => sig
+                .iter()
+                .zip(info.args)
+                .map(|(arg, value)| decode_arg(pid, info.arch, *arg, value))
+                .collect(),
+            None => vec!["...".to_string()],
+        };
+
+        let event = TraceEvent {
+            pid: pid.as_raw(),
+            sysname,
+            args,
+            retval: None,
+        };
+        match NORETURNS.contains(&event.sysname.as_str()) {
+            true => self.send(&event),
+            false => _ = self.pending.insert(pid, event),
+        }
+    }
+
+    fn trace_exit(&mut self, pid: Pid, info: &PtraceSyscallInfo) {
+        let Some(mut event) = self.pending.remove(&pid) else {
+            return;
+        };
+        event.retval = Some(info.rval());
+        self.send(&event);
+    }
+
+    // The main process may be gone, the error is ignored since the tracees
+    // are not affected.
+    fn send(&mut self, event: &TraceEvent) {
+        let config = bincode::config::standard();
+        if let Ok(encoded) = bincode::serde::encode_to_vec(event, config) {
+            _ = self.writer.write_all(&encoded);
+        }
+    }
+}
+
+fn sysname(arch: u32, nr: u64) -> String {
+    let syscall = nr as i32;
+    translate_audit_arch(arch, syscall)
+        .and_then(|arch| {
+            ScmpSyscall::from_raw_syscall(syscall)
+                .get_name_by_arch(translate_arch(arch))
+                .ok()
+        })
+        .unwrap_or_else(|| format!("syscall_{nr}"))
+}
+
+fn decode_arg(pid: Pid, arch: u32, arg: Arg, value: u64) -> String {
+    match arg {
+        Int => (value as i32).to_string(),
+        Long => (value as i64).to_string(),
+        Hex => format!("{value:#x}"),
+        Ptr => decode_ptr(value),
+        Fd => (value as i32).to_string(),
+        DirFd => match value as i32 {
+            libc::AT_FDCWD => "AT_FDCWD".to_string(),
+            fd => fd.to_string(),
+        },
+        Path => match read_cstr(pid, value as usize) {
+            Some(path) => format!("{path:?}"),
+            None => decode_ptr(value),
+        },
+        Argv => decode_argv(pid, arch, value),
+        OpenFlags => decode_open_flags(value as libc::c_int),
+        AtFlags => decode_flags(value as libc::c_int, AT_FLAGS),
+        Mode => match value as u32 {
+            0 => "0".to_string(),
+            mode => format!("0{mode:o}"),
+        },
+    }
+}
+
+fn decode_ptr(value: u64) -> String {
+    match value {
+        0 => "NULL".to_string(),
+        _ => format!("{value:#x}"),
+    }
+}
+
+fn decode_argv(pid: Pid, arch: u32, addr: u64) -> String {
+    if addr == 0 {
+        return "NULL".to_string();
+    }
+
+    let ptr_size = match arch & AUDIT_ARCH_64BIT {
+        0 => 4,
+        _ => 8,
+    };
+    let mut argv = vec![];
+    for i in 0..=ARGV_MAX {
+        let ptr_addr = addr as usize + i * ptr_size;
+        let ptr = match ptr_size {
+            4 => read_u32(pid, ptr_addr).map(u64::from),
+            _ => read_u64(pid, ptr_addr),
+        };
+        let Some(ptr) = ptr else {
+            return decode_ptr(addr);
+        };
+        if ptr == 0 {
+            break;
+        }
+        if i == ARGV_MAX {
+            argv.push("...".to_string());
+            break;
+        }
+        match read_cstr(pid, ptr as usize) {
+            Some(arg) => argv.push(format!("{arg:?}")),
+            None => argv.push(decode_ptr(ptr)),
+        }
+    }
+    format!("[{}]", argv.join(", "))
+}
+
+fn decode_open_flags(flags: libc::c_int) -> String {
+    let mode = match flags & libc::O_ACCMODE {
+        libc::O_WRONLY => "O_WRONLY",
+        libc::O_RDWR => "O_RDWR",
+        _ => "O_RDONLY",
+    };
+    match decode_flags(flags & !libc::O_ACCMODE, OPEN_FLAGS).as_str() {
+        "0" => mode.to_string(),
+        rest => format!("{mode}|{rest}"),
+    }
+}
+
+fn decode_flags(mut flags: libc::c_int, names: &[(libc::c_int, &str)]) -> String {
+    let mut decoded = vec![];
+    for (flag, name) in names {
+        if flags & flag == *flag && *flag != 0 {
+            decoded.push(name.to_string());
+            flags &= !flag;
+        }
+    }
+    if flags != 0 {
+        decoded.push(format!("{flags:#x}"));
+    }
+    match decoded.is_empty() {
+        true => "0".to_string(),
+        false => decoded.join("|"),
+    }
+}
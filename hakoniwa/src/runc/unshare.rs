@@ -1,6 +1,16 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::io::prelude::*;
+use std::os::fd::{AsFd, OwnedFd};
+
 use super::error::*;
-use super::sys::{self, FsFlags, MsFlags, PathBuf};
-use crate::{Container, FsOperation, GroupFile, MountOptions, Namespace, PasswdFile, Runctl};
+use super::sys::{self, CloneFlags, ForkResult, FsFlags, MsFlags, Path, PathBuf};
+use crate::unshare::{IdMap, NamespaceRefImp};
+use crate::{
+    Container, FsOperation, GroupFile, Mount, MountOptions, Namespace, PasswdFile, Runctl,
+};
+
+pub(crate) type IdmappedMounts = HashMap<String, OwnedFd>;
 
 macro_rules! if_namespace_then {
     ($namespace:expr, $container:ident, $fn:ident) => {
@@ -13,22 +23,30 @@ macro_rules! if_namespace_then {
 }
 
 pub(crate) fn newuser(container: &Container) -> Result<()> {
+    setns(container)?;
     if container.namespaces.is_empty() {
         return Ok(());
     }
 
-    sys::unshare(container.get_namespaces_clone_flags())?;
+    // The PID namespace is created after the mount, so that helper programs,
+    // e.g. fuse-overlayfs, will not become the init process of it.
+    let mut clone_flags = container.get_namespaces_clone_flags();
+    clone_flags.remove(CloneFlags::CLONE_NEWPID);
+    sys::unshare(clone_flags)?;
     if_namespace_then!(Namespace::User, container, setuidmap)?;
     if_namespace_then!(Namespace::User, container, setgidmap)?;
     Ok(())
 }
 
-pub(crate) fn newns(container: &Container) -> Result<()> {
+pub(crate) fn newns(container: &Container, idmapped: IdmappedMounts) -> Result<()> {
     if container.namespaces.is_empty() {
         return Ok(());
     }
 
-    if_namespace_then!(Namespace::Mount, container, mount)?;
+    if container.namespaces.contains(&Namespace::Mount) {
+        mount(container, idmapped)?;
+    }
+    if_namespace_then!(Namespace::Pid, container, newpid)?;
     Ok(())
 }
 
@@ -43,8 +61,47 @@ pub(crate) fn tidyup(container: &Container) -> Result<()> {
     Ok(())
 }
 
+// Join existing namespaces.
+//
+// The USER namespace is joined first, which gives us privileges over the
+// namespaces owned by it. The others are joined before creating the new USER
+// namespace, as the privileges over their owning user namespace will be lost
+// after that.
+fn setns(container: &Container) -> Result<()> {
+    if container.joined_namespaces.is_empty() {
+        return Ok(());
+    }
+
+    // Open all of them first, the paths may resolve differently after
+    // joining a MOUNT namespace.
+    let mut fds: Vec<(Namespace, OwnedFd)> = vec![];
+    for namespace in [
+        Namespace::User,
+        Namespace::Cgroup,
+        Namespace::Ipc,
+        Namespace::Network,
+        Namespace::Pid,
+        Namespace::Uts,
+        Namespace::Mount,
+    ] {
+        let fd = match container.joined_namespaces.get(&namespace) {
+            Some(ns) => match &ns.0 {
+                NamespaceRefImp::Path(path) => sys::open_rdonly(path)?,
+                NamespaceRefImp::Fd(fd) => sys::dup(fd.as_fd())?,
+            },
+            None => continue,
+        };
+        fds.push((namespace, fd));
+    }
+
+    for (namespace, fd) in fds {
+        sys::setns(fd.as_fd(), namespace.to_clone_flag())?;
+    }
+    Ok(())
+}
+
 // [pivot_root]: https://man7.org/linux/man-pages/man2/pivot_root.2.html
-fn mount(container: &Container) -> Result<()> {
+fn mount(container: &Container, idmapped: IdmappedMounts) -> Result<()> {
     // Get the mount point for the container root fs.
     let new_root = container.rootdir_abspath.as_path();
 
@@ -52,14 +109,24 @@ fn mount(container: &Container) -> Result<()> {
     // shared propagation (which would cause pivot_root() to
     // return an error), and prevent propagation of mount
     // events to the initial mount namespace.
-    sys::mount_make_private("/")?;
+    //
+    // Mount events from the initial mount namespace are still
+    // received, so that bind mounts can opt in to them with
+    // MountOptions::SLAVE.
+    sys::mount_propagation("/", MsFlags::MS_SLAVE | MsFlags::MS_REC)?;
 
     // Ensure that "new_root" is a mount point.
-    sys::mount(new_root, new_root, MsFlags::MS_BIND)?;
+    if let Some(mount) = &container.rootdir_overlaymount {
+        mount_overlay(container, mount, new_root)?;
+    } else {
+        sys::mount(new_root, new_root, MsFlags::MS_BIND)?;
+        sys::mount_propagation(new_root, MsFlags::MS_PRIVATE)?;
+    }
 
     // Initialize rootfs under "new_root".
+    let mount_api = sys::mount_setattr_supported();
     sys::chdir(new_root)?;
-    initialize_rootfs(container)?;
+    initialize_rootfs(container, idmapped, mount_api)?;
 
     // Create directory to which "old_root" will be pivoted.
     sys::mkdir_p(".oldrootfs")?;
@@ -75,7 +142,9 @@ fn mount(container: &Container) -> Result<()> {
     sys::rmdir("/.oldrootfs")?;
 
     // Make MsFlags::MS_RDONLY option work properly.
-    remount_rdonly(container)?;
+    if !mount_api {
+        remount_rdonly(container)?;
+    }
 
     // Apply filesystem operations.
     apply_fs_operations(container)?;
@@ -85,7 +154,14 @@ fn mount(container: &Container) -> Result<()> {
 }
 
 // Initialize rootfs under Container#rootdir.
-fn initialize_rootfs(container: &Container) -> Result<()> {
+//
+// With the new mount API, mounts are created detached, then attached once
+// the mount flags are applied, so that no remount is required.
+fn initialize_rootfs(
+    container: &Container,
+    mut idmapped: IdmappedMounts,
+    mount_api: bool,
+) -> Result<()> {
     for mount in container.get_mounts() {
         let target_relpath = &mount
             .target
@@ -94,7 +170,9 @@ fn initialize_rootfs(container: &Container) -> Result<()> {
 
         // Mount procfs.
         if mount.fstype == "proc" {
-            if !container.namespaces.contains(&Namespace::Pid) {
+            if !container.namespaces.contains(&Namespace::Pid)
+                && !container.joined_namespaces.contains_key(&Namespace::Pid)
+            {
                 Err(Error::MountProcfsEPERM)?;
             }
 
@@ -108,6 +186,12 @@ fn initialize_rootfs(container: &Container) -> Result<()> {
         // Mount tmpfs.
         if mount.fstype == "tmpfs" {
             sys::mkdir_p(target_relpath)?;
+            if mount_api {
+                let flags = mount.options.to_ms_flags();
+                let fd = sys::fsmount(&mount.fstype, &mount.source, flags)?;
+                sys::move_mount(fd.as_fd(), target_relpath)?;
+                continue;
+            }
             sys::mount_filesystem(
                 &mount.fstype,
                 &mount.source,
@@ -125,7 +209,14 @@ fn initialize_rootfs(container: &Container) -> Result<()> {
                 target_relpath,
                 MsFlags::MS_BIND | MsFlags::MS_NOSUID,
             )?;
-            initialize_devfs(target_relpath)?;
+            initialize_devfs(container, target_relpath)?;
+            continue;
+        }
+
+        // Mount overlayfs.
+        if mount.fstype == "overlay" {
+            sys::mkdir_p(target_relpath)?;
+            mount_overlay(container, mount, target_relpath)?;
             continue;
         }
 
@@ -145,15 +236,165 @@ fn initialize_rootfs(container: &Container) -> Result<()> {
             PathBuf::from(&target_relpath).parent().map(sys::mkdir_p);
             sys::touch(target_relpath)?
         }
-        sys::mount(source_abspath, target_relpath, mount.options.to_ms_flags())?;
+        let recursive = mount.options.contains(MountOptions::REC);
+        let fd = match idmapped.remove(&mount.target) {
+            Some(fd) => Some(fd),
+            None if mount_api => Some(sys::open_tree(source_abspath, recursive)?),
+            None => None,
+        };
+        if let Some(fd) = fd {
+            let options = mount.options.to_ms_flags();
+            let res = sys::mount_setattr_flags(fd.as_fd(), options, recursive);
+            if res.is_err() && container.runctl.contains(&Runctl::MountFallback) {
+                let options = unprivileged_mount_flags(source_abspath, options)?;
+                sys::mount_setattr_flags(fd.as_fd(), options, recursive)?;
+            } else {
+                res?;
+            }
+            sys::move_mount(fd.as_fd(), target_relpath)?;
+        } else {
+            sys::mount(source_abspath, target_relpath, mount.options.to_ms_flags())?;
+        }
+        sys::mount_propagation(target_relpath, mount.options.to_propagation_ms_flags())?;
     }
     Ok(())
 }
 
+// Create id-mapped mounts, which are attached to the container root fs later.
+//
+// Changing the idmapping of a mount requires the CAP_SYS_ADMIN capability in
+// the USER namespace that owns the filesystem, thus it must be done before
+// entering the new USER namespace.
+pub(crate) fn idmapped(container: &Container) -> Result<IdmappedMounts> {
+    let mut idmapped = IdmappedMounts::new();
+    let mounts = container
+        .get_mounts()
+        .into_iter()
+        .filter(|mount| mount.options.contains(MountOptions::IDMAP))
+        .collect::<Vec<_>>();
+    if mounts.is_empty() || !container.namespaces.contains(&Namespace::Mount) {
+        return Ok(idmapped);
+    }
+
+    let userns = idmapped_userns(container)?;
+    for mount in mounts {
+        let recursive = mount.options.contains(MountOptions::REC);
+        let fd = sys::open_tree(&mount.source, recursive)?;
+        sys::mount_setattr_idmap(fd.as_fd(), userns.as_fd(), recursive)?;
+        idmapped.insert(mount.target.clone(), fd);
+    }
+    Ok(idmapped)
+}
+
+// Create a new USER namespace for id-mapped mounts.
+//
+// By default, it has the same [ug]idmap as the container. Otherwise, the
+// current user is mapped to the owner in the container.
+//
+// A helper process is required, as the current process can not leave its
+// USER namespace.
+fn idmapped_userns(container: &Container) -> Result<OwnedFd> {
+    let (uidmaps, gidmaps) = match &container.idmapped_owner {
+        Some((uidmap, gidmap)) => (
+            vec![idmapped_owner(uidmap, container.uidmaps.as_deref())?],
+            vec![idmapped_owner(gidmap, container.gidmaps.as_deref())?],
+        ),
+        None => (
+            container.uidmaps.clone().unwrap_or_default(),
+            container.gidmaps.clone().unwrap_or_default(),
+        ),
+    };
+    let uid_map = uidmaps.iter().map(|m| m.to_line()).collect::<String>();
+    let gid_map = gidmaps.iter().map(|m| m.to_line()).collect::<String>();
+
+    let (mut ready_reader, mut ready_writer) = std::io::pipe()?;
+    let (mut hold_reader, hold_writer) = std::io::pipe()?;
+    match sys::fork()? {
+        ForkResult::Parent { child } => {
+            drop(ready_writer);
+            drop(hold_reader);
+
+            let mut ready = [0];
+            let fd = ready_reader
+                .read_exact(&mut ready)
+                .map_err(Error::from)
+                .and_then(|_| {
+                    sys::fwrite(format!("/proc/{child}/uid_map"), &uid_map)?;
+                    sys::fwrite(format!("/proc/{child}/gid_map"), &gid_map)?;
+                    sys::open_rdonly(format!("/proc/{child}/ns/user"))
+                });
+
+            drop(hold_writer);
+            sys::waitpid(child)?;
+            fd
+        }
+        ForkResult::Child => {
+            drop(ready_reader);
+            drop(hold_writer);
+            if sys::unshare(CloneFlags::CLONE_NEWUSER).is_ok() {
+                _ = ready_writer.write_all(&[0]);
+            }
+            drop(ready_writer);
+
+            // Wait until the parent closes the pipe.
+            _ = hold_reader.read(&mut [0]);
+            std::process::exit(0)
+        }
+    }
+}
+
+// Map the owner on the host to the host id of the owner in the container.
+fn idmapped_owner(owner: &IdMap, idmaps: Option<&[IdMap]>) -> Result<IdMap> {
+    let id = owner.container_id;
+    idmaps
+        .unwrap_or_default()
+        .iter()
+        .find(|m| m.container_id <= id && id - m.container_id < m.size)
+        .map(|m| IdMap {
+            container_id: owner.host_id,
+            host_id: m.host_id + (id - m.container_id),
+            size: 1,
+        })
+        .ok_or(Error::IdmappedOwnerNotMapped(id))
+}
+
+// Mount overlayfs on "target".
+//
+// Unprivileged overlayfs uses the "user.overlay." xattr namespace instead
+// of the "trusted.overlay." one, which requires kernel 5.11.
+fn mount_overlay<P: AsRef<Path> + Debug>(
+    container: &Container,
+    mount: &Mount,
+    target: P,
+) -> Result<()> {
+    for path in mount.data_paths() {
+        path.strip_prefix('/')
+            .ok_or(Error::MountSourcePathMustBeAbsolute(path.to_string()))?;
+    }
+
+    let data = if container.namespaces.contains(&Namespace::User)
+        || container.joined_namespaces.contains_key(&Namespace::User)
+    {
+        format!("userxattr,{}", mount.data)
+    } else {
+        mount.data.clone()
+    };
+    let res = sys::mount_overlay(&target, mount.options.to_ms_flags(), &data);
+    if res.is_ok() {
+        return Ok(());
+    }
+
+    if container.runctl.contains(&Runctl::MountFallback) {
+        sys::fuse_overlayfs(&target, &mount.data)
+    } else {
+        res
+    }
+}
+
 // Initialize devfs under "target_relpath".
 //
 // [bubblewrap#SETUP_MOUNT_DEV]: https://github.com/containers/bubblewrap/blob/9ca3b05ec787acfb4b17bed37db5719fa777834f/bubblewrap.c#L1370
-fn initialize_devfs(target_relpath: &str) -> Result<()> {
+fn initialize_devfs(container: &Container, target_relpath: &str) -> Result<()> {
     for dev in ["null", "zero", "full", "random", "urandom", "tty"] {
         let source = format!("/dev/{dev}");
         let target = format!("{target_relpath}/{dev}");
@@ -181,13 +422,14 @@ fn initialize_devfs(target_relpath: &str) -> Result<()> {
     let pts_target_relpath = format!("{target_relpath}/pts");
     let pts_flags = MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC;
     sys::mkdir_p(&pts_target_relpath)?;
-    sys::mount_filesystem("devpts", "devpts", pts_target_relpath, pts_flags)?;
+    sys::mount_devpts(pts_target_relpath, pts_flags)?;
 
     let ptmx_original = "pts/ptmx".to_string();
     let ptmx_link = format!("{target_relpath}/ptmx");
     sys::symlink(ptmx_original, ptmx_link)?;
 
-    if sys::isatty()? {
+    // The console will be the pseudo-terminal allocated later if required.
+    if !container.pty && sys::isatty()? {
         let source = sys::ttyname()?;
         let target = format!("{target_relpath}/console");
         let flags = MsFlags::MS_BIND | MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC;
@@ -294,16 +536,26 @@ fn mount2(container: &Container) -> Result<()> {
         sys::rmdir("/.oldproc")?;
     }
 
-    if !container.runctl.contains(&Runctl::RootdirRW) {
-        let mut options = MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_REMOUNT;
-        options = unprivileged_mount_flags(".", options)?;
-        options.insert(MsFlags::MS_RDONLY);
-        sys::mount("", ".", options)?;
+    if !container.runctl.contains(&Runctl::RootdirRW) && container.rootdir_overlaymount.is_none() {
+        if sys::mount_setattr_supported() {
+            let fd = sys::open_rdonly(".")?;
+            sys::mount_setattr_flags(fd.as_fd(), MsFlags::MS_RDONLY, false)?;
+        } else {
+            let mut options = MsFlags::MS_BIND | MsFlags::MS_REC | MsFlags::MS_REMOUNT;
+            options = unprivileged_mount_flags(".", options)?;
+            options.insert(MsFlags::MS_RDONLY);
+            sys::mount("", ".", options)?;
+        }
     }
 
     Ok(())
 }
 
+// Create the PID namespace, the next child will be the init process of it.
+fn newpid(_: &Container) -> Result<()> {
+    sys::unshare(CloneFlags::CLONE_NEWPID)
+}
+
 // UID map to use for the user namespace.
 fn setuidmap(container: &Container) -> Result<()> {
     if container.needs_mainp_setup_ugidmap() {
@@ -0,0 +1,110 @@
+use std::os::fd::{AsFd, OwnedFd};
+use std::time::{Duration, Instant};
+
+use super::error::*;
+use super::sys::{
+    self, Pid, PollFd, PollFlags, PollTimeout, SfdFlags, SigSet, SigmaskHow, Signal, SignalFd,
+    WaitPidFlag, WaitStatus,
+};
+
+/// Waits for the internal process through its pidfd.
+///
+/// The pidfd becomes readable when the process exits, and SIGCHLD is received
+/// via a signalfd for ptrace stops. Both are polled with the remaining time,
+/// so no signal handler and no global state are required for the timeout.
+pub(crate) struct Waiter {
+    pidfd: OwnedFd,
+    sigchld: SignalFd,
+    deadline: Option<Instant>,
+    descendants: bool,
+}
+
+impl Waiter {
+    /// Constructs a new Waiter for the child process `child`.
+    pub(crate) fn new(child: Pid) -> Result<Self> {
+        let mut mask = SigSet::empty();
+        mask.add(Signal::SIGCHLD);
+        sys::sigprocmask(SigmaskHow::SIG_BLOCK, &mask)?;
+
+        let flags = SfdFlags::SFD_NONBLOCK | SfdFlags::SFD_CLOEXEC;
+        Ok(Self {
+            pidfd: sys::pidfd_open(child)?,
+            sigchld: sys::signalfd(&mask, flags)?,
+            deadline: None,
+            descendants: false,
+        })
+    }
+
+    /// Kills the child process if it is still alive after `secs` seconds.
+    pub(crate) fn timeout(&mut self, secs: u64) {
+        self.deadline = Some(Instant::now() + Duration::from_secs(secs));
+    }
+
+    /// Also waits for the descendants of the child process, which are traced
+    /// through PTRACE_O_TRACEFORK, etc.
+    pub(crate) fn descendants(&mut self) {
+        self.descendants = true;
+    }
+
+    /// Kills the child process.
+    pub(crate) fn kill(&self) -> Result<()> {
+        sys::pidfd_send_signal(self.pidfd.as_fd(), Signal::SIGKILL)
+    }
+
+    /// Waits for the child process to exit or stop.
+    pub(crate) fn wait(&mut self) -> Result<WaitStatus> {
+        if self.descendants {
+            return self.wait_descendants();
+        }
+
+        loop {
+            let flags = WaitPidFlag::WEXITED | WaitPidFlag::WNOHANG;
+            match sys::waitid(self.pidfd.as_fd(), flags)? {
+                WaitStatus::StillAlive => self.poll()?,
+                // waitid reports a signal-delivery-stop as CLD_TRAPPED, keep
+                // it consistent with waitpid.
+                WaitStatus::PtraceEvent(pid, signal, 0) => {
+                    return Ok(WaitStatus::Stopped(pid, signal));
+                }
+                ws => return Ok(ws),
+            }
+        }
+    }
+
+    fn wait_descendants(&mut self) -> Result<WaitStatus> {
+        loop {
+            let flags = WaitPidFlag::__WALL | WaitPidFlag::WNOHANG;
+            match sys::waitpid_any(flags)? {
+                WaitStatus::StillAlive => self.poll()?,
+                ws => return Ok(ws),
+            }
+        }
+    }
+
+    fn poll(&mut self) -> Result<()> {
+        let timeout = match self.deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                PollTimeout::try_from(remaining).unwrap_or(PollTimeout::MAX)
+            }
+            None => PollTimeout::NONE,
+        };
+
+        let mut fds = [
+            PollFd::new(self.pidfd.as_fd(), PollFlags::POLLIN),
+            PollFd::new(self.sigchld.as_fd(), PollFlags::POLLIN),
+        ];
+        if sys::poll(&mut fds, timeout)? > 0 {
+            while let Ok(Some(_)) = self.sigchld.read_signal() {}
+        }
+
+        // Time limit exceeded.
+        if let Some(deadline) = self.deadline
+            && Instant::now() >= deadline
+        {
+            self.deadline = None;
+            sys::pidfd_send_signal(self.pidfd.as_fd(), Signal::SIGKILL)?;
+        }
+        Ok(())
+    }
+}
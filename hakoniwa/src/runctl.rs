@@ -5,8 +5,9 @@ pub enum Runctl {
     RootdirRW,
 
     /// Fallback when the specific configuration is not applicable. E.g try to
-    /// remount a bind mount again after the first attempt failed on source
-    /// filesystems that have nodev, noexec, nosuid, etc.
+    /// apply the flags of a bind mount again after the first attempt failed on
+    /// source filesystems that have nodev, noexec, nosuid, etc, or mount overlayfs
+    /// via fuse-overlayfs when the kernel refuses to mount it.
     MountFallback,
 
     /// Get memory usage through proc_pid_smaps_rollup at exit.
@@ -18,4 +19,77 @@ pub enum Runctl {
     /// Allow the internal process to gain more privileges than its parent
     /// process. Aka do not set the no_new_privs bit.
     AllowNewPrivs,
+
+    /// Fail if the seccomp filter has rules that cannot be applied, e.g. a
+    /// misspelled syscall name, instead of skipping them.
+    SeccompStrict,
+
+    /// Trace the internal process and its descendants, and report the syscall
+    /// blocked by a seccomp rule with the `KillProcess`, `KillThread` or
+    /// `Trap` action in [ExitStatus::blocked_syscall].
+    ///
+    /// The container cannot be traced by another tracer inside it, e.g. gdb
+    /// or strace, when it is set.
+    ///
+    /// [ExitStatus::blocked_syscall]: crate::ExitStatus::blocked_syscall
+    ReportBlockedSyscall,
+
+    /// Trace the internal process and its descendants, and record every
+    /// syscall they make into [ExitStatus::recorded_syscalls], which can be
+    /// turned into a minimal allow-list with
+    /// [Filter::from_recorded_syscalls].
+    ///
+    /// Recording starts once the seccomp filter is loaded, or at the execve
+    /// of the program without one, so the syscalls made by the container to
+    /// set itself up are not recorded. Syscalls are only seen after passing
+    /// the seccomp filter, if any.
+    ///
+    /// [ExitStatus::recorded_syscalls]: crate::ExitStatus::recorded_syscalls
+    /// [Filter::from_recorded_syscalls]: crate::seccomp::Filter::from_recorded_syscalls
+    RecordSyscalls,
+
+    /// Trace the internal process and its descendants, and record the
+    /// directories their filesystem syscalls access into
+    /// [ExitStatus::recorded_fs_accesses], which can be turned into landlock
+    /// rules with [Ruleset::from_recorded_fs_accesses].
+    ///
+    /// Only successful syscalls are recorded. It requires the `seccomp`
+    /// feature to decode the syscalls, the command fails to spawn without it.
+    ///
+    /// [ExitStatus::recorded_fs_accesses]: crate::ExitStatus::recorded_fs_accesses
+    /// [Ruleset::from_recorded_fs_accesses]: crate::landlock::Ruleset::from_recorded_fs_accesses
+    RecordFsAccess,
+
+    /// Trace the internal process and its descendants, and send every
+    /// syscall they make, with its decoded arguments and return value, to
+    /// the sink set by [Container::trace_sink], or the stderr of the main
+    /// process by default.
+    ///
+    /// It requires the `seccomp` feature to decode the syscalls.
+    ///
+    /// [Container::trace_sink]: crate::Container::trace_sink
+    TraceSyscalls,
+
+    /// Fail if the landlock ruleset is enforced below the given level, e.g.
+    /// on an older kernel which supports only a part of the restrictions
+    /// in [CompatMode::Relax]. Without a ruleset, nothing is enforced.
+    ///
+    /// The status actually achieved is reported in
+    /// [ExitStatus::landlock_status].
+    ///
+    /// [CompatMode::Relax]: crate::landlock::CompatMode::Relax
+    /// [ExitStatus::landlock_status]: crate::ExitStatus::landlock_status
+    LandlockRequire(crate::LandlockRulesetStatus),
+
+    /// Restrict the filesystem with the landlock rules generated from the
+    /// mounts of the container, see [Ruleset::from_container]. The rules of
+    /// the ruleset set by [Container::landlock_ruleset], if any, are added
+    /// to them.
+    ///
+    /// It requires the `landlock` feature, the command fails to spawn
+    /// without it.
+    ///
+    /// [Ruleset::from_container]: crate::landlock::Ruleset::from_container
+    /// [Container::landlock_ruleset]: crate::Container::landlock_ruleset
+    LandlockFromMounts,
 }
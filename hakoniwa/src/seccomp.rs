@@ -3,11 +3,26 @@
 mod action;
 mod arch;
 mod argcmp;
+mod check;
+mod compiled_filter;
 mod filter;
+mod group;
+mod notify;
+mod oci;
 mod rule;
 
+pub mod presets;
+
 pub use action::Action;
 pub use arch::Arch;
 pub use argcmp::{ArgCmp, ArgCmpOp};
+pub use check::FilterCheck;
+pub use compiled_filter::CompiledFilter;
 pub use filter::Filter;
+pub use group::SyscallGroup;
+pub use notify::{Notification, Response, SeccompHandler};
 pub use rule::Rule;
+
+pub(crate) use arch::translate_arch;
+pub(crate) use compiled_filter::{SockFilter, translate_audit_arch};
+pub(crate) use notify::Notifier;
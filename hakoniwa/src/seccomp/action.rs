@@ -10,3 +10,20 @@ pub enum Action {
     Trace(u16),
     Trap,
 }
+
+impl Action {
+    /// Returns the precedence of the action when multiple filters match a
+    /// syscall, the same as the kernel, see seccomp(2).
+    pub(crate) fn precedence(&self) -> u8 {
+        match self {
+            Action::KillProcess => 7,
+            Action::KillThread => 6,
+            Action::Trap => 5,
+            Action::Errno(_) => 4,
+            Action::Notify => 3,
+            Action::Trace(_) => 2,
+            Action::Log => 1,
+            Action::Allow => 0,
+        }
+    }
+}
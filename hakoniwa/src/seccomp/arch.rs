@@ -1,3 +1,5 @@
+use libseccomp::ScmpArch;
+
 /// Represents a CPU architecture.
 #[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
 pub enum Arch {
@@ -27,38 +29,130 @@ pub enum Arch {
     Sh,
 }
 
+// The name, the name in OCI seccomp profiles, and the libseccomp value of
+// each architecture.
+const ARCHES: [(Arch, &str, &str, ScmpArch); 24] = [
+    (Arch::Native, "native", "SCMP_ARCH_NATIVE", ScmpArch::Native),
+    (Arch::X86, "x86", "SCMP_ARCH_X86", ScmpArch::X86),
+    (Arch::X8664, "x86_64", "SCMP_ARCH_X86_64", ScmpArch::X8664),
+    (Arch::X32, "x32", "SCMP_ARCH_X32", ScmpArch::X32),
+    (Arch::Arm, "arm", "SCMP_ARCH_ARM", ScmpArch::Arm),
+    (
+        Arch::Aarch64,
+        "aarch64",
+        "SCMP_ARCH_AARCH64",
+        ScmpArch::Aarch64,
+    ),
+    (
+        Arch::Loongarch64,
+        "loongarch64",
+        "SCMP_ARCH_LOONGARCH64",
+        ScmpArch::Loongarch64,
+    ),
+    (Arch::M68k, "m68k", "SCMP_ARCH_M68K", ScmpArch::M68k),
+    (Arch::Mips, "mips", "SCMP_ARCH_MIPS", ScmpArch::Mips),
+    (Arch::Mips64, "mips64", "SCMP_ARCH_MIPS64", ScmpArch::Mips64),
+    (
+        Arch::Mips64n32,
+        "mips64n32",
+        "SCMP_ARCH_MIPS64N32",
+        ScmpArch::Mips64N32,
+    ),
+    (Arch::Mipsel, "mipsel", "SCMP_ARCH_MIPSEL", ScmpArch::Mipsel),
+    (
+        Arch::Mipsel64,
+        "mipsel64",
+        "SCMP_ARCH_MIPSEL64",
+        ScmpArch::Mipsel64,
+    ),
+    (
+        Arch::Mipsel64n32,
+        "mipsel64n32",
+        "SCMP_ARCH_MIPSEL64N32",
+        ScmpArch::Mipsel64N32,
+    ),
+    (Arch::Ppc, "ppc", "SCMP_ARCH_PPC", ScmpArch::Ppc),
+    (Arch::Ppc64, "ppc64", "SCMP_ARCH_PPC64", ScmpArch::Ppc64),
+    (
+        Arch::Ppc64le,
+        "ppc64le",
+        "SCMP_ARCH_PPC64LE",
+        ScmpArch::Ppc64Le,
+    ),
+    (Arch::S390, "s390", "SCMP_ARCH_S390", ScmpArch::S390),
+    (Arch::S390x, "s390x", "SCMP_ARCH_S390X", ScmpArch::S390X),
+    (Arch::Parisc, "parisc", "SCMP_ARCH_PARISC", ScmpArch::Parisc),
+    (
+        Arch::Parisc64,
+        "parisc64",
+        "SCMP_ARCH_PARISC64",
+        ScmpArch::Parisc64,
+    ),
+    (
+        Arch::Riscv64,
+        "riscv64",
+        "SCMP_ARCH_RISCV64",
+        ScmpArch::Riscv64,
+    ),
+    (Arch::Sheb, "sheb", "SCMP_ARCH_SHEB", ScmpArch::Sheb),
+    (Arch::Sh, "sh", "SCMP_ARCH_SH", ScmpArch::Sh),
+];
+
+impl Arch {
+    /// Returns the architecture of the running kernel.
+    pub(crate) fn runtime() -> Self {
+        translate_scmp_arch(ScmpArch::native()).unwrap_or(Arch::Native)
+    }
+
+    /// Parses an architecture in OCI seccomp profiles, e.g. `SCMP_ARCH_X86_64`.
+    pub(crate) fn from_oci_str(arch: &str) -> Option<Self> {
+        ARCHES.iter().find(|row| row.2 == arch).map(|row| row.0)
+    }
+
+    /// Returns the name of the architecture in OCI seccomp profiles.
+    pub(crate) fn to_oci_str(self) -> &'static str {
+        ARCHES.iter().find(|row| row.0 == self).expect("ARCHES").2
+    }
+}
+
 impl std::str::FromStr for Arch {
     type Err = crate::Error;
 
     fn from_str(arch: &str) -> Result<Self, Self::Err> {
-        Ok(match arch.to_lowercase().as_ref() {
-            "x86" => Arch::X86,
-            "amd64" | "x86-64" | "x86_64" | "x64" => Arch::X8664,
-            "x32" => Arch::X32,
-            "arm" => Arch::Arm,
-            "arm64" | "aarch64" => Arch::Aarch64,
-            "loong64" | "loongarch64" => Arch::Loongarch64,
-            "m68k" => Arch::M68k,
-            "mips" => Arch::Mips,
-            "mips64" => Arch::Mips64,
-            "mips64n32" => Arch::Mips64n32,
-            "mipsel" => Arch::Mipsel,
-            "mipsel64" => Arch::Mipsel64,
-            "mipsel64n32" => Arch::Mipsel64n32,
-            "ppc" => Arch::Ppc,
-            "ppc64" => Arch::Ppc64,
-            "ppc64le" => Arch::Ppc64le,
-            "s390" => Arch::S390,
-            "s390x" => Arch::S390x,
-            "parisc" => Arch::Parisc,
-            "parisc64" => Arch::Parisc64,
-            "riscv64" => Arch::Riscv64,
-            "sheb" => Arch::Sheb,
-            "sh" => Arch::Sh,
-            arch => {
-                let err = format!("unsupported architectures {arch}");
-                Err(Self::Err::SeccompError(err))?
+        let arch = arch.to_lowercase();
+        let name = match arch.as_str() {
+            "amd64" | "x86-64" | "x64" => "x86_64",
+            "arm64" => "aarch64",
+            "loong64" => "loongarch64",
+            name => name,
+        };
+        match ARCHES
+            .iter()
+            .find(|row| row.0 != Arch::Native && row.1 == name)
+        {
+            Some(row) => Ok(row.0),
+            None => {
+                let err = format!("unsupported architectures {name}");
+                Err(Self::Err::SeccompError(err))
             }
-        })
+        }
+    }
+}
+
+impl std::fmt::Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (_, name, _, _) = ARCHES.iter().find(|row| row.0 == *self).expect("ARCHES");
+        f.write_str(name)
     }
 }
+
+pub(crate) fn translate_arch(arch: Arch) -> ScmpArch {
+    ARCHES.iter().find(|row| row.0 == arch).expect("ARCHES").3
+}
+
+pub(crate) fn translate_scmp_arch(arch: ScmpArch) -> Option<Arch> {
+    ARCHES
+        .iter()
+        .find(|row| row.0 != Arch::Native && row.3 == arch)
+        .map(|row| row.0)
+}
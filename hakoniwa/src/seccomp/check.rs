@@ -0,0 +1,152 @@
+use libseccomp::ScmpSyscall;
+use std::collections::HashSet;
+use std::fmt;
+
+use super::arch::translate_arch;
+use super::compiled_filter::resolve_rules;
+use super::{Arch, Filter, Rule};
+use crate::Capability;
+
+// libseccomp resolves syscalls which do not exist on an architecture to a
+// pseudo syscall number below this value.
+const PSEUDO_SYSCALL_UNSUPPORTED: i32 = -10000;
+
+/// The result of [Filter::check], which lists the rules that do not work as
+/// they are written.
+#[derive(Clone, Debug, Default)]
+pub struct FilterCheck {
+    /// Syscalls which do not exist on the architecture, rules on them are
+    /// skipped for that architecture.
+    pub unknown_syscalls: Vec<(Arch, String)>,
+
+    /// Rules whose action is the same as the default action.
+    pub redundant_rules: Vec<Rule>,
+
+    /// Rules which never take effect, since another rule on the same
+    /// syscall has a higher precedence.
+    pub shadowed_rules: Vec<Rule>,
+
+    /// Conditional rules on syscalls which are multiplexed through
+    /// socketcall(2) or ipc(2) on the architecture. The argcmps are dropped,
+    /// so the rule matches the syscall regardless of its arguments.
+    pub dropped_argcmps: Vec<(Arch, Rule)>,
+
+    arches: Vec<Arch>,
+}
+
+impl FilterCheck {
+    /// Checks the rules which apply to `caps`, which are all capabilities if
+    /// None.
+    pub(crate) fn new(filter: &Filter, caps: Option<&HashSet<Capability>>) -> Self {
+        // The native architecture is always in the filter.
+        let native = Arch::runtime();
+        let mut others = filter
+            .architectures
+            .iter()
+            .filter(|&&arch| arch != Arch::Native && arch != native)
+            .copied()
+            .collect::<Vec<_>>();
+        others.sort_by_key(|arch| arch.to_string());
+
+        let mut check = Self {
+            arches: [vec![native], others].concat(),
+            ..Default::default()
+        };
+        let rules = filter.active_rules(caps);
+        check.check_syscalls(&rules);
+        check.check_rules(filter, &rules);
+        check
+    }
+
+    /// Returns true if every rule can be applied, i.e. every syscall exists
+    /// on at least one of the architectures and no argcmps are dropped.
+    ///
+    /// Redundant and shadowed rules do not change the behavior of the
+    /// filter, so they are not taken into account.
+    pub fn is_ok(&self) -> bool {
+        self.errors().is_empty()
+    }
+
+    /// Returns the reasons why [FilterCheck::is_ok] is false.
+    pub(crate) fn errors(&self) -> Vec<String> {
+        let mut errors = vec![];
+        let mut seen = vec![];
+        for (_, sysname) in &self.unknown_syscalls {
+            if seen.contains(&sysname) {
+                continue;
+            }
+            seen.push(sysname);
+
+            let count = self
+                .unknown_syscalls
+                .iter()
+                .filter(|(_, s)| s == sysname)
+                .count();
+            if count == self.arches.len() {
+                errors.push(format!("unknown syscall: {sysname}"));
+            }
+        }
+        for (arch, rule) in &self.dropped_argcmps {
+            errors.push(format!("dropped argcmps: {rule} ({arch})"));
+        }
+        errors
+    }
+
+    fn check_syscalls(&mut self, rules: &[&Rule]) {
+        let mut sysnames: Vec<&str> = vec![];
+        for rule in rules {
+            if !sysnames.contains(&rule.sysname.as_str()) {
+                sysnames.push(&rule.sysname);
+            }
+        }
+
+        for &arch in &self.arches {
+            for &sysname in &sysnames {
+                match ScmpSyscall::from_name_by_arch(sysname, translate_arch(arch)) {
+                    Ok(syscall) if syscall.as_raw_syscall() > PSEUDO_SYSCALL_UNSUPPORTED => {
+                        if syscall.as_raw_syscall() >= 0 {
+                            continue;
+                        }
+                        let rules = rules
+                            .iter()
+                            .filter(|rule| rule.sysname == sysname && !rule.argcmps.is_empty());
+                        for &rule in rules {
+                            self.dropped_argcmps.push((arch, rule.clone()));
+                        }
+                    }
+                    _ => self.unknown_syscalls.push((arch, sysname.to_string())),
+                }
+            }
+        }
+    }
+
+    fn check_rules(&mut self, filter: &Filter, rules: &[&Rule]) {
+        let resolved = resolve_rules(rules);
+        self.redundant_rules = resolved
+            .main
+            .into_iter()
+            .filter(|rule| rule.action == filter.default_action)
+            .cloned()
+            .collect();
+        self.shadowed_rules = resolved.shadowed.into_iter().cloned().collect();
+    }
+}
+
+impl fmt::Display for FilterCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = vec![];
+        for (arch, sysname) in &self.unknown_syscalls {
+            lines.push(format!("unknown syscall: {sysname} ({arch})"));
+        }
+        for rule in &self.redundant_rules {
+            lines.push(format!("redundant rule: {rule}"));
+        }
+        for rule in &self.shadowed_rules {
+            lines.push(format!("shadowed rule: {rule}"));
+        }
+        for (arch, rule) in &self.dropped_argcmps {
+            lines.push(format!("dropped argcmps: {rule} ({arch})"));
+        }
+        f.write_str(&lines.join("\n"))
+    }
+}
@@ -0,0 +1,304 @@
+use libseccomp::*;
+use nix::sys::memfd::{self, MFdFlags};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+use super::arch::translate_arch;
+use super::{Action, Arch, ArgCmp, ArgCmpOp, Filter, Rule};
+use crate::Capability;
+use crate::error::*;
+
+/// A single BPF instruction, the same layout as `struct sock_filter`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SockFilter {
+    pub(crate) code: u16,
+    pub(crate) jt: u8,
+    pub(crate) jf: u8,
+    pub(crate) k: u32,
+}
+
+/// A [Filter] compiled into BPF programs.
+///
+/// Compiling resolves the syscall names and generates the programs with
+/// libseccomp, so the internal process only needs to install them with a
+/// plain `seccomp(SECCOMP_SET_MODE_FILTER)` call.
+#[derive(Clone, Debug)]
+pub struct CompiledFilter {
+    pub(crate) programs: Arc<[Program]>,
+}
+
+/// A BPF program, which is installed as a separate seccomp filter.
+#[derive(Clone, Debug)]
+pub(crate) struct Program {
+    pub(crate) insns: Box<[SockFilter]>,
+    pub(crate) notify: bool,
+}
+
+impl CompiledFilter {
+    /// Compiles the filter, the syscalls not supported by the kernel are
+    /// skipped.
+    ///
+    /// libseccomp ignores a conditional rule if the syscall also has an
+    /// unconditional rule, so such conditional rules are compiled into an
+    /// extra program whose default action is [Action::Allow]. The kernel
+    /// takes the action with the highest precedence when multiple filters
+    /// match a syscall.
+    ///
+    /// The rules which depend on capabilities are compiled for `caps`, which
+    /// are all capabilities if None.
+    pub(crate) fn compile(filter: &Filter, caps: Option<&HashSet<Capability>>) -> Result<Self> {
+        let resolved = resolve_rules(&filter.active_rules(caps));
+        let arches = &filter.architectures;
+
+        let mut programs = vec![];
+        if !resolved.overlay.is_empty() {
+            programs.push(Program::compile(Action::Allow, arches, &resolved.overlay)?);
+        }
+        programs.push(Program::compile(
+            filter.default_action,
+            arches,
+            &resolved.main,
+        )?);
+
+        // Only one listener can be created for the filters of a process.
+        if programs.iter().filter(|p| p.notify).count() > 1 {
+            let err = "Notify rules cannot be mixed with unconditional rules on the same syscall";
+            Err(Error::SeccompError(err.to_string()))?
+        }
+
+        let programs = programs.into();
+        Ok(Self { programs })
+    }
+
+    /// Returns the number of BPF instructions.
+    pub fn len(&self) -> usize {
+        self.programs.iter().map(|p| p.insns.len()).sum()
+    }
+
+    /// Returns true if the programs have no BPF instructions.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Program {
+    fn compile(default_action: Action, arches: &HashSet<Arch>, rules: &[&Rule]) -> Result<Self> {
+        let ctx = Self::compile_imp(default_action, arches, rules)
+            .map_err(|e| Error::SeccompError(e.to_string()))?;
+        let insns = Self::export(&ctx)?;
+        let notify = default_action == Action::Notify
+            || rules.iter().any(|rule| rule.action == Action::Notify);
+        Ok(Self { insns, notify })
+    }
+
+    fn compile_imp(
+        default_action: Action,
+        arches: &HashSet<Arch>,
+        rules: &[&Rule],
+    ) -> std::result::Result<ScmpFilterContext, error::SeccompError> {
+        // Create a new filter context, and arrange the syscalls in a binary
+        // tree rather than a linear list if supported (libseccomp v2.5+).
+        let default_scmp_action = translate_action(default_action);
+        let mut ctx = ScmpFilterContext::new(default_scmp_action)?;
+        _ = ctx.set_ctl_optimize(2);
+
+        // Add architectures.
+        for arch in arches {
+            let scmp_arch = translate_arch(*arch);
+            ctx.add_arch(scmp_arch)?;
+        }
+
+        // Add rules.
+        for rule in rules {
+            let (action, sysname, argcmps) = (rule.action, &rule.sysname, &rule.argcmps);
+
+            // If the action is the same as the default action, the rule is
+            // redundant, skip it.
+            let scmp_action = translate_action(action);
+            if scmp_action == default_scmp_action {
+                continue;
+            }
+
+            // If the syscall is not supported by the kernel, skip it.
+            let scmp_syscall = match ScmpSyscall::from_name(sysname) {
+                Ok(syscall) => syscall,
+                Err(_) => continue,
+            };
+
+            // Adds a single rule for an unconditional action on a syscall.
+            if argcmps.is_empty() {
+                ctx.add_rule(scmp_action, scmp_syscall)?;
+                continue;
+            }
+
+            // Adds a single rule for a conditional action on a syscall.
+            let scmp_argcmps = translate_argcmps(argcmps);
+            ctx.add_rule_conditional(scmp_action, scmp_syscall, &scmp_argcmps)?;
+        }
+
+        Ok(ctx)
+    }
+
+    fn export(ctx: &ScmpFilterContext) -> Result<Box<[SockFilter]>> {
+        // The BPF program is exported to a memfd, since exporting to memory
+        // requires libseccomp v2.6.
+        let mut bytes = vec![];
+        let fd = memfd::memfd_create("hakoniwa-seccomp", MFdFlags::MFD_CLOEXEC)
+            .map_err(ProcessErrorKind::NixError)?;
+        let mut file = File::from(fd);
+        ctx.export_bpf(&file)
+            .map_err(|e| Error::SeccompError(e.to_string()))?;
+        file.seek(SeekFrom::Start(0))
+            .and_then(|_| file.read_to_end(&mut bytes))
+            .map_err(ProcessErrorKind::StdIoError)?;
+
+        let insns = bytes
+            .chunks_exact(size_of::<SockFilter>())
+            .map(|c| SockFilter {
+                code: u16::from_ne_bytes([c[0], c[1]]),
+                jt: c[2],
+                jf: c[3],
+                k: u32::from_ne_bytes([c[4], c[5], c[6], c[7]]),
+            })
+            .collect();
+        Ok(insns)
+    }
+}
+
+/// The rules split into the rules of the main program and the rules of the
+/// overlay program.
+#[derive(Default)]
+pub(crate) struct ResolvedRules<'a> {
+    pub(crate) main: Vec<&'a Rule>,
+    pub(crate) overlay: Vec<&'a Rule>,
+    pub(crate) shadowed: Vec<&'a Rule>,
+}
+
+/// Splits the rules into the rules of the main program and the rules of the
+/// overlay program.
+///
+/// When a syscall has several unconditional rules, the one with the highest
+/// precedence wins. A conditional rule is moved to the overlay if it has a
+/// higher precedence than the unconditional rule, otherwise it never takes
+/// effect and is shadowed.
+pub(crate) fn resolve_rules<'a>(rules: &[&'a Rule]) -> ResolvedRules<'a> {
+    let mut unconditional: HashMap<&str, Action> = HashMap::new();
+    for rule in rules.iter().filter(|rule| rule.argcmps.is_empty()) {
+        unconditional
+            .entry(&rule.sysname)
+            .and_modify(|action| {
+                if rule.action.precedence() > action.precedence() {
+                    *action = rule.action
+                }
+            })
+            .or_insert(rule.action);
+    }
+
+    let mut added = HashSet::new();
+    let mut resolved = ResolvedRules::default();
+    for &rule in rules {
+        match unconditional.get(rule.sysname.as_str()) {
+            None => resolved.main.push(rule),
+            Some(action) if rule.argcmps.is_empty() => {
+                if *action == rule.action && added.insert(&rule.sysname) {
+                    resolved.main.push(rule)
+                } else {
+                    resolved.shadowed.push(rule)
+                }
+            }
+            Some(action) if rule.action.precedence() > action.precedence() => {
+                resolved.overlay.push(rule)
+            }
+            Some(_) => resolved.shadowed.push(rule),
+        }
+    }
+    resolved
+}
+
+fn translate_action(action: Action) -> ScmpAction {
+    match action {
+        Action::Allow => ScmpAction::Allow,
+        Action::Errno(v) => ScmpAction::Errno(v),
+        Action::KillProcess => ScmpAction::KillProcess,
+        Action::KillThread => ScmpAction::KillThread,
+        Action::Log => ScmpAction::Log,
+        Action::Notify => ScmpAction::Notify,
+        Action::Trace(v) => ScmpAction::Trace(v),
+        Action::Trap => ScmpAction::Trap,
+    }
+}
+
+/// Translates an `AUDIT_ARCH_*` value, e.g. the `arch` of `struct seccomp_data`.
+pub(crate) fn translate_audit_arch(arch: u32, syscall: i32) -> Option<Arch> {
+    const EM_386: u32 = 3;
+    const EM_68K: u32 = 4;
+    const EM_MIPS: u32 = 8;
+    const EM_PARISC: u32 = 15;
+    const EM_PPC: u32 = 20;
+    const EM_PPC64: u32 = 21;
+    const EM_S390: u32 = 22;
+    const EM_ARM: u32 = 40;
+    const EM_SH: u32 = 42;
+    const EM_X86_64: u32 = 62;
+    const EM_AARCH64: u32 = 183;
+    const EM_RISCV: u32 = 243;
+    const EM_LOONGARCH: u32 = 258;
+    const ARCH_64BIT: u32 = 0x8000_0000;
+    const ARCH_LE: u32 = 0x4000_0000;
+    const ARCH_MIPS64_N32: u32 = 0x2000_0000;
+    const X32_SYSCALL_BIT: i32 = 0x4000_0000;
+
+    Some(match arch {
+        a if a == EM_386 | ARCH_LE => Arch::X86,
+        a if a == EM_X86_64 | ARCH_64BIT | ARCH_LE => match syscall & X32_SYSCALL_BIT {
+            0 => Arch::X8664,
+            _ => Arch::X32,
+        },
+        a if a == EM_ARM | ARCH_LE => Arch::Arm,
+        a if a == EM_AARCH64 | ARCH_64BIT | ARCH_LE => Arch::Aarch64,
+        a if a == EM_LOONGARCH | ARCH_64BIT | ARCH_LE => Arch::Loongarch64,
+        a if a == EM_68K => Arch::M68k,
+        a if a == EM_MIPS => Arch::Mips,
+        a if a == EM_MIPS | ARCH_64BIT => Arch::Mips64,
+        a if a == EM_MIPS | ARCH_64BIT | ARCH_MIPS64_N32 => Arch::Mips64n32,
+        a if a == EM_MIPS | ARCH_LE => Arch::Mipsel,
+        a if a == EM_MIPS | ARCH_64BIT | ARCH_LE => Arch::Mipsel64,
+        a if a == EM_MIPS | ARCH_64BIT | ARCH_LE | ARCH_MIPS64_N32 => Arch::Mipsel64n32,
+        a if a == EM_PPC => Arch::Ppc,
+        a if a == EM_PPC64 | ARCH_64BIT => Arch::Ppc64,
+        a if a == EM_PPC64 | ARCH_64BIT | ARCH_LE => Arch::Ppc64le,
+        a if a == EM_S390 => Arch::S390,
+        a if a == EM_S390 | ARCH_64BIT => Arch::S390x,
+        a if a == EM_PARISC => Arch::Parisc,
+        a if a == EM_PARISC | ARCH_64BIT => Arch::Parisc64,
+        a if a == EM_RISCV | ARCH_64BIT | ARCH_LE => Arch::Riscv64,
+        a if a == EM_SH => Arch::Sheb,
+        a if a == EM_SH | ARCH_LE => Arch::Sh,
+        _ => None?,
+    })
+}
+
+fn translate_argcmps(argcmps: &[ArgCmp]) -> Vec<ScmpArgCompare> {
+    argcmps
+        .iter()
+        .map(|cmp| {
+            let mut datum = cmp.datum_a;
+            let op = match cmp.op {
+                ArgCmpOp::Ne => ScmpCompareOp::NotEqual,
+                ArgCmpOp::Lt => ScmpCompareOp::Less,
+                ArgCmpOp::Le => ScmpCompareOp::LessOrEqual,
+                ArgCmpOp::Eq => ScmpCompareOp::Equal,
+                ArgCmpOp::Gt => ScmpCompareOp::Greater,
+                ArgCmpOp::Ge => ScmpCompareOp::GreaterEqual,
+                ArgCmpOp::MaskedEq => {
+                    datum = cmp.datum_b;
+                    ScmpCompareOp::MaskedEqual(cmp.datum_a)
+                }
+            };
+            ScmpArgCompare::new(cmp.arg, op, datum)
+        })
+        .collect()
+}
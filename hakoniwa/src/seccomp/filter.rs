@@ -1,15 +1,31 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 
-use super::{Action, Arch, ArgCmp, Rule};
+use super::{Action, Arch, ArgCmp, ArgCmpOp, CompiledFilter, FilterCheck, Rule, SyscallGroup, oci};
+use crate::error::Result;
+use crate::{Capability, RecordedSyscall};
 
 /// Represents a filter that allows one to configure actions to take on matched
 /// syscalls and furthermore also allows matching on values passed as
 /// arguments to syscalls.
+///
+/// If multiple rules match a syscall, the action with the highest precedence
+/// is taken, see seccomp(2). E.g. [Action::Errno] beats [Action::Allow], so
+/// a syscall group can be allowed while some of its syscalls are denied:
+///
+/// ```no_run
+/// use hakoniwa::seccomp::*;
+///
+/// let mut filter = Filter::new(Action::KillProcess);
+/// filter.add_group_rule(Action::Allow, SyscallGroup::SystemService);
+/// filter.add_group_rule(Action::Errno(libc::EPERM), SyscallGroup::Privileged);
+/// filter.add_socket_family_rule(Action::Errno(libc::EAFNOSUPPORT), libc::AF_NETLINK);
+/// ```
 #[derive(Clone, Debug)]
 pub struct Filter {
     pub(crate) default_action: Action,
     pub(crate) architectures: HashSet<Arch>,
     pub(crate) rules: Vec<Rule>,
+    pub(crate) layout: Option<oci::Layout>,
 }
 
 impl Filter {
@@ -19,9 +35,71 @@ impl Filter {
             default_action,
             architectures: HashSet::new(),
             rules: vec![],
+            layout: None,
         }
     }
 
+    /// Creates a filter from an [OCI seccomp profile], which is used by runc,
+    /// crun, podman and docker.
+    ///
+    /// The `archMap` of the profile is resolved for the runtime architecture.
+    /// The `includes` and `excludes` of the syscall entries are evaluated
+    /// against the runtime architecture and the capabilities of the
+    /// container when the filter is compiled, or all capabilities if they
+    /// are left as is. The layout of the profile is kept, so
+    /// [Filter::to_oci_json] gives back the same profile.
+    ///
+    /// [OCI seccomp profile]: https://github.com/opencontainers/runtime-spec/blob/main/config-linux.md#seccomp
+    pub fn from_oci_json(data: &str) -> Result<Self> {
+        oci::from_json(data)
+    }
+
+    /// Exports the filter as an OCI seccomp profile.
+    ///
+    /// Rules added to the filter are appended to the `syscalls` of the
+    /// profile it was created from.
+    pub fn to_oci_json(&self) -> Result<String> {
+        oci::to_json(self)
+    }
+
+    /// Creates a minimal allow-list filter from the syscalls recorded with
+    /// [Runctl::RecordSyscalls], any other syscall takes the default action.
+    ///
+    /// The syscalls whose arguments are recorded, e.g. `socket`, are only
+    /// allowed with the recorded values.
+    ///
+    /// [Runctl::RecordSyscalls]: crate::Runctl::RecordSyscalls
+    pub fn from_recorded_syscalls(
+        default_action: Action,
+        syscalls: &[RecordedSyscall],
+    ) -> Result<Self> {
+        let mut arches = BTreeSet::new();
+        let mut unconditional = BTreeSet::new();
+        let mut conditional = BTreeSet::new();
+        for syscall in syscalls {
+            arches.insert(syscall.arch.as_str());
+            if syscall.args.is_empty() {
+                unconditional.insert(syscall.sysname.as_str());
+            }
+            for &(index, value) in &syscall.args {
+                conditional.insert((syscall.sysname.as_str(), index, value));
+            }
+        }
+
+        let mut filter = Self::new(default_action);
+        for arch in arches {
+            filter.add_arch(arch.parse()?);
+        }
+        for sysname in unconditional {
+            filter.add_rule(Action::Allow, sysname);
+        }
+        for (sysname, index, value) in conditional {
+            let argcmp = ArgCmp::new(index, ArgCmpOp::Eq, value, 0);
+            filter.add_rule_conditional(Action::Allow, sysname, &[argcmp]);
+        }
+        Ok(filter)
+    }
+
     /// Adds an architecture to the filter.
     pub fn add_arch(&mut self, arch: Arch) -> &mut Self {
         self.architectures.insert(arch);
@@ -30,14 +108,7 @@ impl Filter {
 
     /// Adds a single rule for an unconditional action on a syscall.
     pub fn add_rule(&mut self, action: Action, syscall: &str) -> &mut Self {
-        let sysname = syscall.to_string();
-        let argcmps = vec![];
-        self.rules.push(Rule {
-            action,
-            sysname,
-            argcmps,
-        });
-        self
+        self.add_rule_conditional(action, syscall, &[])
     }
 
     /// Adds a single rule for a conditional action on a syscall.
@@ -53,13 +124,70 @@ impl Filter {
             action,
             sysname,
             argcmps,
+            entry: None,
         });
         self
     }
 
+    /// Adds an unconditional rule for each syscall in the group.
+    pub fn add_group_rule(&mut self, action: Action, group: SyscallGroup) -> &mut Self {
+        for syscall in group.syscalls() {
+            self.add_rule(action, syscall);
+        }
+        self
+    }
+
+    /// Adds a rule for the `socket` syscall creating sockets of the address
+    /// family, e.g. `libc::AF_NETLINK`.
+    ///
+    /// On architectures which multiplex the socket calls through
+    /// `socketcall`, e.g. x86, the arguments cannot be inspected, so it is
+    /// necessary to deny `socketcall` as well.
+    pub fn add_socket_family_rule(&mut self, action: Action, family: i32) -> &mut Self {
+        let argcmp = ArgCmp::new(0, ArgCmpOp::Eq, family as u64, 0);
+        self.add_rule_conditional(action, "socket", &[argcmp])
+    }
+
+    /// Checks the rules against the architectures of the filter, and reports
+    /// the rules that do not work as they are written, e.g. a misspelled
+    /// syscall name.
+    pub fn check(&self) -> FilterCheck {
+        FilterCheck::new(self, None)
+    }
+
+    /// Compiles the filter into BPF programs, the rules which depend on
+    /// capabilities are compiled as if all capabilities are kept.
+    pub fn compile(&self) -> Result<CompiledFilter> {
+        CompiledFilter::compile(self, None)
+    }
+
+    /// Returns the rules which apply to the runtime architecture and the
+    /// capabilities, which are all capabilities if None.
+    pub(crate) fn active_rules(&self, caps: Option<&HashSet<Capability>>) -> Vec<&Rule> {
+        let layout = self.layout.as_ref();
+        self.rules
+            .iter()
+            .filter(|rule| match (rule.entry, layout) {
+                (Some(index), Some(layout)) => layout.applies(index, caps),
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Returns true if the filter may kill or trap the process with SIGSYS.
+    pub(crate) fn raises_sigsys(&self) -> bool {
+        let sigsys = |action| {
+            matches!(
+                action,
+                Action::KillProcess | Action::KillThread | Action::Trap
+            )
+        };
+        sigsys(self.default_action) || self.rules.iter().any(|rule| sigsys(rule.action))
+    }
+
     /// Returns a list of filter rules.
     #[doc(hidden)]
     pub fn get_rules(&self) -> Vec<Rule> {
-        self.rules.clone()
+        self.active_rules(None).into_iter().cloned().collect()
     }
 }
@@ -0,0 +1,711 @@
+use std::collections::HashSet;
+use std::fmt;
+
+/// Represents a predefined set of syscalls, the same as the syscall sets of
+/// systemd's `SystemCallFilter=`.
+///
+/// A set may include other sets, e.g. `@system-service` includes
+/// `@basic-io` and `@network-io`. Syscalls not supported by the runtime
+/// architecture are skipped when the filter is compiled.
+#[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum SyscallGroup {
+    /// `@aio`: Asynchronous I/O.
+    Aio,
+    /// `@basic-io`: Reading and writing file descriptors.
+    BasicIo,
+    /// `@chown`: Changing file ownership.
+    Chown,
+    /// `@clock`: Changing the system clock.
+    Clock,
+    /// `@cpu-emulation`: CPU emulation functionality.
+    CpuEmulation,
+    /// `@debug`: Debugging, performance monitoring and tracing.
+    Debug,
+    /// `@default`: Syscalls needed by virtually every process.
+    Default,
+    /// `@file-system`: File system operations.
+    FileSystem,
+    /// `@io-event`: Event loops.
+    IoEvent,
+    /// `@ipc`: Pipes, SysV IPC, POSIX message queues and other IPC.
+    Ipc,
+    /// `@keyring`: Kernel keyring access.
+    Keyring,
+    /// `@memlock`: Locking of memory in RAM.
+    Memlock,
+    /// `@module`: Loading and unloading of kernel modules.
+    Module,
+    /// `@mount`: Mounting and unmounting of file systems.
+    Mount,
+    /// `@network-io`: Socket I/O.
+    NetworkIo,
+    /// `@obsolete`: Unusual, obsolete or unimplemented syscalls.
+    Obsolete,
+    /// `@pkey`: Memory protection keys.
+    Pkey,
+    /// `@privileged`: All syscalls which need super-user capabilities.
+    Privileged,
+    /// `@process`: Process control, execution and namespacing.
+    Process,
+    /// `@raw-io`: Raw I/O port access.
+    RawIo,
+    /// `@reboot`: Rebooting and preparing reboots.
+    Reboot,
+    /// `@resources`: Changing resource limits, memory and scheduling.
+    Resources,
+    /// `@sandbox`: Sandboxing functionality, i.e. landlock and seccomp.
+    Sandbox,
+    /// `@setuid`: Changing user and group credentials.
+    Setuid,
+    /// `@signal`: Controlling signal handling.
+    Signal,
+    /// `@swap`: Enabling and disabling swap devices.
+    Swap,
+    /// `@sync`: Synchronizing files and memory to disk.
+    Sync,
+    /// `@system-service`: A reasonable set of syscalls used by common
+    /// system services.
+    SystemService,
+    /// `@timer`: Scheduling operations by time.
+    Timer,
+}
+
+impl SyscallGroup {
+    /// Returns the names of the syscalls in the set, including the syscalls
+    /// of the nested sets.
+    pub fn syscalls(&self) -> Vec<&'static str> {
+        let mut visited = HashSet::new();
+        let mut syscalls = vec![];
+        self.expand(&mut visited, &mut syscalls);
+        syscalls
+    }
+
+    fn expand(&self, visited: &mut HashSet<&'static str>, syscalls: &mut Vec<&'static str>) {
+        for &name in self.entries() {
+            if !visited.insert(name) {
+                continue;
+            }
+            match name.parse::<Self>() {
+                Ok(group) => group.expand(visited, syscalls),
+                Err(_) => syscalls.push(name),
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Aio => "@aio",
+            Self::BasicIo => "@basic-io",
+            Self::Chown => "@chown",
+            Self::Clock => "@clock",
+            Self::CpuEmulation => "@cpu-emulation",
+            Self::Debug => "@debug",
+            Self::Default => "@default",
+            Self::FileSystem => "@file-system",
+            Self::IoEvent => "@io-event",
+            Self::Ipc => "@ipc",
+            Self::Keyring => "@keyring",
+            Self::Memlock => "@memlock",
+            Self::Module => "@module",
+            Self::Mount => "@mount",
+            Self::NetworkIo => "@network-io",
+            Self::Obsolete => "@obsolete",
+            Self::Pkey => "@pkey",
+            Self::Privileged => "@privileged",
+            Self::Process => "@process",
+            Self::RawIo => "@raw-io",
+            Self::Reboot => "@reboot",
+            Self::Resources => "@resources",
+            Self::Sandbox => "@sandbox",
+            Self::Setuid => "@setuid",
+            Self::Signal => "@signal",
+            Self::Swap => "@swap",
+            Self::Sync => "@sync",
+            Self::SystemService => "@system-service",
+            Self::Timer => "@timer",
+        }
+    }
+
+    fn entries(&self) -> &'static [&'static str] {
+        match self {
+            Self::Aio => &[
+                "io_cancel",
+                "io_destroy",
+                "io_getevents",
+                "io_pgetevents",
+                "io_pgetevents_time64",
+                "io_setup",
+                "io_submit",
+                "io_uring_enter",
+                "io_uring_register",
+                "io_uring_setup",
+            ],
+            Self::BasicIo => &[
+                "_llseek",
+                "close",
+                "close_range",
+                "dup",
+                "dup2",
+                "dup3",
+                "lseek",
+                "pread64",
+                "preadv",
+                "preadv2",
+                "pwrite64",
+                "pwritev",
+                "pwritev2",
+                "read",
+                "readv",
+                "write",
+                "writev",
+            ],
+            Self::Chown => &[
+                "chown", "chown32", "fchown", "fchown32", "fchownat", "lchown", "lchown32",
+            ],
+            Self::Clock => &[
+                "adjtimex",
+                "clock_adjtime",
+                "clock_adjtime64",
+                "clock_settime",
+                "clock_settime64",
+                "settimeofday",
+            ],
+            Self::CpuEmulation => &[
+                "modify_ldt",
+                "subpage_prot",
+                "switch_endian",
+                "vm86",
+                "vm86old",
+            ],
+            Self::Debug => &[
+                "lookup_dcookie",
+                "perf_event_open",
+                "pidfd_getfd",
+                "ptrace",
+                "rtas",
+                "s390_runtime_instr",
+                "sys_debug_setcontext",
+            ],
+            Self::Default => &[
+                "arch_prctl",
+                "brk",
+                "cacheflush",
+                "clock_getres",
+                "clock_getres_time64",
+                "clock_gettime",
+                "clock_gettime64",
+                "clock_nanosleep",
+                "clock_nanosleep_time64",
+                "execve",
+                "exit",
+                "exit_group",
+                "futex",
+                "futex_time64",
+                "futex_waitv",
+                "get_robust_list",
+                "get_thread_area",
+                "getegid",
+                "getegid32",
+                "geteuid",
+                "geteuid32",
+                "getgid",
+                "getgid32",
+                "getgroups",
+                "getgroups32",
+                "getpgid",
+                "getpgrp",
+                "getpid",
+                "getppid",
+                "getrandom",
+                "getresgid",
+                "getresgid32",
+                "getresuid",
+                "getresuid32",
+                "getrlimit",
+                "getsid",
+                "gettid",
+                "gettimeofday",
+                "getuid",
+                "getuid32",
+                "membarrier",
+                "mmap",
+                "mmap2",
+                "mprotect",
+                "munmap",
+                "nanosleep",
+                "pause",
+                "prlimit64",
+                "restart_syscall",
+                "riscv_flush_icache",
+                "riscv_hwprobe",
+                "rseq",
+                "rt_sigreturn",
+                "sched_getaffinity",
+                "sched_yield",
+                "set_robust_list",
+                "set_thread_area",
+                "set_tid_address",
+                "set_tls",
+                "sigreturn",
+                "time",
+                "ugetrlimit",
+            ],
+            Self::FileSystem => &[
+                "access",
+                "chdir",
+                "chmod",
+                "close",
+                "creat",
+                "faccessat",
+                "faccessat2",
+                "fallocate",
+                "fchdir",
+                "fchmod",
+                "fchmodat",
+                "fchmodat2",
+                "fcntl",
+                "fcntl64",
+                "fgetxattr",
+                "flistxattr",
+                "fremovexattr",
+                "fsetxattr",
+                "fstat",
+                "fstat64",
+                "fstatat64",
+                "fstatfs",
+                "fstatfs64",
+                "ftruncate",
+                "ftruncate64",
+                "futimesat",
+                "getcwd",
+                "getdents",
+                "getdents64",
+                "getxattr",
+                "inotify_add_watch",
+                "inotify_init",
+                "inotify_init1",
+                "inotify_rm_watch",
+                "lgetxattr",
+                "link",
+                "linkat",
+                "listxattr",
+                "llistxattr",
+                "lremovexattr",
+                "lsetxattr",
+                "lstat",
+                "lstat64",
+                "mkdir",
+                "mkdirat",
+                "mknod",
+                "mknodat",
+                "newfstatat",
+                "oldfstat",
+                "oldlstat",
+                "oldstat",
+                "open",
+                "openat",
+                "openat2",
+                "readlink",
+                "readlinkat",
+                "removexattr",
+                "rename",
+                "renameat",
+                "renameat2",
+                "rmdir",
+                "setxattr",
+                "stat",
+                "stat64",
+                "statfs",
+                "statfs64",
+                "statx",
+                "symlink",
+                "symlinkat",
+                "truncate",
+                "truncate64",
+                "unlink",
+                "unlinkat",
+                "utime",
+                "utimensat",
+                "utimensat_time64",
+                "utimes",
+            ],
+            Self::IoEvent => &[
+                "_newselect",
+                "epoll_create",
+                "epoll_create1",
+                "epoll_ctl",
+                "epoll_ctl_old",
+                "epoll_pwait",
+                "epoll_pwait2",
+                "epoll_wait",
+                "epoll_wait_old",
+                "eventfd",
+                "eventfd2",
+                "poll",
+                "ppoll",
+                "ppoll_time64",
+                "pselect6",
+                "pselect6_time64",
+                "select",
+            ],
+            Self::Ipc => &[
+                "ipc",
+                "memfd_create",
+                "mq_getsetattr",
+                "mq_notify",
+                "mq_open",
+                "mq_timedreceive",
+                "mq_timedreceive_time64",
+                "mq_timedsend",
+                "mq_timedsend_time64",
+                "mq_unlink",
+                "msgctl",
+                "msgget",
+                "msgrcv",
+                "msgsnd",
+                "pipe",
+                "pipe2",
+                "process_madvise",
+                "process_vm_readv",
+                "process_vm_writev",
+                "semctl",
+                "semget",
+                "semop",
+                "semtimedop",
+                "semtimedop_time64",
+                "shmat",
+                "shmctl",
+                "shmdt",
+                "shmget",
+            ],
+            Self::Keyring => &["add_key", "keyctl", "request_key"],
+            Self::Memlock => &["mlock", "mlock2", "mlockall", "munlock", "munlockall"],
+            Self::Module => &["delete_module", "finit_module", "init_module"],
+            Self::Mount => &[
+                "chroot",
+                "fsconfig",
+                "fsmount",
+                "fsopen",
+                "fspick",
+                "mount",
+                "mount_setattr",
+                "move_mount",
+                "open_tree",
+                "pivot_root",
+                "umount",
+                "umount2",
+            ],
+            Self::NetworkIo => &[
+                "accept",
+                "accept4",
+                "bind",
+                "connect",
+                "getpeername",
+                "getsockname",
+                "getsockopt",
+                "listen",
+                "recv",
+                "recvfrom",
+                "recvmmsg",
+                "recvmmsg_time64",
+                "recvmsg",
+                "send",
+                "sendmmsg",
+                "sendmsg",
+                "sendto",
+                "setsockopt",
+                "shutdown",
+                "socket",
+                "socketcall",
+                "socketpair",
+            ],
+            Self::Obsolete => &[
+                "_sysctl",
+                "afs_syscall",
+                "bdflush",
+                "break",
+                "create_module",
+                "ftime",
+                "get_kernel_syms",
+                "getpmsg",
+                "gtty",
+                "idle",
+                "lock",
+                "mpx",
+                "prof",
+                "profil",
+                "putpmsg",
+                "query_module",
+                "security",
+                "sgetmask",
+                "ssetmask",
+                "stime",
+                "stty",
+                "sysfs",
+                "tuxcall",
+                "ulimit",
+                "uselib",
+                "ustat",
+                "vserver",
+            ],
+            Self::Pkey => &["pkey_alloc", "pkey_free", "pkey_mprotect"],
+            Self::Privileged => &[
+                "@chown",
+                "@clock",
+                "@module",
+                "@raw-io",
+                "@reboot",
+                "@swap",
+                "_sysctl",
+                "acct",
+                "bpf",
+                "capset",
+                "chroot",
+                "fanotify_init",
+                "fanotify_mark",
+                "nfsservctl",
+                "open_by_handle_at",
+                "pivot_root",
+                "quotactl",
+                "quotactl_fd",
+                "setdomainname",
+                "setfsuid",
+                "setfsuid32",
+                "setgroups",
+                "setgroups32",
+                "sethostname",
+                "setresuid",
+                "setresuid32",
+                "setreuid",
+                "setreuid32",
+                "setuid",
+                "setuid32",
+                "vhangup",
+            ],
+            Self::Process => &[
+                "capget",
+                "clone",
+                "clone3",
+                "execveat",
+                "fork",
+                "getrusage",
+                "kill",
+                "pidfd_open",
+                "pidfd_send_signal",
+                "prctl",
+                "rt_sigqueueinfo",
+                "rt_tgsigqueueinfo",
+                "setns",
+                "swapcontext",
+                "tgkill",
+                "times",
+                "tkill",
+                "unshare",
+                "vfork",
+                "wait4",
+                "waitid",
+                "waitpid",
+            ],
+            Self::RawIo => &[
+                "ioperm",
+                "iopl",
+                "pciconfig_iobase",
+                "pciconfig_read",
+                "pciconfig_write",
+                "s390_pci_mmio_read",
+                "s390_pci_mmio_write",
+            ],
+            Self::Reboot => &["kexec_file_load", "kexec_load", "reboot"],
+            Self::Resources => &[
+                "ioprio_set",
+                "mbind",
+                "migrate_pages",
+                "move_pages",
+                "nice",
+                "sched_setaffinity",
+                "sched_setattr",
+                "sched_setparam",
+                "sched_setscheduler",
+                "set_mempolicy",
+                "set_mempolicy_home_node",
+                "setpriority",
+                "setrlimit",
+            ],
+            Self::Sandbox => &[
+                "landlock_add_rule",
+                "landlock_create_ruleset",
+                "landlock_restrict_self",
+                "seccomp",
+            ],
+            Self::Setuid => &[
+                "setgid",
+                "setgid32",
+                "setgroups",
+                "setgroups32",
+                "setregid",
+                "setregid32",
+                "setresgid",
+                "setresgid32",
+                "setresuid",
+                "setresuid32",
+                "setreuid",
+                "setreuid32",
+                "setuid",
+                "setuid32",
+            ],
+            Self::Signal => &[
+                "rt_sigaction",
+                "rt_sigpending",
+                "rt_sigprocmask",
+                "rt_sigsuspend",
+                "rt_sigtimedwait",
+                "rt_sigtimedwait_time64",
+                "sigaction",
+                "sigaltstack",
+                "signal",
+                "signalfd",
+                "signalfd4",
+                "sigpending",
+                "sigprocmask",
+                "sigsuspend",
+            ],
+            Self::Swap => &["swapoff", "swapon"],
+            Self::Sync => &[
+                "fdatasync",
+                "fsync",
+                "msync",
+                "sync",
+                "sync_file_range",
+                "sync_file_range2",
+                "syncfs",
+            ],
+            Self::SystemService => &[
+                "@aio",
+                "@basic-io",
+                "@chown",
+                "@default",
+                "@file-system",
+                "@io-event",
+                "@ipc",
+                "@keyring",
+                "@memlock",
+                "@network-io",
+                "@process",
+                "@resources",
+                "@setuid",
+                "@signal",
+                "@sync",
+                "@timer",
+                "arm_fadvise64_64",
+                "capset",
+                "copy_file_range",
+                "fadvise64",
+                "fadvise64_64",
+                "flock",
+                "get_mempolicy",
+                "getcpu",
+                "getpriority",
+                "ioctl",
+                "ioprio_get",
+                "kcmp",
+                "madvise",
+                "mremap",
+                "name_to_handle_at",
+                "oldolduname",
+                "olduname",
+                "personality",
+                "readahead",
+                "readdir",
+                "remap_file_pages",
+                "sched_get_priority_max",
+                "sched_get_priority_min",
+                "sched_getattr",
+                "sched_getparam",
+                "sched_getscheduler",
+                "sched_rr_get_interval",
+                "sched_rr_get_interval_time64",
+                "sendfile",
+                "sendfile64",
+                "setfsgid",
+                "setfsgid32",
+                "setfsuid",
+                "setfsuid32",
+                "setpgid",
+                "setsid",
+                "splice",
+                "sysinfo",
+                "tee",
+                "umask",
+                "uname",
+                "userfaultfd",
+                "vmsplice",
+            ],
+            Self::Timer => &[
+                "alarm",
+                "getitimer",
+                "setitimer",
+                "timer_create",
+                "timer_delete",
+                "timer_getoverrun",
+                "timer_gettime",
+                "timer_gettime64",
+                "timer_settime",
+                "timer_settime64",
+                "timerfd_create",
+                "timerfd_gettime",
+                "timerfd_gettime64",
+                "timerfd_settime",
+                "timerfd_settime64",
+                "times",
+            ],
+        }
+    }
+}
+
+impl fmt::Display for SyscallGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for SyscallGroup {
+    type Err = crate::Error;
+
+    fn from_str(group: &str) -> Result<Self, Self::Err> {
+        Ok(match group {
+            "@aio" => Self::Aio,
+            "@basic-io" => Self::BasicIo,
+            "@chown" => Self::Chown,
+            "@clock" => Self::Clock,
+            "@cpu-emulation" => Self::CpuEmulation,
+            "@debug" => Self::Debug,
+            "@default" => Self::Default,
+            "@file-system" => Self::FileSystem,
+            "@io-event" => Self::IoEvent,
+            "@ipc" => Self::Ipc,
+            "@keyring" => Self::Keyring,
+            "@memlock" => Self::Memlock,
+            "@module" => Self::Module,
+            "@mount" => Self::Mount,
+            "@network-io" => Self::NetworkIo,
+            "@obsolete" => Self::Obsolete,
+            "@pkey" => Self::Pkey,
+            "@privileged" => Self::Privileged,
+            "@process" => Self::Process,
+            "@raw-io" => Self::RawIo,
+            "@reboot" => Self::Reboot,
+            "@resources" => Self::Resources,
+            "@sandbox" => Self::Sandbox,
+            "@setuid" => Self::Setuid,
+            "@signal" => Self::Signal,
+            "@swap" => Self::Swap,
+            "@sync" => Self::Sync,
+            "@system-service" => Self::SystemService,
+            "@timer" => Self::Timer,
+            group => {
+                let err = format!("unknown syscall group {group:?}");
+                Err(Self::Err::SeccompError(err))?
+            }
+        })
+    }
+}
@@ -0,0 +1,231 @@
+use libseccomp::{ScmpNotifReq, ScmpNotifResp, ScmpNotifRespFlags};
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use std::ffi::CString;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd};
+use std::os::unix::fs::FileExt;
+use std::sync::Arc;
+use std::thread;
+
+use super::{Arch, arch::translate_scmp_arch};
+
+/// A handler of the seccomp user-notifications, which is invoked by the
+/// supervisor when a syscall matches a rule with [Action::Notify].
+///
+/// Any `Fn(&Notification) -> Response` closure can be used as a handler.
+///
+/// [Action::Notify]: crate::seccomp::Action::Notify
+pub trait SeccompHandler: Send + Sync + 'static {
+    /// Decides how to respond to the notified syscall.
+    fn handle(&self, notification: &Notification) -> Response;
+}
+
+impl<F> SeccompHandler for F
+where
+    F: Fn(&Notification) -> Response + Send + Sync + 'static,
+{
+    fn handle(&self, notification: &Notification) -> Response {
+        self(notification)
+    }
+}
+
+/// The response to a notified syscall.
+#[derive(Debug)]
+pub enum Response {
+    /// Lets the kernel execute the syscall.
+    ///
+    /// The syscall arguments may have been changed by another thread of the
+    /// target since they were inspected, so this should not be used to make
+    /// security decisions.
+    Continue,
+
+    /// Skips the syscall, and makes it return the value.
+    Return(i64),
+
+    /// Skips the syscall, and makes it fail with the errno.
+    Errno(i32),
+
+    /// Skips the syscall, installs the file descriptor into the target, and
+    /// makes the syscall return the new file descriptor number.
+    InjectFd(OwnedFd),
+}
+
+/// A syscall of the internal process that has been notified to the supervisor.
+#[derive(Debug)]
+pub struct Notification<'a> {
+    /// The notification ID.
+    pub id: u64,
+
+    /// The process that triggered the notification, in the PID namespace of
+    /// the supervisor.
+    pub pid: u32,
+
+    /// The syscall number.
+    pub syscall: i32,
+
+    /// The syscall name, or empty if it is unknown to libseccomp.
+    pub sysname: String,
+
+    /// The architecture of the syscall.
+    pub arch: Option<Arch>,
+
+    /// The syscall arguments.
+    pub args: [u64; 6],
+
+    /// The address of the instruction that triggered the notification.
+    pub instruction_pointer: u64,
+
+    fd: BorrowedFd<'a>,
+}
+
+impl<'a> Notification<'a> {
+    fn new(fd: BorrowedFd<'a>, req: ScmpNotifReq) -> Self {
+        Self {
+            id: req.id,
+            pid: req.pid,
+            syscall: req.data.syscall.as_raw_syscall(),
+            sysname: req
+                .data
+                .syscall
+                .get_name_by_arch(req.data.arch)
+                .unwrap_or_default(),
+            arch: translate_scmp_arch(req.data.arch),
+            args: req.data.args,
+            instruction_pointer: req.data.instr_pointer,
+            fd,
+        }
+    }
+
+    /// Returns true if the target is still blocked in the notified syscall.
+    ///
+    /// Once the target is gone, its pid may be reused by another process,
+    /// so anything read from `/proc/<pid>` can only be trusted if the
+    /// notification is still valid after the read.
+    pub fn is_valid(&self) -> bool {
+        libseccomp::notify_id_valid(self.fd.as_raw_fd(), self.id).is_ok()
+    }
+
+    /// Reads the memory of the target at `addr` into `buf`, returns the
+    /// number of bytes read.
+    pub fn read_memory(&self, addr: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let mem = File::open(format!("/proc/{}/mem", self.pid))?;
+        if !self.is_valid() {
+            Err(io::Error::from_raw_os_error(libc::ENOENT))?;
+        }
+
+        let n = mem.read_at(buf, addr)?;
+        if !self.is_valid() {
+            Err(io::Error::from_raw_os_error(libc::ENOENT))?;
+        }
+        Ok(n)
+    }
+
+    /// Reads a NUL-terminated string of at most `max` bytes from the memory
+    /// of the target at `addr`, e.g. the pathname argument of `openat`.
+    pub fn read_cstring(&self, addr: u64, max: usize) -> io::Result<CString> {
+        let mut buf = vec![0; max];
+        let n = self.read_memory(addr, &mut buf)?;
+        match buf[..n].iter().position(|&c| c == 0) {
+            Some(pos) => {
+                buf.truncate(pos);
+                Ok(CString::new(buf).expect("no interior NUL"))
+            }
+            None => Err(io::Error::from_raw_os_error(libc::ENAMETOOLONG)),
+        }
+    }
+
+    /// Installs the file descriptor `fd` into the target, returns the new
+    /// file descriptor number in the target.
+    ///
+    /// Unlike [Response::InjectFd], the syscall still needs a response.
+    pub fn add_fd(&self, fd: BorrowedFd<'_>) -> io::Result<i32> {
+        self.addfd(fd, 0)
+    }
+
+    fn addfd(&self, fd: BorrowedFd<'_>, flags: u32) -> io::Result<i32> {
+        let addfd = libc::seccomp_notif_addfd {
+            id: self.id,
+            flags,
+            srcfd: fd.as_raw_fd() as u32,
+            newfd: 0,
+            newfd_flags: libc::O_CLOEXEC as u32,
+        };
+        let ret =
+            unsafe { libc::ioctl(self.fd.as_raw_fd(), libc::SECCOMP_IOCTL_NOTIF_ADDFD, &addfd) };
+        match ret {
+            -1 => Err(io::Error::last_os_error()),
+            fd => Ok(fd),
+        }
+    }
+
+    fn respond(&self, response: Response) -> io::Result<()> {
+        let (id, flags) = (self.id, ScmpNotifRespFlags::empty());
+        let resp = match response {
+            Response::Continue => ScmpNotifResp::new_continue(id, ScmpNotifRespFlags::CONTINUE),
+            Response::Return(val) => ScmpNotifResp::new_val(id, val, flags),
+            Response::Errno(errno) => ScmpNotifResp::new(id, 0, -errno, 0),
+            Response::InjectFd(fd) => {
+                self.addfd(fd.as_fd(), libc::SECCOMP_ADDFD_FLAG_SEND as u32)?;
+                return Ok(());
+            }
+        };
+        resp.respond(self.fd.as_raw_fd()).map_err(io::Error::other)
+    }
+}
+
+/// A shared seccomp user-notification handler.
+#[derive(Clone)]
+pub(crate) struct Notifier(Arc<dyn SeccompHandler>);
+
+impl Notifier {
+    pub(crate) fn new<H: SeccompHandler>(handler: H) -> Self {
+        Self(Arc::new(handler))
+    }
+
+    /// Spawns a supervisor thread which handles the notifications until
+    /// all processes using the filter are gone.
+    pub(crate) fn supervise(&self, fd: OwnedFd) -> io::Result<()> {
+        let handler = self.0.clone();
+        thread::Builder::new()
+            .name("hakoniwa-seccomp".to_string())
+            .spawn(move || supervise(fd, handler))?;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Notifier")
+    }
+}
+
+fn supervise(fd: OwnedFd, handler: Arc<dyn SeccompHandler>) {
+    loop {
+        // POLLHUP is reported when there are no more processes using the
+        // filter.
+        let mut fds = [PollFd::new(fd.as_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, PollTimeout::NONE) {
+            Ok(_) => {}
+            Err(nix::Error::EINTR) => continue,
+            Err(_) => break,
+        }
+        let revents = fds[0].revents().unwrap_or(PollFlags::POLLHUP);
+        if !revents.contains(PollFlags::POLLIN) {
+            break;
+        }
+
+        // The target may have been killed before the notification could
+        // be received or responded, just ignore it.
+        let req = match ScmpNotifReq::receive(fd.as_raw_fd()) {
+            Ok(req) => req,
+            Err(_) => continue,
+        };
+        let notification = Notification::new(fd.as_fd(), req);
+        let response = handler.handle(&notification);
+        if let Err(err) = notification.respond(response) {
+            log::debug!("seccomp: respond to notification failed: {err}");
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! OCI seccomp profile, the format used by runc, crun, podman and docker.
+//!
+//! The parts of the profile which are not expressed by the rules, including
+//! unknown fields, are kept in the filter as its [Layout], so that a filter
+//! loaded from a profile can be exported without losing anything.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use super::{Action, Arch, ArgCmp, ArgCmpOp, Filter, Rule};
+use crate::Capability;
+use crate::error::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct Profile {
+    #[serde(rename = "defaultAction")]
+    pub(crate) default_action: String,
+    #[serde(rename = "defaultErrnoRet", skip_serializing_if = "Option::is_none")]
+    pub(crate) default_errno_ret: Option<i32>,
+    #[serde(rename = "architectures", skip_serializing_if = "Option::is_none")]
+    pub(crate) architectures: Option<Vec<String>>,
+    #[serde(rename = "archMap", skip_serializing_if = "Option::is_none")]
+    pub(crate) arch_map: Option<Vec<ProfileArchMap>>,
+    #[serde(rename = "syscalls", skip_serializing_if = "Option::is_none")]
+    pub(crate) syscalls: Option<Vec<ProfileSyscall>>,
+    #[serde(flatten)]
+    pub(crate) extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct ProfileArchMap {
+    #[serde(rename = "architecture")]
+    pub(crate) arch: String,
+    #[serde(rename = "subArchitectures", skip_serializing_if = "Option::is_none")]
+    pub(crate) sub_arches: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub(crate) extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct ProfileSyscall {
+    #[serde(rename = "names")]
+    pub(crate) names: Vec<String>,
+    #[serde(rename = "action")]
+    pub(crate) action: String,
+    #[serde(rename = "errnoRet", skip_serializing_if = "Option::is_none")]
+    pub(crate) errno_ret: Option<i32>,
+    #[serde(rename = "args", skip_serializing_if = "Option::is_none")]
+    pub(crate) args: Option<Vec<ProfileSyscallArg>>,
+    #[serde(rename = "includes", skip_serializing_if = "Option::is_none")]
+    pub(crate) includes: Option<ProfileFilter>,
+    #[serde(rename = "excludes", skip_serializing_if = "Option::is_none")]
+    pub(crate) excludes: Option<ProfileFilter>,
+    #[serde(flatten)]
+    pub(crate) extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct ProfileSyscallArg {
+    #[serde(rename = "index")]
+    pub(crate) index: u32,
+    #[serde(rename = "value")]
+    pub(crate) value: u64,
+    #[serde(rename = "valueTwo", skip_serializing_if = "Option::is_none")]
+    pub(crate) value_two: Option<u64>,
+    #[serde(rename = "op")]
+    pub(crate) op: String,
+    #[serde(flatten)]
+    pub(crate) extra: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct ProfileFilter {
+    #[serde(rename = "arches", skip_serializing_if = "Option::is_none")]
+    pub(crate) arches: Option<Vec<String>>,
+    #[serde(rename = "caps", skip_serializing_if = "Option::is_none")]
+    pub(crate) caps: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub(crate) extra: Map<String, Value>,
+}
+
+/// The parts of an OCI seccomp profile which are not expressed by the rules
+/// of the filter created from it, e.g. unknown fields, the `archMap` and the
+/// `includes`/`excludes` of the syscall entries. The filter is exported in
+/// the layout of the profile with them.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Layout {
+    default_action: (String, Option<i32>),
+    architectures: Option<Vec<String>>,
+    arch_map: Option<Vec<ProfileArchMap>>,
+    syscalls: bool,
+    extra: Map<String, Value>,
+    entries: Vec<LayoutEntry>,
+}
+
+/// A syscall entry of the profile, without its names, which are the
+/// [Rule]s referring to it.
+#[derive(Clone, Debug)]
+struct LayoutEntry {
+    action: (String, Option<i32>),
+    args: Option<Vec<ProfileSyscallArg>>,
+    includes: Option<ProfileFilter>,
+    excludes: Option<ProfileFilter>,
+    extra: Map<String, Value>,
+}
+
+impl Layout {
+    /// Returns true if the syscall entry applies to the runtime architecture
+    /// and the capabilities, which are all capabilities if None.
+    pub(crate) fn applies(&self, index: usize, caps: Option<&HashSet<Capability>>) -> bool {
+        self.entries
+            .get(index)
+            .is_none_or(|entry| entry.applies(caps))
+    }
+}
+
+impl LayoutEntry {
+    /// See [podman#setupSeccomp].
+    ///
+    /// [podman#setupSeccomp]: https://github.com/containers/podman/blob/27f42775ce9bbe2957a89a02b2e48e26e0645552/vendor/github.com/containers/common/pkg/seccomp/seccomp_linux.go#L101
+    fn applies(&self, caps: Option<&HashSet<Capability>>) -> bool {
+        let runtime_arch = Arch::runtime();
+        let excludes = self.excludes.clone().unwrap_or_default();
+        let arches = excludes.arches.unwrap_or_default();
+        let excluded_caps = excludes.caps.unwrap_or_default();
+        if !arches.is_empty() && contains_arch(&arches, runtime_arch) {
+            return false;
+        }
+        if excluded_caps.iter().any(|cap| contains_cap(cap, caps)) {
+            return false;
+        }
+
+        let includes = self.includes.clone().unwrap_or_default();
+        let arches = includes.arches.unwrap_or_default();
+        let included_caps = includes.caps.unwrap_or_default();
+        if !arches.is_empty() && !contains_arch(&arches, runtime_arch) {
+            return false;
+        }
+        included_caps.iter().all(|cap| contains_cap(cap, caps))
+    }
+}
+
+/// Creates a filter from the profile.
+pub(crate) fn from_json(data: &str) -> Result<Filter> {
+    let profile: Profile =
+        serde_json::from_str(data).map_err(|e| Error::SeccompError(e.to_string()))?;
+    if profile
+        .architectures
+        .as_ref()
+        .is_some_and(|v| !v.is_empty())
+        && profile.arch_map.as_ref().is_some_and(|v| !v.is_empty())
+    {
+        let msg = "'architectures' and 'archMap' were specified in the seccomp profile, use either 'architectures' or 'archMap'";
+        Err(Error::SeccompError(msg.to_string()))?;
+    }
+
+    let errno_ret = profile.default_errno_ret.unwrap_or_default();
+    let default_action = str_to_action(&profile.default_action, errno_ret)?;
+
+    // The architectures for the runtime architecture.
+    let mut architectures = HashSet::new();
+    for arch in profile.architectures.iter().flatten() {
+        architectures.insert(str_to_arch(arch)?);
+    }
+    let runtime_arch = Arch::runtime();
+    for map in profile.arch_map.iter().flatten() {
+        if runtime_arch == str_to_arch(&map.arch)? {
+            architectures.insert(runtime_arch);
+            for sub_arch in map.sub_arches.iter().flatten() {
+                architectures.insert(str_to_arch(sub_arch)?);
+            }
+            break;
+        }
+    }
+
+    // The rules of every entry, whether it applies is decided when the
+    // filter is compiled.
+    let mut rules = vec![];
+    let mut entries = vec![];
+    for (index, syscall) in profile.syscalls.iter().flatten().enumerate() {
+        let action = str_to_action(&syscall.action, syscall.errno_ret.unwrap_or_default())?;
+        let argcmps = args_to_argcmps(syscall.args.as_deref().unwrap_or_default())?;
+        for name in &syscall.names {
+            rules.push(Rule {
+                action,
+                sysname: name.to_string(),
+                argcmps: argcmps.clone(),
+                entry: Some(index),
+            });
+        }
+        entries.push(LayoutEntry {
+            action: (syscall.action.clone(), syscall.errno_ret),
+            args: syscall.args.clone(),
+            includes: syscall.includes.clone(),
+            excludes: syscall.excludes.clone(),
+            extra: syscall.extra.clone(),
+        });
+    }
+
+    let layout = Layout {
+        default_action: (profile.default_action, profile.default_errno_ret),
+        architectures: profile.architectures,
+        arch_map: profile.arch_map,
+        syscalls: profile.syscalls.is_some(),
+        extra: profile.extra,
+        entries,
+    };
+    Ok(Filter {
+        default_action,
+        architectures,
+        rules,
+        layout: Some(layout),
+    })
+}
+
+/// Exports the filter as a profile, in the layout of the profile it was
+/// created from, if any.
+pub(crate) fn to_json(filter: &Filter) -> Result<String> {
+    let layout = filter.layout.clone().unwrap_or_default();
+    let (default_action, default_errno_ret) =
+        written_action(&layout.default_action, filter.default_action);
+
+    // Architectures added to the filter are added to the `archMap` entry of
+    // the runtime architecture if `archMap` is used.
+    let mut arches = filter.architectures.iter().copied().collect::<Vec<_>>();
+    arches.sort_by_key(|arch| arch.to_oci_str());
+    let mut architectures = layout.architectures;
+    let mut arch_map = layout.arch_map;
+    match &mut arch_map {
+        Some(arch_map) if !arch_map.is_empty() => {
+            for arch in arches {
+                add_arch_map(arch_map, arch);
+            }
+        }
+        _ => {
+            for arch in arches {
+                let name = arch.to_oci_str().to_string();
+                let architectures = architectures.get_or_insert_default();
+                if !architectures.contains(&name) {
+                    architectures.push(name);
+                }
+            }
+        }
+    }
+
+    // Rules of the same entry are merged back into it, rules added to the
+    // filter are merged into the last entry if possible.
+    let mut syscalls: Vec<ProfileSyscall> = vec![];
+    let mut last_entry = None;
+    for rule in &filter.rules {
+        let entry = rule.entry.and_then(|index| layout.entries.get(index));
+        if let (Some(last), Some(_)) = (syscalls.last_mut(), entry)
+            && rule.entry == last_entry
+        {
+            last.names.push(rule.sysname.clone());
+            continue;
+        }
+
+        let syscall = match entry {
+            Some(entry) => {
+                let (action, errno_ret) = written_action(&entry.action, rule.action);
+                ProfileSyscall {
+                    names: vec![rule.sysname.clone()],
+                    action,
+                    errno_ret,
+                    args: written_args(&entry.args, &rule.argcmps),
+                    includes: entry.includes.clone(),
+                    excludes: entry.excludes.clone(),
+                    extra: entry.extra.clone(),
+                }
+            }
+            None => {
+                let (action, errno_ret) = action_to_str(rule.action);
+                ProfileSyscall {
+                    names: vec![rule.sysname.clone()],
+                    action: action.to_string(),
+                    errno_ret,
+                    args: written_args(&None, &rule.argcmps),
+                    ..Default::default()
+                }
+            }
+        };
+        last_entry = rule.entry;
+
+        if let Some(last) = syscalls.last_mut()
+            && entry.is_none()
+            && last.action == syscall.action
+            && last.errno_ret == syscall.errno_ret
+            && last.args == syscall.args
+            && last.includes.is_none()
+            && last.excludes.is_none()
+            && last.extra.is_empty()
+        {
+            last.names.push(rule.sysname.clone());
+            continue;
+        }
+        syscalls.push(syscall);
+    }
+
+    let profile = Profile {
+        default_action,
+        default_errno_ret,
+        architectures,
+        arch_map,
+        syscalls: match syscalls.is_empty() && !layout.syscalls {
+            true => None,
+            false => Some(syscalls),
+        },
+        extra: layout.extra,
+    };
+    serde_json::to_string_pretty(&profile).map_err(|e| Error::SeccompError(e.to_string()))
+}
+
+/// Adds an architecture to the `archMap` entry of the runtime architecture.
+fn add_arch_map(arch_map: &mut Vec<ProfileArchMap>, arch: Arch) {
+    let name = arch.to_oci_str().to_string();
+    let runtime_arch = Arch::runtime().to_oci_str();
+    match arch_map.iter_mut().find(|map| map.arch == runtime_arch) {
+        Some(map) if map.arch == name => {}
+        Some(map) => {
+            let sub_arches = map.sub_arches.get_or_insert_default();
+            if !sub_arches.contains(&name) {
+                sub_arches.push(name);
+            }
+        }
+        None => arch_map.push(ProfileArchMap {
+            arch: runtime_arch.to_string(),
+            sub_arches: Some(vec![name]),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Returns the action as written in the profile if it is the same action,
+/// e.g. `SCMP_ACT_KILL` or an `SCMP_ACT_ERRNO` without `errnoRet`.
+fn written_action(written: &(String, Option<i32>), action: Action) -> (String, Option<i32>) {
+    let (name, errno_ret) = written;
+    match str_to_action(name, errno_ret.unwrap_or_default()) {
+        Ok(a) if a == action => written.clone(),
+        _ => {
+            let (name, errno_ret) = action_to_str(action);
+            (name.to_string(), errno_ret)
+        }
+    }
+}
+
+/// Returns the args as written in the profile if they are the same argcmps,
+/// e.g. without `valueTwo`.
+fn written_args(
+    written: &Option<Vec<ProfileSyscallArg>>,
+    argcmps: &[ArgCmp],
+) -> Option<Vec<ProfileSyscallArg>> {
+    let same = args_to_argcmps(written.as_deref().unwrap_or_default())
+        .is_ok_and(|written| written == argcmps);
+    match (same, argcmps.is_empty()) {
+        (true, _) => written.clone(),
+        (false, true) => None,
+        (false, false) => Some(argcmps.iter().map(argcmp_to_arg).collect()),
+    }
+}
+
+fn str_to_action(action: &str, errno: i32) -> Result<Action> {
+    Ok(match action {
+        "SCMP_ACT_ALLOW" => Action::Allow,
+        "SCMP_ACT_ERRNO" => Action::Errno(errno),
+        "SCMP_ACT_KILL_PROCESS" => Action::KillProcess,
+        "SCMP_ACT_KILL_THREAD" => Action::KillThread,
+        "SCMP_ACT_KILL" => Action::KillThread,
+        "SCMP_ACT_LOG" => Action::Log,
+        "SCMP_ACT_NOTIFY" => Action::Notify,
+        "SCMP_ACT_TRACE" => Action::Trace(errno as u16),
+        "SCMP_ACT_TRAP" => Action::Trap,
+        _ => Err(Error::SeccompError(format!("unknown action {action:?}")))?,
+    })
+}
+
+fn action_to_str(action: Action) -> (&'static str, Option<i32>) {
+    match action {
+        Action::Allow => ("SCMP_ACT_ALLOW", None),
+        Action::Errno(errno) => ("SCMP_ACT_ERRNO", Some(errno)),
+        Action::KillProcess => ("SCMP_ACT_KILL_PROCESS", None),
+        Action::KillThread => ("SCMP_ACT_KILL_THREAD", None),
+        Action::Log => ("SCMP_ACT_LOG", None),
+        Action::Notify => ("SCMP_ACT_NOTIFY", None),
+        Action::Trace(errno) => ("SCMP_ACT_TRACE", Some(errno as i32)),
+        Action::Trap => ("SCMP_ACT_TRAP", None),
+    }
+}
+
+fn str_to_arch(arch: &str) -> Result<Arch> {
+    Arch::from_oci_str(arch).ok_or_else(|| Error::SeccompError(format!("unknown arch {arch:?}")))
+}
+
+const ARGCMP_OPS: [(ArgCmpOp, &str); 7] = [
+    (ArgCmpOp::Ne, "SCMP_CMP_NE"),
+    (ArgCmpOp::Lt, "SCMP_CMP_LT"),
+    (ArgCmpOp::Le, "SCMP_CMP_LE"),
+    (ArgCmpOp::Eq, "SCMP_CMP_EQ"),
+    (ArgCmpOp::Ge, "SCMP_CMP_GE"),
+    (ArgCmpOp::Gt, "SCMP_CMP_GT"),
+    (ArgCmpOp::MaskedEq, "SCMP_CMP_MASKED_EQ"),
+];
+
+fn str_to_argcmp_op(op: &str) -> Result<ArgCmpOp> {
+    match ARGCMP_OPS.iter().find(|(_, name)| *name == op) {
+        Some((op, _)) => Ok(*op),
+        None => Err(Error::SeccompError(format!("unknown argcmp op {op:?}"))),
+    }
+}
+
+fn args_to_argcmps(args: &[ProfileSyscallArg]) -> Result<Vec<ArgCmp>> {
+    args.iter()
+        .map(|arg| {
+            let op = str_to_argcmp_op(&arg.op)?;
+            let datum_b = arg.value_two.unwrap_or_default();
+            Ok(ArgCmp::new(arg.index, op, arg.value, datum_b))
+        })
+        .collect()
+}
+
+fn argcmp_to_arg(argcmp: &ArgCmp) -> ProfileSyscallArg {
+    let (_, op) = ARGCMP_OPS
+        .iter()
+        .find(|(op, _)| *op == argcmp.op)
+        .expect("ARGCMP_OPS");
+    ProfileSyscallArg {
+        index: argcmp.arg,
+        value: argcmp.datum_a,
+        value_two: Some(argcmp.datum_b),
+        op: op.to_string(),
+        ..Default::default()
+    }
+}
+
+fn contains_arch(arches: &[String], runtime_arch: Arch) -> bool {
+    arches.iter().any(|str| match Arch::from_str(str) {
+        Ok(arch) => arch == runtime_arch,
+        Err(_) => false,
+    })
+}
+
+fn contains_cap(cap: &str, caps: Option<&HashSet<Capability>>) -> bool {
+    match caps {
+        Some(caps) => Capability::from_str(cap).is_ok_and(|cap| caps.contains(&cap)),
+        None => true,
+    }
+}
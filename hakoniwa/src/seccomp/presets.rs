@@ -0,0 +1,20 @@
+//! Built-in seccomp profiles.
+
+use super::Filter;
+
+const AUDIT: &str = include_str!("presets/audit.json");
+const PODMAN: &str = include_str!("presets/podman.json");
+
+/// A profile which allows and logs all syscalls, useful for finding out what
+/// syscalls an application uses.
+pub fn audit() -> Filter {
+    Filter::from_oci_json(AUDIT).expect("presets/audit.json is valid")
+}
+
+/// The default profile of [podman], which blocks the syscalls that are
+/// dangerous or not namespaced.
+///
+/// [podman]: https://github.com/containers/common/blob/main/pkg/seccomp/seccomp.json
+pub fn podman() -> Filter {
+    Filter::from_oci_json(PODMAN).expect("presets/podman.json is valid")
+}
@@ -6,6 +6,11 @@ pub struct Rule {
     pub action: Action,
     pub sysname: String,
     pub(crate) argcmps: Vec<ArgCmp>,
+    // The index of the syscall entry of the OCI seccomp profile the rule is
+    // created from, see [Layout].
+    //
+    // [Layout]: super::oci::Layout
+    pub(crate) entry: Option<usize>,
 }
 
 impl std::fmt::Display for Rule {
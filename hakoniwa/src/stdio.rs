@@ -1,12 +1,21 @@
-use std::io::{PipeReader, PipeWriter, pipe};
+use nix::sys::memfd::{self, MFdFlags};
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{PipeReader, PipeWriter, SeekFrom, pipe};
+use std::os::fd::OwnedFd;
+use std::sync::Arc;
 
 use crate::error::*;
 
 /// Describes what to do with a standard I/O stream.
-#[derive(Clone, Copy, Debug)]
+///
+/// Since it may own a file descriptor, it is [Clone] but no longer [Copy].
+#[derive(Clone, Debug)]
 pub enum Stdio {
     Inherit,
     MakePipe,
+    Null,
+    Fd(Arc<OwnedFd>),
 }
 
 impl Stdio {
@@ -20,14 +29,76 @@ impl Stdio {
         Self::MakePipe
     }
 
-    /// Create a pipe that arranged to connect the parent and child processes.
-    pub(crate) fn make_pipe(io: Self) -> Result<(Option<PipeReader>, Option<PipeWriter>)> {
+    /// This stream will be ignored, the same as attaching it to `/dev/null`.
+    pub fn null() -> Self {
+        Self::Null
+    }
+
+    /// Arrange the parent and child processes for the stdin stream, returns
+    /// (child side, parent side).
+    pub(crate) fn make_stdin(io: &Self) -> Result<(Option<OwnedFd>, Option<PipeWriter>)> {
         Ok(match io {
             Self::Inherit => (None, None),
             Self::MakePipe => {
                 let pipe = pipe().map_err(ProcessErrorKind::StdIoError)?;
-                (Some(pipe.0), Some(pipe.1))
+                (Some(pipe.0.into()), Some(pipe.1))
             }
+            Self::Null => (Some(Self::open_devnull()?), None),
+            Self::Fd(fd) => (Some(Self::try_clone(fd)?), None),
         })
     }
+
+    /// Arrange the parent and child processes for the stdout/stderr stream,
+    /// returns (parent side, child side).
+    pub(crate) fn make_output(io: &Self) -> Result<(Option<PipeReader>, Option<OwnedFd>)> {
+        Ok(match io {
+            Self::Inherit => (None, None),
+            Self::MakePipe => {
+                let pipe = pipe().map_err(ProcessErrorKind::StdIoError)?;
+                (Some(pipe.0), Some(pipe.1.into()))
+            }
+            Self::Null => (None, Some(Self::open_devnull()?)),
+            Self::Fd(fd) => (None, Some(Self::try_clone(fd)?)),
+        })
+    }
+
+    /// Create an in-memory file that contains `bytes`, used as stdin. Unlike
+    /// a pipe, it never blocks the parent process no matter how large it is.
+    pub(crate) fn make_bytes(bytes: &[u8]) -> Result<OwnedFd> {
+        let fd = memfd::memfd_create("hakoniwa-stdin", MFdFlags::MFD_CLOEXEC)
+            .map_err(ProcessErrorKind::NixError)?;
+        let mut file = File::from(fd);
+        file.write_all(bytes)
+            .and_then(|_| file.seek(SeekFrom::Start(0)))
+            .map_err(ProcessErrorKind::StdIoError)?;
+        Ok(file.into())
+    }
+
+    fn open_devnull() -> Result<OwnedFd> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .open("/dev/null")
+            .map_err(ProcessErrorKind::StdIoError)?;
+        Ok(file.into())
+    }
+
+    fn try_clone(fd: &OwnedFd) -> Result<OwnedFd> {
+        let fd = fd.try_clone().map_err(ProcessErrorKind::StdIoError)?;
+        Ok(fd)
+    }
+}
+
+impl From<File> for Stdio {
+    /// Converts a [File] into a [Stdio], e.g. redirects the output to a log file.
+    fn from(file: File) -> Self {
+        Self::Fd(Arc::new(file.into()))
+    }
+}
+
+impl From<OwnedFd> for Stdio {
+    /// Converts an [OwnedFd] into a [Stdio].
+    fn from(fd: OwnedFd) -> Self {
+        Self::Fd(Arc::new(fd))
+    }
 }
@@ -0,0 +1,176 @@
+use nix::errno::Errno;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{self, BufReader, PipeReader, pipe};
+use std::os::fd::OwnedFd;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+// The syscalls returning an address, which is shown in hex.
+const RETURN_ADDRS: &[&str] = &["brk", "mmap", "mremap"];
+
+/// A syscall made by the internal process or its descendants, which is
+/// traced through [Runctl::TraceSyscalls].
+///
+/// [Runctl::TraceSyscalls]: crate::Runctl::TraceSyscalls
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TraceEvent {
+    /// The process that made the syscall, as seen outside the container.
+    pub pid: i32,
+
+    /// The name of the syscall.
+    pub sysname: String,
+
+    /// The decoded arguments, e.g. `AT_FDCWD`, `"/etc/hosts"`, `O_RDONLY`.
+    pub args: Vec<String>,
+
+    /// The return value, or None if the syscall does not return, e.g.
+    /// `exit_group`.
+    pub retval: Option<i64>,
+}
+
+impl TraceEvent {
+    /// Returns the errno if the syscall failed.
+    pub fn errno(&self) -> Option<i32> {
+        match self.retval {
+            Some(retval) if (-4095..0).contains(&retval) => Some(-retval as i32),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TraceEvent {
+    /// Formats the event in the style of `strace -f`, e.g.
+    /// `[pid 42] openat(AT_FDCWD, "/etc/hosts", O_RDONLY|O_CLOEXEC) = 3`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[pid {}] {}({})",
+            self.pid,
+            self.sysname,
+            self.args.join(", ")
+        )?;
+        match (self.retval, self.errno()) {
+            (_, Some(errno)) => {
+                let errno = Errno::from_raw(errno);
+                write!(f, " = -1 {:?} ({})", errno, errno.desc())
+            }
+            (Some(retval), None) if RETURN_ADDRS.contains(&self.sysname.as_str()) => {
+                write!(f, " = {retval:#x}")
+            }
+            (Some(retval), None) => write!(f, " = {retval}"),
+            (None, None) => write!(f, " = ?"),
+        }
+    }
+}
+
+/// A handler of the traced syscalls, which is invoked in a thread of the
+/// main process.
+///
+/// Any `Fn(&TraceEvent)` closure can be used as a handler.
+pub trait TraceHandler: Send + Sync + 'static {
+    /// Handles the traced syscall.
+    fn handle(&self, event: &TraceEvent);
+}
+
+impl<F> TraceHandler for F
+where
+    F: Fn(&TraceEvent) + Send + Sync + 'static,
+{
+    fn handle(&self, event: &TraceEvent) {
+        self(event)
+    }
+}
+
+/// Describes where the traced syscalls go.
+#[derive(Clone)]
+pub struct TraceSink(Sink);
+
+#[derive(Clone)]
+enum Sink {
+    Stderr,
+    File(Arc<File>),
+    Handler(Arc<dyn TraceHandler>),
+}
+
+impl TraceSink {
+    /// The traced syscalls are written to the stderr of the main process,
+    /// one line per syscall.
+    pub fn stderr() -> Self {
+        Self(Sink::Stderr)
+    }
+
+    /// The traced syscalls are passed to `handler`.
+    pub fn handler<H: TraceHandler>(handler: H) -> Self {
+        Self(Sink::Handler(Arc::new(handler)))
+    }
+
+    /// Spawns a thread which reads the traced syscalls sent by the child
+    /// process, until the write end of the pipe is closed, returns (the
+    /// thread, a pipe which is closed once the thread finishes).
+    pub(crate) fn drain(&self, reader: PipeReader) -> io::Result<(JoinHandle<()>, PipeReader)> {
+        let sink = self.0.clone();
+        let (done_reader, done_writer) = pipe()?;
+        let thread = thread::Builder::new()
+            .name("hakoniwa-trace".to_string())
+            .spawn(move || {
+                drain(reader, sink);
+                drop(done_writer);
+            })?;
+        Ok((thread, done_reader))
+    }
+}
+
+impl Default for TraceSink {
+    fn default() -> Self {
+        Self::stderr()
+    }
+}
+
+impl fmt::Debug for TraceSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Sink::Stderr => f.write_str("TraceSink::Stderr"),
+            Sink::File(file) => write!(f, "TraceSink::File({file:?})"),
+            Sink::Handler(_) => f.write_str("TraceSink::Handler"),
+        }
+    }
+}
+
+impl From<File> for TraceSink {
+    /// Converts a [File] into a [TraceSink], the traced syscalls are written
+    /// to it one line per syscall.
+    fn from(file: File) -> Self {
+        Self(Sink::File(Arc::new(file)))
+    }
+}
+
+impl From<OwnedFd> for TraceSink {
+    /// Converts an [OwnedFd] into a [TraceSink].
+    fn from(fd: OwnedFd) -> Self {
+        Self(Sink::File(Arc::new(File::from(fd))))
+    }
+}
+
+fn drain(reader: PipeReader, sink: Sink) {
+    let mut reader = BufReader::new(reader);
+    let config = bincode::config::standard();
+    // The pipe is closed when the child process exits.
+    while let Ok(event) =
+        bincode::serde::decode_from_std_read::<TraceEvent, _, _>(&mut reader, config)
+    {
+        let result = match &sink {
+            Sink::Stderr => io::stderr().write_all(format!("{event}\n").as_bytes()),
+            Sink::File(file) => file.as_ref().write_all(format!("{event}\n").as_bytes()),
+            Sink::Handler(handler) => {
+                handler.handle(&event);
+                Ok(())
+            }
+        };
+        if let Err(err) = result {
+            log::debug!("trace: write event failed: {err}");
+        }
+    }
+}
@@ -6,7 +6,9 @@ mod newuser;
 pub(crate) use newns::{FsMakeDir, FsMakeSymlink, FsOperation, FsWriteFile, Mount};
 pub(crate) use newuser::IdMap;
 
-pub use namespace::Namespace;
+pub(crate) use namespace::NamespaceRefImp;
+
+pub use namespace::{Namespace, NamespaceRef};
 pub use newnet::{Network, Pasta};
 pub use newns::MountOptions;
 
@@ -1,4 +1,7 @@
 use nix::sched::CloneFlags;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Linux namespace types.
 ///
@@ -42,3 +45,58 @@ impl std::fmt::Display for Namespace {
         }
     }
 }
+
+/// Reference to an existing namespace, used by [Container::join].
+///
+/// It can be constructed from a path such as `/proc/<pid>/ns/net` or a
+/// bind-mounted ns file, or from a file descriptor that refers to such a
+/// file or to a process (pidfd).
+///
+/// [Container::join]: crate::Container::join
+#[derive(Clone, Debug)]
+pub struct NamespaceRef(pub(crate) NamespaceRefImp);
+
+#[derive(Clone, Debug)]
+pub(crate) enum NamespaceRefImp {
+    Path(PathBuf),
+    Fd(Arc<OwnedFd>),
+}
+
+impl From<&str> for NamespaceRef {
+    fn from(path: &str) -> Self {
+        Self(NamespaceRefImp::Path(PathBuf::from(path)))
+    }
+}
+
+impl From<String> for NamespaceRef {
+    fn from(path: String) -> Self {
+        Self(NamespaceRefImp::Path(PathBuf::from(path)))
+    }
+}
+
+impl From<&Path> for NamespaceRef {
+    fn from(path: &Path) -> Self {
+        Self(NamespaceRefImp::Path(path.to_path_buf()))
+    }
+}
+
+impl From<PathBuf> for NamespaceRef {
+    fn from(path: PathBuf) -> Self {
+        Self(NamespaceRefImp::Path(path))
+    }
+}
+
+impl From<OwnedFd> for NamespaceRef {
+    fn from(fd: OwnedFd) -> Self {
+        Self(NamespaceRefImp::Fd(Arc::new(fd)))
+    }
+}
+
+impl std::fmt::Display for NamespaceRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            NamespaceRefImp::Path(path) => write!(f, "{}", path.to_string_lossy()),
+            NamespaceRefImp::Fd(fd) => write!(f, "fd:{}", fd.as_raw_fd()),
+        }
+    }
+}
@@ -1,22 +1,64 @@
+use nix::libc::MS_NOSYMFOLLOW;
 use nix::mount::MsFlags;
 
+use crate::error::UnErrorKind;
+
 bitflags::bitflags! {
     /// Mount flags.
     ///
+    /// The propagation flags (PRIVATE, SLAVE, SHARED, UNBINDABLE) change the
+    /// propagation type of a bind mount after it is created, the R prefixed
+    /// ones apply to all of its submounts too. At most one of them can be
+    /// set, bind mounts are RPRIVATE by default.
+    ///
+    /// The IDMAP flag makes a bind mount id-mapped, see [Container::idmapped_owner].
+    ///
+    /// [Container::idmapped_owner]: crate::Container::idmapped_owner
     /// [mount]: https://man7.org/linux/man-pages/man2/mount.2.html
     /// [mount.h]: https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/mount.h
+    /// [sharedsubtree]: https://docs.kernel.org/filesystems/sharedsubtree.html
     #[derive(Hash, Eq, PartialEq, Clone, Copy, Debug)]
     pub struct MountOptions: u32 {
-        const RDONLY = 1;
-        const NOSUID = 2;
-        const NODEV  = 4;
-        const NOEXEC = 8;
-        const BIND   = 4096;
-        const REC    = 16384;
+        const RDONLY      = 1;
+        const NOSUID      = 2;
+        const NODEV       = 4;
+        const NOEXEC      = 8;
+        const NOSYMFOLLOW = 256;
+        const NOATIME     = 1024;
+        const BIND        = 4096;
+        const REC         = 16384;
+        const UNBINDABLE  = 131072;
+        const PRIVATE     = 262144;
+        const SLAVE       = 524288;
+        const SHARED      = 1048576;
+        const RELATIME    = 2097152;
+        const RUNBINDABLE = 67108864;
+        const RPRIVATE    = 134217728;
+        const RSLAVE      = 268435456;
+        const RSHARED     = 536870912;
+        const IDMAP       = 2147483648;
     }
 }
 
 impl MountOptions {
+    const PROPAGATION: Self = Self::UNBINDABLE
+        .union(Self::PRIVATE)
+        .union(Self::SLAVE)
+        .union(Self::SHARED)
+        .union(Self::RUNBINDABLE)
+        .union(Self::RPRIVATE)
+        .union(Self::RSLAVE)
+        .union(Self::RSHARED);
+
+    /// Rejects more than one propagation flag.
+    pub(crate) fn validate(self) -> Result<(), UnErrorKind> {
+        if self.intersection(Self::PROPAGATION).iter().count() > 1 {
+            let errmsg = "more than one propagation type".to_string();
+            Err(UnErrorKind::InvalidMountOptions(errmsg))?;
+        }
+        Ok(())
+    }
+
     pub(crate) fn to_ms_flags(self) -> MsFlags {
         let mut flags = MsFlags::empty();
         for option in self.iter() {
@@ -25,13 +67,31 @@ impl MountOptions {
                 Self::NOSUID => flags.insert(MsFlags::MS_NOSUID),
                 Self::NODEV => flags.insert(MsFlags::MS_NODEV),
                 Self::NOEXEC => flags.insert(MsFlags::MS_NOEXEC),
+                Self::NOSYMFOLLOW => flags.insert(MsFlags::from_bits_retain(MS_NOSYMFOLLOW)),
+                Self::NOATIME => flags.insert(MsFlags::MS_NOATIME),
                 Self::BIND => flags.insert(MsFlags::MS_BIND),
                 Self::REC => flags.insert(MsFlags::MS_REC),
+                Self::RELATIME => flags.insert(MsFlags::MS_RELATIME),
                 _ => {}
             }
         }
         flags
     }
+
+    /// Returns the flags to change the propagation type, defaults to rprivate.
+    pub(crate) fn to_propagation_ms_flags(self) -> MsFlags {
+        let rec = MsFlags::MS_REC;
+        match self.intersection(Self::PROPAGATION) {
+            Self::UNBINDABLE => MsFlags::MS_UNBINDABLE,
+            Self::PRIVATE => MsFlags::MS_PRIVATE,
+            Self::SLAVE => MsFlags::MS_SLAVE,
+            Self::SHARED => MsFlags::MS_SHARED,
+            Self::RUNBINDABLE => MsFlags::MS_UNBINDABLE | rec,
+            Self::RSLAVE => MsFlags::MS_SLAVE | rec,
+            Self::RSHARED => MsFlags::MS_SHARED | rec,
+            _ => MsFlags::MS_PRIVATE | rec,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -40,6 +100,55 @@ pub(crate) struct Mount {
     pub(crate) target: String,
     pub(crate) fstype: String,
     pub(crate) options: MountOptions,
+    pub(crate) data: String,
+}
+
+impl Mount {
+    /// Constructs a new overlayfs Mount.
+    ///
+    /// The paths must not contain `\`, `,` or `:`, which are the separators
+    /// of the mount data.
+    pub(crate) fn overlay<P: AsRef<str>>(
+        lowerdirs: &[P],
+        upperdir: &str,
+        workdir: &str,
+        target: String,
+    ) -> Result<Self, UnErrorKind> {
+        let lowerdirs = lowerdirs.iter().map(|dir| dir.as_ref()).collect::<Vec<_>>();
+        if lowerdirs.is_empty() {
+            let errmsg = "at least one lowerdir is required".to_string();
+            Err(UnErrorKind::InvalidOverlayMount(errmsg))?;
+        }
+        for path in lowerdirs.iter().chain([&upperdir, &workdir]) {
+            if path.is_empty() || path.contains(['\\', ',', ':']) {
+                let errmsg = format!("unsupported path {path:?}");
+                Err(UnErrorKind::InvalidOverlayMount(errmsg))?;
+            }
+        }
+
+        let data = format!(
+            "lowerdir={},upperdir={},workdir={}",
+            lowerdirs.join(":"),
+            upperdir,
+            workdir
+        );
+        Ok(Self {
+            source: "overlay".to_string(),
+            target,
+            fstype: "overlay".to_string(),
+            options: MountOptions::NOSUID,
+            data,
+        })
+    }
+
+    /// Returns all host paths referred by the mount data.
+    pub(crate) fn data_paths(&self) -> Vec<&str> {
+        self.data
+            .split(',')
+            .filter_map(|option| option.split_once('='))
+            .flat_map(|(_, paths)| paths.split(':'))
+            .collect()
+    }
 }
 
 impl std::fmt::Display for Mount {
@@ -48,6 +157,7 @@ impl std::fmt::Display for Mount {
             "devfs" => return write!(f, "  devfs: {}", self.target),
             "tmpfs" => return write!(f, "  tmpfs: {}", self.target),
             "proc" => return write!(f, "   proc: {}", self.target),
+            "overlay" => return write!(f, "overlay: {} -> {}", self.data, self.target),
             _ => {}
         };
 
@@ -0,0 +1,120 @@
+#[cfg(all(test, feature = "tokio"))]
+mod async_child_test {
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use hakoniwa::{Command, Container, Stdio};
+
+    fn command(program: &str) -> Command {
+        Container::new().rootfs("/").unwrap().command(program)
+    }
+
+    #[tokio::test]
+    async fn test_wait() {
+        let mut child = command("/bin/sh")
+            .args(["-c", "exit 3"])
+            .spawn_async()
+            .unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.code, 3);
+        assert_eq!(status.exit_code, Some(3));
+        assert!(status.rusage.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_wait_timeout() {
+        let mut child = command("/bin/sleep")
+            .arg("4")
+            .wait_timeout(1)
+            .spawn_async()
+            .unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.code, 128 + 9);
+        assert_eq!(status.reason, "process(/bin/sleep) received signal SIGKILL");
+    }
+
+    #[tokio::test]
+    async fn test_wait_cancelled() {
+        let mut child = command("/bin/sh")
+            .args(["-c", "sleep 1; exit 3"])
+            .spawn_async()
+            .unwrap();
+        let result = tokio::time::timeout(Duration::from_millis(100), child.wait()).await;
+        assert!(result.is_err());
+
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.code, 3);
+        assert_eq!(status.exit_code, Some(3));
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.code, 3);
+    }
+
+    #[tokio::test]
+    async fn test_wait_killed() {
+        let mut child = command("/bin/sleep").arg("4").spawn_async().unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        child.kill().unwrap();
+        let status = child.wait().await.unwrap();
+        assert_eq!(status.code, 125);
+        assert_eq!(status.reason, "container received signal SIGKILL");
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[tokio::test]
+    async fn test_wait_trace() {
+        use hakoniwa::{Runctl, TraceEvent, TraceSink};
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let events_clone = events.clone();
+        let mut child = Container::new()
+            .runctl(Runctl::TraceSyscalls)
+            .trace_sink(TraceSink::handler(move |event: &TraceEvent| {
+                events_clone.lock().unwrap().push(event.sysname.clone());
+            }))
+            .rootfs("/")
+            .unwrap()
+            .command("/bin/true")
+            .spawn_async()
+            .unwrap();
+        assert!(child.wait().await.unwrap().success());
+
+        // All the traced syscalls are sent once the status is returned.
+        let events = events.lock().unwrap();
+        assert_eq!(events.first().unwrap(), "execve");
+        assert_eq!(events.last().unwrap(), "exit_group");
+    }
+
+    #[tokio::test]
+    async fn test_wait_with_output() {
+        let child = command("/bin/sh")
+            .args(["-c", "echo out; echo err >&2"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn_async()
+            .unwrap();
+        let output = child.wait_with_output().await.unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+    }
+
+    #[tokio::test]
+    async fn test_stdin_stdout() {
+        let mut child = command("/bin/cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn_async()
+            .unwrap();
+
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(b"hello").await.unwrap();
+        drop(stdin);
+
+        let mut stdout = String::new();
+        let mut reader = child.stdout.take().unwrap();
+        reader.read_to_string(&mut stdout).await.unwrap();
+        assert_eq!(stdout, "hello");
+        assert!(child.wait().await.unwrap().success());
+    }
+}
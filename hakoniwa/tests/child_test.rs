@@ -1,5 +1,7 @@
 #[cfg(test)]
 mod child_test {
+    use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+    use std::os::fd::AsFd;
     use std::{thread, time};
 
     use hakoniwa::{Command, Container};
@@ -95,6 +97,27 @@ mod child_test {
         assert_eq!(status.rusage.unwrap().real_time.as_secs(), 1);
     }
 
+    #[test]
+    fn test_wait_timeout_multiple() {
+        let mut child1 = command("/bin/sleep")
+            .arg("4")
+            .wait_timeout(1)
+            .spawn()
+            .unwrap();
+        let mut child2 = command("/bin/sleep")
+            .arg("4")
+            .wait_timeout(2)
+            .spawn()
+            .unwrap();
+
+        let status = child2.wait().unwrap();
+        assert_eq!(status.code, 128 + 9);
+        assert_eq!(status.rusage.unwrap().real_time.as_secs(), 2);
+        let status = child1.wait().unwrap();
+        assert_eq!(status.code, 128 + 9);
+        assert_eq!(status.rusage.unwrap().real_time.as_secs(), 1);
+    }
+
     #[test]
     fn test_wait_killed() {
         let mut child = command("/bin/sleep").arg("1").spawn().unwrap();
@@ -118,4 +141,25 @@ mod child_test {
         let status = child.wait().unwrap();
         assert!(status.success());
     }
+
+    #[test]
+    fn test_kill_after_wait() {
+        let mut child = command("/bin/true").spawn().unwrap();
+
+        let status = child.wait().unwrap();
+        assert!(status.success());
+        child.kill().unwrap();
+    }
+
+    #[test]
+    fn test_as_fd() {
+        let mut child = command("/bin/sleep").arg("1").spawn().unwrap();
+
+        let mut fds = [PollFd::new(child.as_fd(), PollFlags::POLLIN)];
+        assert_eq!(poll(&mut fds, PollTimeout::ZERO).unwrap(), 0);
+        assert_eq!(poll(&mut fds, PollTimeout::from(3000u16)).unwrap(), 1);
+
+        let status = child.try_wait().unwrap();
+        assert!(status.unwrap().success());
+    }
 }
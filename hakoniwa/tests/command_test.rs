@@ -2,7 +2,9 @@
 mod command_test {
     use assertables::*;
     use std::collections::HashMap;
+    use std::fs::{self, File};
     use std::io::prelude::*;
+    use std::os::fd::OwnedFd;
 
     use hakoniwa::{Command, Container, Runctl, Stdio};
 
@@ -51,6 +53,87 @@ mod command_test {
         assert_contains!(String::from_utf8_lossy(&output.stdout), "MYENV2=2\n");
     }
 
+    #[test]
+    fn test_env_inherit() {
+        let path = std::env::var("PATH").unwrap();
+        let output = command("/bin/env")
+            .env_inherit(["PAT?", "CARGO_PKG_*"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_contains!(stdout, &format!("PATH={path}\n"));
+        assert_contains!(stdout, "CARGO_PKG_NAME=hakoniwa\n");
+        assert_not_contains!(stdout, "CARGO_MANIFEST_DIR=");
+    }
+
+    #[test]
+    fn test_env_inherit_default() {
+        let envs = command("/bin/env").env_inherit_default().get_envs();
+        assert_eq!(envs.get("PATH"), std::env::var("PATH").ok().as_ref());
+        assert!(
+            envs.keys()
+                .all(|k| k == "PATH" || k == "TERM" || k == "LANG" || k.starts_with("LC_"))
+        );
+    }
+
+    #[test]
+    fn test_env_remove() {
+        let output = command("/bin/env")
+            .env_inherit(["PATH"])
+            .env("MYENV", "1")
+            .env_remove("PATH")
+            .env_remove("MYENV")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    }
+
+    #[test]
+    fn test_env_clear() {
+        let output = command("/bin/env")
+            .env_inherit(["PATH"])
+            .env("MYENV", "1")
+            .env_clear()
+            .env("MYENV2", "2")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "MYENV2=2\n");
+    }
+
+    #[test]
+    fn test_env_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        fs::write(
+            &path,
+            r#"# comment
+MYENV1=1
+export MYENV2="a \"b\" c"
+MYENV3='$HOME'
+MYENV4=4 # comment
+"#,
+        )
+        .unwrap();
+
+        let mut command = command("/bin/env");
+        let envs = command.env_file(&path).unwrap().get_envs();
+        assert_eq!(envs.get("MYENV1").unwrap(), "1");
+        assert_eq!(envs.get("MYENV2").unwrap(), "a \"b\" c");
+        assert_eq!(envs.get("MYENV3").unwrap(), "$HOME");
+        assert_eq!(envs.get("MYENV4").unwrap(), "4");
+    }
+
+    #[test]
+    fn test_env_file_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".env");
+        fs::write(&path, "MY ENV=1\n").unwrap();
+        assert!(command("/bin/env").env_file(&path).is_err());
+    }
+
     #[test]
     fn test_current_dir() {
         let output = command("/bin/pwd").current_dir("/bin").output().unwrap();
@@ -112,6 +195,52 @@ mod command_test {
         assert_eq!(String::from_utf8_lossy(&output.stdout), "11\n");
     }
 
+    #[test]
+    fn test_spawn_stdin_null() {
+        let output = command("/bin/wc")
+            .arg("-c")
+            .stdin(Stdio::null())
+            .wait_timeout(1)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "0\n");
+    }
+
+    #[test]
+    fn test_spawn_stdin_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stdin");
+        fs::write(&path, "stdin file").unwrap();
+
+        let output = command("/bin/wc")
+            .arg("-c")
+            .stdin(File::open(&path).unwrap().into())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "10\n");
+    }
+
+    #[test]
+    fn test_spawn_stdin_bytes() {
+        let output = command("/bin/wc")
+            .arg("-c")
+            .stdin_bytes(vec![b'0'; 1024 * 1024])
+            .wait_timeout(1)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "1048576\n");
+
+        let mut command = command("/bin/cat");
+        let command = command.stdin_bytes(b"stdin bytes".to_vec());
+        let output = command.output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "stdin bytes");
+        let output = command.output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "stdin bytes");
+    }
+
     #[test]
     fn test_spawn_stdout_inherit() {
         let mut child = command("bin/echo").arg("stdout inherit").spawn().unwrap();
@@ -133,6 +262,128 @@ mod command_test {
         assert_eq!(String::from_utf8_lossy(&output.stdout), "stdout piped\n");
     }
 
+    #[test]
+    fn test_spawn_stdout_null() {
+        let output = command("/bin/echo")
+            .arg("stdout null")
+            .stdout(Stdio::null())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+    }
+
+    #[test]
+    fn test_spawn_stdout_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stdout");
+
+        let mut command = command("/bin/echo");
+        let command = command
+            .arg("stdout file")
+            .stdout(File::create(&path).unwrap().into());
+        let output = command.output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+        let status = command.status().unwrap();
+        assert!(status.success());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "stdout file\nstdout file\n"
+        );
+    }
+
+    #[test]
+    fn test_spawn_stderr_fd() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stderr");
+        let fd = OwnedFd::from(File::create(&path).unwrap());
+
+        let output = command("/bin/grep").stderr(fd.into()).output().unwrap();
+        assert!(!output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+        assert_contains!(fs::read_to_string(&path).unwrap(), "Usage: ");
+    }
+
+    #[test]
+    fn test_spawn_stdout_stderr_same_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output");
+        let stdio = Stdio::from(File::create(&path).unwrap());
+
+        let status = command("/bin/sh")
+            .args(["-c", "echo stdout; echo stderr >&2"])
+            .stdout(stdio.clone())
+            .stderr(stdio)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "stdout\nstderr\n");
+    }
+
+    #[test]
+    fn test_pty() {
+        let mut child = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .devfsmount("/dev")
+            .command("/bin/sh")
+            .args(["-c", "tty; test -t 0 && test -t 2 && echo isatty"])
+            .pty(true)
+            .spawn()
+            .unwrap();
+        assert!(child.pty.is_some());
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "/dev/pts/0\r\nisatty\r\n"
+        );
+    }
+
+    #[test]
+    fn test_pty_controlling_terminal() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .devfsmount("/dev")
+            .command("/bin/sh")
+            .args(["-c", "echo controlling > /dev/tty"])
+            .pty(true)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "controlling\r\n");
+    }
+
+    #[test]
+    fn test_pty_write() {
+        let mut child = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .devfsmount("/dev")
+            .command("/bin/head")
+            .args(["-n1"])
+            .pty(true)
+            .spawn()
+            .unwrap();
+        let mut pty = child.pty.take().unwrap();
+        pty.write_all(b"pty write\n").unwrap();
+        let mut buf = vec![];
+        _ = pty.read_to_end(&mut buf);
+        let status = child.wait().unwrap();
+        assert!(status.success());
+        assert_eq!(String::from_utf8_lossy(&buf), "pty write\r\npty write\r\n");
+    }
+
+    #[test]
+    fn test_pty_devfs_missing() {
+        let output = command("/bin/true").pty(true).output().unwrap();
+        assert!(!output.status.success());
+        assert_eq!(output.status.code, 125);
+        assert_contains!(output.status.reason, "posix_openpt");
+    }
+
     #[test]
     fn test_status_exit_code_zero() {
         let status = command("/bin/true").status().unwrap();
@@ -1,13 +1,18 @@
 #[cfg(test)]
 mod container_test {
     use assertables::*;
-    use nix::unistd::{Uid, User};
+    use nix::unistd::{Gid, Uid, User};
     use regex::Regex;
     use std::env;
     use std::fs::{self, File};
+    use std::io::{BufRead, BufReader};
+    use std::os::fd::AsFd;
+    use std::os::unix::fs::MetadataExt;
     use std::path::PathBuf;
 
-    use hakoniwa::{Container, Namespace, Pasta, Rlimit, Runctl};
+    use hakoniwa::{
+        Capability, Cgroup, Child, Container, MountOptions, Namespace, Pasta, Rlimit, Runctl, Stdio,
+    };
 
     fn current_dir() -> PathBuf {
         PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR")))
@@ -34,6 +39,39 @@ mod container_test {
         ))
     }
 
+    fn cgroup_controllers() -> Option<Vec<String>> {
+        let mountinfo = fs::read_to_string("/proc/self/mountinfo").unwrap();
+        let mountpoint = mountinfo.lines().find_map(|line| {
+            let fields = line.split(" ").collect::<Vec<_>>();
+            let sep = fields.iter().position(|f| *f == "-")?;
+            (fields[sep + 1] == "cgroup2").then(|| fields[4].to_string())
+        })?;
+        let cgroup = fs::read_to_string("/proc/self/cgroup").unwrap();
+        let cgroup = cgroup.lines().find_map(|line| line.strip_prefix("0::"))?;
+        let dir = PathBuf::from(mountpoint).join(cgroup.trim_start_matches("/"));
+        if nix::unistd::access(&dir, nix::unistd::AccessFlags::W_OK).is_err() {
+            return None;
+        }
+        let controllers = fs::read_to_string(dir.join("cgroup.controllers")).ok()?;
+        Some(
+            controllers
+                .split_whitespace()
+                .map(|c| c.to_string())
+                .collect(),
+        )
+    }
+
+    fn locked_mountpoint() -> Option<String> {
+        let mountinfo = fs::read_to_string("/proc/self/mountinfo").unwrap();
+        mountinfo.lines().find_map(|line| {
+            let fields = line.split(" ").collect::<Vec<_>>();
+            let locked = fields[5]
+                .split(",")
+                .any(|o| ["nosuid", "nodev", "noexec"].contains(&o));
+            locked.then(|| fields[4].to_string())
+        })
+    }
+
     fn userns_auto_uidmaps() -> Vec<(u32, u32, u32)> {
         let user = User::from_uid(Uid::current()).unwrap().unwrap();
         let username = user.name;
@@ -125,6 +163,106 @@ mod container_test {
         assert!(output.status.success());
     }
 
+    fn spawn_readlink_ns(container: &mut Container, ns: &str) -> (Child, String) {
+        let mut child = container
+            .command("/bin/sh")
+            .args(["-c", &format!("readlink /proc/self/ns/{ns}; sleep 2")])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        stdout.read_line(&mut line).unwrap();
+        (child, line)
+    }
+
+    #[test]
+    fn test_join_uts() {
+        let mut container = Container::new();
+        container
+            .rootfs("/")
+            .unwrap()
+            .unshare(Namespace::Uts)
+            .hostname("myhost");
+        let (mut child, _) = spawn_readlink_ns(&mut container, "uts");
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .join(Namespace::Uts, format!("/proc/{}/ns/uts", child.id()))
+            .command("/bin/hostname")
+            .output()
+            .unwrap();
+        child.kill().unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "myhost\n");
+    }
+
+    #[test]
+    fn test_join_network_pidfd() {
+        let mut container = Container::new();
+        container.rootfs("/").unwrap().unshare(Namespace::Network);
+        let (mut child, ns) = spawn_readlink_ns(&mut container, "net");
+
+        let pidfd = child.as_fd().try_clone_to_owned().unwrap();
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .join(Namespace::Network, pidfd)
+            .command("/bin/readlink")
+            .arg("/proc/self/ns/net")
+            .output()
+            .unwrap();
+        child.kill().unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), ns);
+        assert_ne!(
+            fs::read_link("/proc/self/ns/net")
+                .unwrap()
+                .to_string_lossy(),
+            ns.trim()
+        );
+    }
+
+    #[test]
+    fn test_join_user() {
+        let mut container = Container::new();
+        container.rootfs("/").unwrap().unshare(Namespace::Ipc);
+        let (mut child, ns) = spawn_readlink_ns(&mut container, "user");
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .join(Namespace::User, format!("/proc/{}/ns/user", child.id()))
+            .join(Namespace::Ipc, format!("/proc/{}/ns/ipc", child.id()))
+            .uidmap(1)
+            .command("/bin/sh")
+            .args(["-c", "readlink /proc/self/ns/user; id -u"])
+            .output()
+            .unwrap();
+        child.kill().unwrap();
+        assert!(output.status.success());
+        let uid = Uid::current().as_raw();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("{ns}{uid}\n")
+        );
+    }
+
+    #[test]
+    fn test_join_not_exists() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .join(Namespace::Network, "/proc/0/ns/net")
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(output.status.code, 125);
+        assert_contains!(output.status.reason, "/proc/0/ns/net");
+    }
+
     #[test]
     fn test_rootdir_customized() {
         let dir = tempfile::tempdir().unwrap();
@@ -151,6 +289,31 @@ mod container_test {
         assert_contains!(format!("{:?}", r.err()), "No such file or directory");
     }
 
+    #[test]
+    fn test_rootdir_overlay() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("myfile.txt"), "lower\n").unwrap();
+        let output = Container::new()
+            .rootdir_overlay(&dir)
+            .rootfs("/")
+            .unwrap()
+            .command("/bin/sh")
+            .args([
+                "-c",
+                "echo upper > /myfile.txt && touch /newfile.txt && cat /myfile.txt",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "upper\n");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("myfile.txt")).unwrap(),
+            "lower\n"
+        );
+        assert!(!dir.path().join("newfile.txt").exists());
+        assert!(!dir.path().join("bin").exists());
+    }
+
     #[test]
     fn test_rootfs_local() {
         let output = Container::new()
@@ -436,6 +599,244 @@ mod container_test {
         assert!(output.status.success());
     }
 
+    #[test]
+    fn test_bindmount_mount_options() {
+        let options = MountOptions::RDONLY
+            | MountOptions::NOSUID
+            | MountOptions::NOEXEC
+            | MountOptions::NOSYMFOLLOW;
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount(&current_dir().to_string_lossy(), "/myhome", options)
+            .unwrap()
+            .command("/bin/findmnt")
+            .args(["-n", "-o", "OPTIONS", "-T", "/myhome"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_starts_with!(stdout, "ro,nosuid,");
+        assert_contains!(stdout, ",noexec,");
+        assert_contains!(stdout, ",nosymfollow");
+    }
+
+    #[test]
+    fn test_bindmount_recursive() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount("/dev", "/mydev", MountOptions::REC)
+            .unwrap()
+            .command("/bin/ls")
+            .arg("/mydev/pts")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "ptmx\n");
+
+        // Submounts are locked in the new USER namespace.
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount("/dev", "/mydev", MountOptions::empty())
+            .unwrap()
+            .command("/bin/ls")
+            .arg("/mydev/pts")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(output.status.reason, "EINVAL");
+    }
+
+    #[test]
+    fn test_bindmount_recursive_rdonly() {
+        let options = MountOptions::REC | MountOptions::RDONLY;
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount("/dev", "/mydev", options)
+            .unwrap()
+            .command("/bin/findmnt")
+            .args(["-n", "-o", "OPTIONS", "/mydev/pts"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("ro,"));
+    }
+
+    #[test]
+    fn test_bindmount_propagation() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount_ro(&current_dir().to_string_lossy(), "/myhome")
+            .command("/bin/findmnt")
+            .args(["-n", "-o", "PROPAGATION", "/myhome"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "private\n");
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount(
+                &current_dir().to_string_lossy(),
+                "/myhome",
+                MountOptions::SHARED,
+            )
+            .unwrap()
+            .command("/bin/findmnt")
+            .args(["-n", "-o", "PROPAGATION", "/myhome"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "shared");
+    }
+
+    #[test]
+    fn test_bindmount_propagation_recursive() {
+        let options = MountOptions::REC | MountOptions::SHARED;
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount("/dev", "/mydev", options)
+            .unwrap()
+            .command("/bin/findmnt")
+            .args(["-n", "-o", "PROPAGATION", "/mydev/pts"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_not_contains!(String::from_utf8_lossy(&output.stdout), "shared");
+
+        let options = MountOptions::REC | MountOptions::RSHARED;
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount("/dev", "/mydev", options)
+            .unwrap()
+            .command("/bin/findmnt")
+            .args(["-n", "-o", "PROPAGATION", "/mydev/pts"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "shared");
+    }
+
+    #[test]
+    fn test_bindmount_propagation_invalid() {
+        let options = MountOptions::PRIVATE | MountOptions::RSLAVE;
+        let err = Container::new()
+            .bindmount("/dev", "/mydev", options)
+            .err()
+            .unwrap();
+        assert_contains!(err.to_string(), "more than one propagation type");
+    }
+
+    #[test]
+    fn test_bindmount_idmap() {
+        if !Uid::current().is_root() {
+            eprintln!(
+                "test container_test::test_bindmount_idmap ... skipped, CAP_SYS_ADMIN required"
+            );
+            return;
+        }
+
+        // Files owned by root on the host appear owned by root in the
+        // container, which is not mapped in the USER namespace.
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().to_string_lossy();
+        let options = MountOptions::REC | MountOptions::IDMAP;
+        let output = Container::new()
+            .uidmap(1000)
+            .gidmap(1000)
+            .rootfs("/")
+            .unwrap()
+            .bindmount(&dir, "/mydir", MountOptions::REC)
+            .unwrap()
+            .bindmount(&dir, "/myidmapdir", options)
+            .unwrap()
+            .command("/bin/stat")
+            .args(["-c", "%u:%g", "/mydir", "/myidmapdir"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "1000:1000\n65534:65534\n"
+        );
+    }
+
+    #[test]
+    fn test_bindmount_idmap_owner() {
+        if !Uid::current().is_root() {
+            eprintln!(
+                "test container_test::test_bindmount_idmap_owner ... skipped, CAP_SYS_ADMIN required"
+            );
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let options = MountOptions::REC | MountOptions::IDMAP;
+        let output = Container::new()
+            .uidmap(1000)
+            .gidmap(1000)
+            .idmapped_owner(1000, 1000)
+            .rootfs("/")
+            .unwrap()
+            .bindmount(&dir.path().to_string_lossy(), "/mydir", options)
+            .unwrap()
+            .command("/bin/sh")
+            .args([
+                "-c",
+                "touch /mydir/myfile.txt && stat -c %u:%g /mydir/myfile.txt",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "1000:1000\n");
+
+        let metadata = fs::metadata(dir.path().join("myfile.txt")).unwrap();
+        assert_eq!(metadata.uid(), Uid::current().as_raw());
+        assert_eq!(metadata.gid(), Gid::current().as_raw());
+    }
+
+    #[test]
+    fn test_bindmount_idmapped() {
+        if !Uid::current().is_root() {
+            eprintln!(
+                "test container_test::test_bindmount_idmapped ... skipped, CAP_SYS_ADMIN required"
+            );
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path().to_string_lossy();
+        let output = Container::new()
+            .uidmap(1000)
+            .gidmap(1000)
+            .idmapped_owner(1000, 1000)
+            .rootfs("/")
+            .unwrap()
+            .bindmount_ro_idmapped(&dir, "/myrodir")
+            .bindmount_rw_idmapped(&dir, "/myrwdir")
+            .command("/bin/sh")
+            .args([
+                "-c",
+                "findmnt -n -o OPTIONS /myrodir && findmnt -n -o OPTIONS /myrwdir",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let options = stdout.lines().collect::<Vec<_>>();
+        assert_starts_with!(options[0], "ro,nosuid");
+        assert_contains!(options[0], "idmapped");
+        assert_starts_with!(options[1], "rw,nosuid");
+        assert_contains!(options[1], "idmapped");
+    }
+
     #[test]
     fn test_devfsmount_mount_options() {
         let output = Container::new()
@@ -572,47 +973,172 @@ mod container_test {
     }
 
     #[test]
-    fn test_file() {
+    fn test_overlaymount_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let (lowerdir, upperdir, workdir) = (
+            dir.path().join("lower"),
+            dir.path().join("upper"),
+            dir.path().join("work"),
+        );
+        for dir in [&lowerdir, &upperdir, &workdir] {
+            fs::create_dir(dir).unwrap();
+        }
+        fs::write(lowerdir.join("myfile.txt"), "lower\n").unwrap();
+
         let output = Container::new()
             .rootfs("/")
             .unwrap()
-            .file("/myfile.txt", "abc")
-            .command("/bin/cat")
-            .arg("/myfile.txt")
+            .overlaymount(
+                &[
+                    &lowerdir.to_string_lossy(),
+                    &current_dir().to_string_lossy(),
+                ],
+                &upperdir.to_string_lossy(),
+                &workdir.to_string_lossy(),
+                "/mydir",
+            )
+            .unwrap()
+            .command("/bin/sh")
+            .args(["-c", "echo upper > /mydir/myfile.txt && ls /mydir"])
             .output()
             .unwrap();
         assert!(output.status.success());
-        assert_contains!(String::from_utf8_lossy(&output.stdout), "abc");
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "myfile.txt\n");
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "Cargo.toml\n");
+        assert_eq!(
+            fs::read_to_string(lowerdir.join("myfile.txt")).unwrap(),
+            "lower\n"
+        );
+        assert_eq!(
+            fs::read_to_string(upperdir.join("myfile.txt")).unwrap(),
+            "upper\n"
+        );
     }
 
     #[test]
-    fn test_dir() {
+    fn test_overlaymount_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let (upperdir, workdir) = (dir.path().join("upper"), dir.path().join("work"));
         let output = Container::new()
             .rootfs("/")
             .unwrap()
-            .dir("/tmp", 0o700)
-            .command("/bin/stat")
-            .args(["--printf", "%A", "/tmp"])
+            .overlaymount(
+                &["/dir/not/exists"],
+                &upperdir.to_string_lossy(),
+                &workdir.to_string_lossy(),
+                "/mydir",
+            )
+            .unwrap()
+            .command("/bin/true")
             .output()
             .unwrap();
-        assert!(output.status.success());
-        assert_contains!(String::from_utf8_lossy(&output.stdout), "drwx------");
-    }
+        assert!(!output.status.success());
+        assert_contains!(output.status.reason, "mount(");
 
-    #[test]
-    fn test_dir_chmod() {
-        let source = current_dir().join("Cargo.toml");
         let output = Container::new()
+            .runctl(Runctl::MountFallback)
             .rootfs("/")
             .unwrap()
-            .bindmount_ro(&source.to_string_lossy(), "/tmp/Cargo.toml")
-            .command("/bin/stat")
-            .args(["--printf", "%A", "/tmp"])
+            .overlaymount(
+                &["/dir/not/exists"],
+                &upperdir.to_string_lossy(),
+                &workdir.to_string_lossy(),
+                "/mydir",
+            )
+            .unwrap()
+            .command("/bin/true")
             .output()
             .unwrap();
-        assert!(output.status.success());
-        assert_contains!(String::from_utf8_lossy(&output.stdout), "drwxr-xr-x");
-
+        assert!(!output.status.success());
+        assert_contains!(output.status.reason, "fuse_overlayfs(");
+    }
+
+    #[test]
+    fn test_overlaymount_runc_error() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .overlaymount(&["lower"], "upper", "work", "/mydir")
+            .unwrap()
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(
+            output.status.reason,
+            "mount source path must be absolute: lower"
+        );
+    }
+
+    #[test]
+    fn test_overlaymount_invalid() {
+        let err = Container::new()
+            .overlaymount(&[], "/upper", "/work", "/mydir")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "invalid overlay mount: at least one lowerdir is required"
+        );
+
+        let err = Container::new()
+            .overlaymount(&["/lower,lowerdir=/"], "/upper", "/work", "/mydir")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            r#"invalid overlay mount: unsupported path "/lower,lowerdir=/""#
+        );
+
+        let err = Container::new()
+            .overlaymount(&["/lower"], "/upper:/work", "/work", "/mydir")
+            .err()
+            .unwrap();
+        assert_contains!(err.to_string(), "unsupported path");
+    }
+
+    #[test]
+    fn test_file() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .file("/myfile.txt", "abc")
+            .command("/bin/cat")
+            .arg("/myfile.txt")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "abc");
+    }
+
+    #[test]
+    fn test_dir() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .dir("/tmp", 0o700)
+            .command("/bin/stat")
+            .args(["--printf", "%A", "/tmp"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "drwx------");
+    }
+
+    #[test]
+    fn test_dir_chmod() {
+        let source = current_dir().join("Cargo.toml");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount_ro(&source.to_string_lossy(), "/tmp/Cargo.toml")
+            .command("/bin/stat")
+            .args(["--printf", "%A", "/tmp"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "drwxr-xr-x");
+
         let output = Container::new()
             .rootfs("/")
             .unwrap()
@@ -824,6 +1350,91 @@ mod container_test {
         assert_contains!(output.status.reason, "pasta: unrecognized option");
     }
 
+    #[test]
+    fn test_cap_drop() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .cap_drop(Capability::CAP_NET_RAW)
+            .command("/bin/grep")
+            .args(["CapBnd", "/proc/self/status"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let bnd = u64::from_str_radix(stdout.trim_start_matches("CapBnd:").trim(), 16).unwrap();
+        assert_eq!(bnd & Capability::CAP_NET_RAW.bitmask(), 0);
+        assert_ne!(bnd & Capability::CAP_CHOWN.bitmask(), 0);
+    }
+
+    #[test]
+    fn test_cap_drop_all() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .cap_drop_all()
+            .command("/bin/grep")
+            .args(["-E", "Cap(Eff|Bnd)", "/proc/self/status"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "CapEff:\t0000000000000000\nCapBnd:\t0000000000000000\n"
+        );
+    }
+
+    #[test]
+    fn test_cap_add() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .uidmap(1000)
+            .gidmap(1000)
+            .cap_drop_all()
+            .cap_add(Capability::CAP_NET_BIND_SERVICE)
+            .command("/bin/grep")
+            .args(["-E", "Cap(Eff|Bnd|Amb)", "/proc/self/status"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "CapEff:\t0000000000000400\nCapBnd:\t0000000000000400\nCapAmb:\t0000000000000400\n"
+        );
+    }
+
+    #[test]
+    fn test_cap_status() {
+        let status = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .uidmap(1000)
+            .gidmap(1000)
+            .cap_drop_all()
+            .cap_add(Capability::CAP_NET_BIND_SERVICE)
+            .command("/bin/true")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let caps = status.capability_status.unwrap();
+        let expected = vec![Capability::CAP_NET_BIND_SERVICE];
+        assert_eq!(caps.effective, expected);
+        assert_eq!(caps.permitted, expected);
+        assert_eq!(caps.inheritable, expected);
+        assert_eq!(caps.bounding, expected);
+        assert_eq!(caps.ambient, expected);
+
+        let status = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .command("/bin/true")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(status.capability_status.is_none());
+    }
+
     #[test]
     fn test_setrlimit_fsize() {
         let output = Container::new()
@@ -845,6 +1456,68 @@ mod container_test {
         assert_contains!(String::from_utf8_lossy(&output.stderr), "File too large");
     }
 
+    #[test]
+    fn test_cgroup() {
+        if cgroup_controllers().is_none() {
+            eprintln!("test container_test::test_cgroup ... skipped, cgroup v2 not writable");
+            return;
+        }
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .cgroup(Cgroup::new())
+            .command("/bin/cat")
+            .arg("/proc/self/cgroup")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stdout), "/hakoniwa-");
+        assert!(output.status.cgroup_stats.is_some());
+    }
+
+    #[test]
+    fn test_cgroup_pids_max() {
+        let controllers = cgroup_controllers().unwrap_or_default();
+        if !controllers.contains(&"pids".to_string()) {
+            eprintln!("test container_test::test_cgroup_pids_max ... skipped, pids controller");
+            return;
+        }
+
+        let mut cgroup = Cgroup::new();
+        cgroup.pids_max(4);
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .cgroup(cgroup)
+            .command("/bin/sh")
+            .args(["-c", "for i in 1 2 3 4 5 6; do sleep 1 & done; wait"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stderr), "fork");
+        assert_eq!(output.status.cgroup_stats.unwrap().pids_peak, 4);
+    }
+
+    #[test]
+    fn test_cgroup_controller_unavailable() {
+        let controllers = cgroup_controllers().unwrap_or_default();
+        if controllers.contains(&"memory".to_string()) {
+            eprintln!("test container_test::test_cgroup_controller_unavailable ... skipped");
+            return;
+        }
+
+        let mut cgroup = Cgroup::new();
+        cgroup.memory_max(16 * 1024 * 1024);
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .cgroup(cgroup)
+            .command("/bin/true")
+            .output();
+        assert!(output.is_err());
+    }
+
     #[cfg(feature = "landlock")]
     #[test]
     fn test_landlock_fs_readable() {
@@ -944,6 +1617,41 @@ mod container_test {
         assert!(output.status.success());
     }
 
+    #[cfg(feature = "landlock")]
+    #[test]
+    fn test_landlock_fs_fine_grained() {
+        use hakoniwa::landlock::*;
+        use std::str::FromStr;
+
+        let mut ruleset = Ruleset::default();
+        ruleset.restrict(Resource::FS, CompatMode::Enforce);
+        ruleset.add_fs_rule("/bin", FsAccess::from_str("r-x").unwrap());
+        ruleset.add_fs_rule("/lib", FsAccess::from_str("r-x").unwrap());
+        ruleset.add_fs_rule("/tmp", FsAccess::from_str("r--,make_reg").unwrap());
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/tmp")
+            .landlock_ruleset(ruleset.clone())
+            .command("/bin/touch")
+            .arg("/tmp/myfile.txt")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/tmp")
+            .landlock_ruleset(ruleset.clone())
+            .command("/bin/mkdir")
+            .arg("/tmp/mydir")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stderr), "Permission denied");
+    }
+
     #[cfg(feature = "landlock")]
     #[test]
     fn test_landlock_fs_rwx_dangerous() {
@@ -1069,85 +1777,719 @@ mod container_test {
 
     #[cfg(feature = "landlock")]
     #[test]
-    fn test_landlock_empty() {
+    fn test_landlock_scope_abstract_unix_socket() {
         use hakoniwa::landlock::*;
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::{SocketAddr, UnixListener};
+
+        let name = format!("hakoniwa-{}", std::process::id());
+        let addr = SocketAddr::from_abstract_name(&name).unwrap();
+        let _listener = UnixListener::bind_addr(&addr).unwrap();
+        let script = format!(
+            "import socket; socket.socket(socket.AF_UNIX).connect('\\0{name}'); print('OK')"
+        );
 
-        let ruleset = Ruleset::default();
-        let output = Container::new()
-            .rootfs(customized_rootfs_path())
-            .unwrap()
-            .landlock_ruleset(ruleset.clone())
-            .command("/bin/true")
+        let output = Container::empty()
+            .command("/bin/python3")
+            .args(["-c", &script])
             .output()
             .unwrap();
         assert!(output.status.success());
-    }
-
-    #[cfg(feature = "seccomp")]
-    #[test]
-    fn test_seccomp_errno() {
-        use hakoniwa::seccomp::*;
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "OK\n");
 
-        // let filter = Filter::new(Action::Errno(libc::EPERM));   // hangs when using Musl libc.
-        let filter = Filter::new(Action::KillProcess);
-        let output = Container::new()
-            .rootfs("/")
-            .unwrap()
-            .seccomp_filter(filter)
-            .command("/bin/echo")
+        let mut ruleset = Ruleset::default();
+        ruleset.restrict(Resource::SCOPE_ABSTRACT_UNIX_SOCKET, CompatMode::Enforce);
+        let output = Container::empty()
+            .landlock_ruleset(ruleset)
+            .command("/bin/python3")
+            .args(["-c", &script])
             .output()
             .unwrap();
         assert!(!output.status.success());
-        assert_eq!(output.status.code, 128 + 31);
-        assert_eq!(
-            output.status.reason,
-            "process(/bin/echo) received signal SIGSYS"
+        assert_contains!(
+            String::from_utf8_lossy(&output.stderr),
+            "Operation not permitted"
         );
-        assert_eq!(output.status.exit_code, None);
     }
 
-    #[cfg(feature = "seccomp")]
+    #[cfg(feature = "landlock")]
     #[test]
-    fn test_seccomp_errno_allowlist() {
-        use hakoniwa::seccomp::*;
+    fn test_landlock_scope_signal() {
+        use hakoniwa::landlock::*;
 
-        let mut filter = Filter::new(Action::Errno(libc::EPERM));
-        filter.add_rule(Action::Allow, "access");
-        filter.add_rule(Action::Allow, "arch_prctl");
-        filter.add_rule(Action::Allow, "brk");
-        filter.add_rule(Action::Allow, "close");
-        filter.add_rule(Action::Allow, "execve");
-        filter.add_rule(Action::Allow, "exit_group");
-        filter.add_rule(Action::Allow, "fstat");
-        filter.add_rule(Action::Allow, "getrandom");
-        filter.add_rule(Action::Allow, "mmap");
-        filter.add_rule(Action::Allow, "mprotect");
-        filter.add_rule(Action::Allow, "munmap");
-        filter.add_rule(Action::Allow, "newfstatat");
-        filter.add_rule(Action::Allow, "openat");
-        filter.add_rule(Action::Allow, "pread64");
-        filter.add_rule(Action::Allow, "prlimit64");
-        filter.add_rule(Action::Allow, "read");
-        filter.add_rule(Action::Allow, "rseq");
-        filter.add_rule(Action::Allow, "set_robust_list");
-        filter.add_rule(Action::Allow, "set_tid_address");
-        filter.add_rule(Action::Allow, "stat");
-        filter.add_rule(Action::Allow, "write");
-        let output = Container::new()
-            .rootfs("/")
-            .unwrap()
-            .seccomp_filter(filter)
-            .command("/bin/echo")
+        // Kill the sleep even if an assertion below fails.
+        struct KillOnDrop(std::process::Child);
+        impl Drop for KillOnDrop {
+            fn drop(&mut self) {
+                _ = self.0.kill();
+                _ = self.0.wait();
+            }
+        }
+
+        let sleep = std::process::Command::new("/bin/sleep")
+            .arg("10")
+            .spawn()
+            .unwrap();
+        let sleep = KillOnDrop(sleep);
+        let pid = sleep.0.id().to_string();
+
+        let output = Container::empty()
+            .command("/bin/kill")
+            .args(["-0", &pid])
             .output()
             .unwrap();
         assert!(output.status.success());
-    }
 
-    #[test]
-    fn test_runctl_rootfs_rw() {
-        let output = Container::new()
-            .runctl(Runctl::RootdirRW)
-            .rootfs("/")
+        let mut ruleset = Ruleset::default();
+        ruleset.restrict(Resource::SCOPE_SIGNAL, CompatMode::Enforce);
+        let output = Container::empty()
+            .landlock_ruleset(ruleset)
+            .command("/bin/kill")
+            .args(["-0", &pid])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(
+            String::from_utf8_lossy(&output.stderr),
+            "Operation not permitted"
+        );
+    }
+
+    #[cfg(feature = "landlock")]
+    fn landlock_abi_version() -> i32 {
+        use hakoniwa::landlock::*;
+
+        let mut ruleset = Ruleset::default();
+        ruleset.restrict(Resource::FS, CompatMode::Relax);
+        let status = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .landlock_ruleset(ruleset)
+            .command("/bin/true")
+            .status()
+            .unwrap();
+        status.landlock_status.map(|e| e.abi).unwrap_or_default()
+    }
+
+    #[cfg(feature = "landlock")]
+    #[test]
+    fn test_landlock_status() {
+        use hakoniwa::landlock::*;
+        use hakoniwa::{LandlockRulesetStatus, Runctl};
+        use std::str::FromStr;
+
+        if landlock_abi_version() < 6 {
+            eprintln!("test container_test::test_landlock_status ... skipped, landlock ABI < 6");
+            return;
+        }
+
+        let mut ruleset = Ruleset::default();
+        ruleset.restrict(Resource::FS, CompatMode::Relax);
+        ruleset.restrict(Resource::SCOPE_SIGNAL, CompatMode::Relax);
+        ruleset.add_fs_rule("/bin", FsAccess::from_str("r-x").unwrap());
+        ruleset.add_fs_rule("/lib", FsAccess::from_str("r-x").unwrap());
+        let status = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .landlock_ruleset(ruleset.clone())
+            .runctl(Runctl::LandlockRequire(
+                LandlockRulesetStatus::FullyEnforced,
+            ))
+            .command("/bin/echo")
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let landlock = status.landlock_status.unwrap();
+        assert_eq!(landlock.ruleset, LandlockRulesetStatus::FullyEnforced);
+        assert!(landlock.abi >= 6);
+        assert_eq!(
+            landlock.handled,
+            [
+                (Resource::FS, Some(FsAccess::R | FsAccess::W | FsAccess::X)),
+                (Resource::SCOPE_SIGNAL, None),
+            ]
+        );
+
+        let status = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .command("/bin/echo")
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(status.landlock_status.is_none());
+    }
+
+    #[cfg(feature = "landlock")]
+    #[test]
+    fn test_landlock_require() {
+        use hakoniwa::landlock::*;
+        use hakoniwa::{LandlockRulesetStatus, Runctl};
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .runctl(Runctl::LandlockRequire(
+                LandlockRulesetStatus::PartiallyEnforced,
+            ))
+            .command("/bin/echo")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(output.status.code, 125);
+        assert_contains!(
+            String::from_utf8_lossy(&output.stderr),
+            "hakoniwa: landlock ruleset is not enforced, but partially enforced is required"
+        );
+
+        // Scoping restrictions are only supported since ABI 6, so they are
+        // dropped in CompatMode::Relax on an older kernel.
+        if landlock_abi_version() >= 6 {
+            eprintln!("test container_test::test_landlock_require ... skipped, landlock ABI >= 6");
+            return;
+        }
+
+        let mut ruleset = Ruleset::default();
+        ruleset.restrict(Resource::FS, CompatMode::Relax);
+        ruleset.restrict(Resource::SCOPE_SIGNAL, CompatMode::Relax);
+        ruleset.add_fs_rule("/", FsAccess::R | FsAccess::X);
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .landlock_ruleset(ruleset)
+            .runctl(Runctl::LandlockRequire(
+                LandlockRulesetStatus::FullyEnforced,
+            ))
+            .command("/bin/echo")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(
+            String::from_utf8_lossy(&output.stderr),
+            "hakoniwa: landlock ruleset is partially enforced, but fully enforced is required"
+        );
+        let landlock = output.status.landlock_status.unwrap();
+        assert_eq!(landlock.ruleset, LandlockRulesetStatus::PartiallyEnforced);
+    }
+
+    #[cfg(feature = "landlock")]
+    #[test]
+    fn test_landlock_from_mounts() {
+        use hakoniwa::Runctl;
+        use hakoniwa::landlock::*;
+
+        let mut container = Container::new();
+        container
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/tmp")
+            .runctl(Runctl::LandlockFromMounts);
+        let output = container
+            .command("/bin/sh")
+            .args(["-c", "echo OK > /tmp/myfile.txt && cat /tmp/myfile.txt"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "OK\n");
+
+        let output = container
+            .command("/bin/sh")
+            .args(["-c", "cp /bin/echo /tmp/echo && /tmp/echo"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stderr), "Permission denied");
+
+        let output = container.command("/bin/ls").arg("/").output().unwrap();
+        assert!(!output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stderr), "Permission denied");
+
+        let mut ruleset = Ruleset::default();
+        ruleset.add_fs_rule("/tmp", FsAccess::X);
+        container.landlock_ruleset(ruleset);
+        let output = container
+            .command("/bin/sh")
+            .args(["-c", "cp /bin/echo /tmp/echo && /tmp/echo OK"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "OK\n");
+    }
+
+    #[cfg(feature = "landlock")]
+    #[test]
+    fn test_landlock_from_mounts_devfs() {
+        use hakoniwa::Runctl;
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .devfsmount("/dev")
+            .runctl(Runctl::LandlockFromMounts)
+            .command("/bin/sh")
+            .args([
+                "-c",
+                "echo OK > /dev/shm/myfile && cat /dev/shm/myfile > /dev/null",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .devfsmount("/dev")
+            .runctl(Runctl::LandlockFromMounts)
+            .command("/bin/mkdir")
+            .arg("/dev/mydir")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stderr), "Permission denied");
+    }
+
+    #[cfg(not(feature = "landlock"))]
+    #[test]
+    fn test_landlock_from_mounts_feature_not_enabled() {
+        use hakoniwa::Runctl;
+
+        let err = Container::new()
+            .runctl(Runctl::LandlockFromMounts)
+            .command("/bin/true")
+            .status()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "LandlockFromMounts requires the `landlock` feature"
+        );
+    }
+
+    #[cfg(feature = "landlock")]
+    #[test]
+    fn test_landlock_empty() {
+        use hakoniwa::landlock::*;
+
+        let ruleset = Ruleset::default();
+        let output = Container::new()
+            .rootfs(customized_rootfs_path())
+            .unwrap()
+            .landlock_ruleset(ruleset.clone())
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_errno() {
+        use hakoniwa::seccomp::*;
+
+        // let filter = Filter::new(Action::Errno(libc::EPERM));   // hangs when using Musl libc.
+        let filter = Filter::new(Action::KillProcess);
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter)
+            .command("/bin/echo")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(output.status.code, 128 + 31);
+        assert_eq!(
+            output.status.reason,
+            "process(/bin/echo) received signal SIGSYS"
+        );
+        assert_eq!(output.status.exit_code, None);
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_blocked_syscall() {
+        use hakoniwa::seccomp::*;
+
+        for action in [Action::KillProcess, Action::KillThread, Action::Trap] {
+            let mut filter = Filter::new(Action::Allow);
+            filter.add_rule(action, "mkdir");
+            filter.add_rule(action, "mkdirat");
+            let output = Container::new()
+                .rootfs("/")
+                .unwrap()
+                .tmpfsmount("/mytmp")
+                .seccomp_filter(filter)
+                .runctl(Runctl::ReportBlockedSyscall)
+                .command("/bin/mkdir")
+                .arg("/mytmp/a")
+                .output()
+                .unwrap();
+            assert!(!output.status.success());
+            assert_eq!(output.status.code, 128 + 31);
+
+            let blocked = output.status.blocked_syscall.unwrap();
+            assert!(blocked.sysname.starts_with("mkdir"));
+            assert!(blocked.instruction_pointer != 0);
+            #[cfg(target_arch = "x86_64")]
+            assert_eq!(blocked.arch, "x86_64");
+        }
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_blocked_syscall_descendant() {
+        use hakoniwa::seccomp::*;
+
+        for action in [Action::KillProcess, Action::KillThread, Action::Trap] {
+            let mut filter = Filter::new(Action::Allow);
+            filter.add_rule(action, "mkdir");
+            filter.add_rule(action, "mkdirat");
+            let output = Container::new()
+                .rootfs("/")
+                .unwrap()
+                .tmpfsmount("/mytmp")
+                .seccomp_filter(filter)
+                .runctl(Runctl::ReportBlockedSyscall)
+                .command("/bin/sh")
+                .args(["-c", "/bin/mkdir /mytmp/a; exit 0"])
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+
+            let blocked = output.status.blocked_syscall.unwrap();
+            assert!(blocked.sysname.starts_with("mkdir"));
+        }
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_blocked_syscall_not_reported() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::KillProcess, "mkdir");
+        filter.add_rule(Action::KillProcess, "mkdirat");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .seccomp_filter(filter)
+            .command("/bin/mkdir")
+            .arg("/mytmp/a")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_eq!(output.status.code, 128 + 31);
+        assert!(output.status.blocked_syscall.is_none());
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_oci_caps() {
+        use hakoniwa::seccomp::*;
+
+        let filter = Filter::from_oci_json(
+            r#"{
+                "defaultAction": "SCMP_ACT_ALLOW",
+                "syscalls": [
+                    {
+                        "names": ["mkdir", "mkdirat"],
+                        "action": "SCMP_ACT_ERRNO",
+                        "errnoRet": 1,
+                        "excludes": { "caps": ["CAP_SYS_ADMIN"] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut container = Container::new();
+        container
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .seccomp_filter(filter);
+
+        let output = container
+            .command("/bin/mkdir")
+            .arg("/mytmp/a")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        container.cap_drop(Capability::CAP_SYS_ADMIN);
+        let output = container
+            .command("/bin/mkdir")
+            .arg("/mytmp/a")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(
+            String::from_utf8_lossy(&output.stderr),
+            "Operation not permitted"
+        );
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_blocked_syscall_none() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::Errno(libc::EPERM), "mkdir");
+        filter.add_rule(Action::Errno(libc::EPERM), "mkdirat");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .seccomp_filter(filter)
+            .runctl(Runctl::ReportBlockedSyscall)
+            .command("/bin/mkdir")
+            .arg("/mytmp/a")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(output.status.blocked_syscall.is_none());
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_group() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::KillProcess);
+        filter.add_group_rule(Action::Allow, SyscallGroup::SystemService);
+        filter.add_group_rule(Action::Errno(libc::EPERM), SyscallGroup::Privileged);
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .seccomp_filter(filter)
+            .command("/bin/sh")
+            .args(["-c", "touch /mytmp/a && chown $(id -u) /mytmp/a"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Operation not permitted"));
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_socket_family() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::KillProcess);
+        filter.add_group_rule(Action::Allow, SyscallGroup::SystemService);
+        filter.add_socket_family_rule(Action::Errno(libc::EAFNOSUPPORT), libc::AF_NETLINK);
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter)
+            .command("/bin/ip")
+            .arg("link")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Address family not supported"));
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_notifier_errno() {
+        use hakoniwa::seccomp::*;
+        use std::sync::{Arc, Mutex};
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::Notify, "mkdir");
+        filter.add_rule(Action::Notify, "mkdirat");
+        let notified = Arc::new(Mutex::new(vec![]));
+        let notified_clone = notified.clone();
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .seccomp_filter(filter)
+            .seccomp_notifier(move |n: &Notification| {
+                let addr = match n.sysname.as_str() {
+                    "mkdir" => n.args[0],
+                    _ => n.args[1],
+                };
+                let path = n.read_cstring(addr, 4096).unwrap();
+                notified_clone
+                    .lock()
+                    .unwrap()
+                    .push(path.to_string_lossy().to_string());
+                Response::Errno(libc::EACCES)
+            })
+            .command("/bin/mkdir")
+            .arg("/mytmp/mydir")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stderr), "Permission denied");
+        assert_eq!(*notified.lock().unwrap(), ["/mytmp/mydir"]);
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_notifier_return() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::Notify, "getpid");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter)
+            .seccomp_notifier(|_: &Notification| Response::Return(4242))
+            .command("/bin/sh")
+            .args(["-c", "echo $$"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "4242\n");
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_notifier_continue() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::Notify, "uname");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter)
+            .seccomp_notifier(|_: &Notification| Response::Continue)
+            .command("/bin/uname")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "Linux\n");
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_notifier_inject_fd() {
+        use hakoniwa::seccomp::*;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("injected");
+        fs::write(&path, "injected from the host\n").unwrap();
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::Notify, "openat");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter)
+            .seccomp_notifier(
+                move |n: &Notification| match n.read_cstring(n.args[1], 4096) {
+                    Ok(p) if p.as_bytes() == b"/myinjected" => {
+                        Response::InjectFd(File::open(&path).unwrap().into())
+                    }
+                    _ => Response::Continue,
+                },
+            )
+            .command("/bin/cat")
+            .arg("/myinjected")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "injected from the host\n"
+        );
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_notifier_pty() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::Notify, "uname");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .devfsmount("/dev")
+            .seccomp_filter(filter)
+            .seccomp_notifier(|_: &Notification| Response::Errno(libc::EPERM))
+            .command("/bin/uname")
+            .pty(true)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(
+            String::from_utf8_lossy(&output.stdout),
+            "Operation not permitted"
+        );
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_errno_allowlist() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::Errno(libc::EPERM));
+        filter.add_rule(Action::Allow, "access");
+        filter.add_rule(Action::Allow, "arch_prctl");
+        filter.add_rule(Action::Allow, "brk");
+        filter.add_rule(Action::Allow, "close");
+        filter.add_rule(Action::Allow, "execve");
+        filter.add_rule(Action::Allow, "exit_group");
+        filter.add_rule(Action::Allow, "fstat");
+        filter.add_rule(Action::Allow, "getrandom");
+        filter.add_rule(Action::Allow, "mmap");
+        filter.add_rule(Action::Allow, "mprotect");
+        filter.add_rule(Action::Allow, "munmap");
+        filter.add_rule(Action::Allow, "newfstatat");
+        filter.add_rule(Action::Allow, "openat");
+        filter.add_rule(Action::Allow, "pread64");
+        filter.add_rule(Action::Allow, "prlimit64");
+        filter.add_rule(Action::Allow, "read");
+        filter.add_rule(Action::Allow, "rseq");
+        filter.add_rule(Action::Allow, "set_robust_list");
+        filter.add_rule(Action::Allow, "set_tid_address");
+        filter.add_rule(Action::Allow, "stat");
+        filter.add_rule(Action::Allow, "write");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter)
+            .command("/bin/echo")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_seccomp_cap_drop() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::KillProcess, "capset");
+        filter.add_rule(Action::KillProcess, "prctl");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter)
+            .cap_drop(Capability::CAP_NET_RAW)
+            .command("/bin/grep")
+            .args(["CapBnd", "/proc/self/status"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let bnd = u64::from_str_radix(stdout.trim_start_matches("CapBnd:").trim(), 16).unwrap();
+        assert_eq!(bnd & Capability::CAP_NET_RAW.bitmask(), 0);
+    }
+
+    #[test]
+    fn test_runctl_rootfs_rw() {
+        let output = Container::new()
+            .runctl(Runctl::RootdirRW)
+            .rootfs("/")
             .unwrap()
             .command("/bin/touch")
             .arg("/myfile.txt")
@@ -1181,6 +2523,38 @@ mod container_test {
         assert!(output.status.success());
     }
 
+    #[test]
+    fn test_runctl_mount_fallback_bindmount_ro() {
+        let Some(mountpoint) = locked_mountpoint() else {
+            eprintln!(
+                "test container_test::test_runctl_mount_fallback_bindmount_ro ... skipped, no locked mount flags"
+            );
+            return;
+        };
+
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .bindmount_ro(&mountpoint, "/mymnt")
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(output.status.reason, "EPERM");
+
+        let output = Container::new()
+            .runctl(Runctl::MountFallback)
+            .rootfs("/")
+            .unwrap()
+            .bindmount_ro(&mountpoint, "/mymnt")
+            .command("/bin/findmnt")
+            .args(["-T", "/mymnt"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stdout), " ro,nosuid");
+    }
+
     #[test]
     fn test_runctl_allow_new_privs() {
         let output = Container::new()
@@ -1204,4 +2578,331 @@ mod container_test {
         assert!(output.status.success());
         assert_eq!(output.status.proc_pid_status.unwrap().nonewprivs, 0);
     }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_runctl_seccomp_strict() {
+        use hakoniwa::seccomp::*;
+
+        let mut filter = Filter::new(Action::Allow);
+        filter.add_rule(Action::Errno(libc::EPERM), "mkdri");
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter.clone())
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let err = Container::new()
+            .runctl(Runctl::SeccompStrict)
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(filter.clone())
+            .command("/bin/true")
+            .output()
+            .unwrap_err();
+        assert_eq!(err.to_string(), "unknown syscall: mkdri");
+
+        // Set after the filter was compiled by an earlier spawn.
+        let mut container = Container::new();
+        container.rootfs("/").unwrap().seccomp_filter(filter);
+        let output = container.command("/bin/true").output().unwrap();
+        assert!(output.status.success());
+        container.runctl(Runctl::SeccompStrict);
+        let err = container.command("/bin/true").output().unwrap_err();
+        assert_eq!(err.to_string(), "unknown syscall: mkdri");
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_runctl_record_syscalls() {
+        use hakoniwa::seccomp::*;
+
+        let output = Container::new()
+            .runctl(Runctl::RecordSyscalls)
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .command("/bin/sh")
+            .args(["-c", "/bin/mkdir /mytmp/a && /bin/ls /mytmp"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a\n");
+
+        // mkdir is made by a descendant of the internal process.
+        let syscalls = output.status.recorded_syscalls.unwrap();
+        assert!(syscalls.iter().any(|s| s.sysname.starts_with("mkdir")));
+        assert!(syscalls.iter().any(|s| s.sysname == "execve"));
+
+        // The recorded syscalls are enough to run it again.
+        let filter = Filter::from_recorded_syscalls(Action::KillProcess, &syscalls).unwrap();
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .seccomp_filter(filter)
+            .command("/bin/sh")
+            .args(["-c", "/bin/mkdir /mytmp/a && /bin/ls /mytmp"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a\n");
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_runctl_record_syscalls_started() {
+        use hakoniwa::seccomp::*;
+
+        // The syscalls made before the program is executed, e.g. resetting
+        // SIGPIPE, are not recorded.
+        let output = Container::new()
+            .runctl(Runctl::RecordSyscalls)
+            .rootfs("/")
+            .unwrap()
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let syscalls = output.status.recorded_syscalls.unwrap();
+        let sysnames = syscalls
+            .iter()
+            .map(|s| s.sysname.as_str())
+            .collect::<Vec<_>>();
+        assert!(sysnames.contains(&"execve"));
+        assert!(!sysnames.contains(&"rt_sigaction"));
+
+        // Capabilities are dropped before the filter is loaded, unless the
+        // no_new_privs bit is not set.
+        let output = Container::new()
+            .runctl(Runctl::RecordSyscalls)
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(Filter::new(Action::Allow))
+            .cap_drop(Capability::CAP_SYS_ADMIN)
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let syscalls = output.status.recorded_syscalls.unwrap();
+        let sysnames = syscalls
+            .iter()
+            .map(|s| s.sysname.as_str())
+            .collect::<Vec<_>>();
+        assert!(!sysnames.contains(&"capset"));
+        assert!(sysnames.contains(&"execve"));
+
+        // The syscalls made after the filter is loaded, e.g. dropping
+        // capabilities, are recorded since they are checked by it.
+        let output = Container::new()
+            .runctl(Runctl::RecordSyscalls)
+            .runctl(Runctl::AllowNewPrivs)
+            .rootfs("/")
+            .unwrap()
+            .seccomp_filter(Filter::new(Action::Allow))
+            .cap_drop(Capability::CAP_SYS_ADMIN)
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let syscalls = output.status.recorded_syscalls.unwrap();
+        let sysnames = syscalls
+            .iter()
+            .map(|s| s.sysname.as_str())
+            .collect::<Vec<_>>();
+        assert!(sysnames.contains(&"capset"));
+        assert!(sysnames.contains(&"prctl"));
+        assert!(sysnames.contains(&"execve"));
+        assert!(!sysnames.contains(&"rt_sigaction"));
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_runctl_record_syscalls_none() {
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .command("/bin/true")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(output.status.recorded_syscalls.is_none());
+    }
+
+    #[cfg(not(feature = "seccomp"))]
+    #[test]
+    fn test_runctl_record_fs_access_feature_not_enabled() {
+        let err = Container::new()
+            .runctl(Runctl::RecordFsAccess)
+            .command("/bin/true")
+            .status()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "RecordFsAccess requires the `seccomp` feature"
+        );
+    }
+
+    #[cfg(not(feature = "seccomp"))]
+    #[test]
+    fn test_runctl_trace_syscalls_feature_not_enabled() {
+        let err = Container::new()
+            .runctl(Runctl::RecordSyscalls)
+            .command("/bin/true")
+            .status()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "RecordSyscalls requires the `seccomp` feature"
+        );
+
+        let err = Container::new()
+            .runctl(Runctl::TraceSyscalls)
+            .command("/bin/true")
+            .status()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "TraceSyscalls requires the `seccomp` feature"
+        );
+    }
+
+    #[cfg(all(feature = "landlock", feature = "seccomp"))]
+    #[test]
+    fn test_runctl_record_fs_access() {
+        use hakoniwa::landlock::*;
+
+        let script = "cd /mytmp && mkdir a && echo 1 > a/b && cat a/b /etc/hostname";
+        let output = Container::new()
+            .runctl(Runctl::RecordFsAccess)
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .command("/bin/sh")
+            .args(["-c", script])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(output.status.recorded_syscalls.is_none());
+
+        let accesses = output.status.recorded_fs_accesses.unwrap();
+        let access = |path: &str| {
+            accesses
+                .iter()
+                .find(|e| e.path == path)
+                .map(|e| e.access.clone())
+                .unwrap_or_default()
+        };
+        assert_eq!(access("/mytmp"), "rw-"); // /mytmp/a is created by it
+        assert_eq!(access("/mytmp/a"), "");
+        assert_eq!(access("/etc"), "r--");
+
+        // The recorded accesses are enough to run it again.
+        let ruleset = Ruleset::from_recorded_fs_accesses(&accesses).unwrap();
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .landlock_ruleset(ruleset)
+            .command("/bin/sh")
+            .args(["-c", script])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        // And the others are denied.
+        let ruleset = Ruleset::from_recorded_fs_accesses(&accesses).unwrap();
+        let output = Container::new()
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .landlock_ruleset(ruleset)
+            .command("/bin/sh")
+            .args(["-c", "cat /etc/hostname && ls /usr"])
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert_contains!(String::from_utf8_lossy(&output.stderr), "Permission denied");
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_runctl_trace_syscalls() {
+        use hakoniwa::{TraceEvent, TraceSink};
+        use std::sync::{Arc, Mutex};
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let events_clone = events.clone();
+        let output = Container::new()
+            .runctl(Runctl::TraceSyscalls)
+            .trace_sink(TraceSink::handler(move |event: &TraceEvent| {
+                events_clone.lock().unwrap().push(event.clone());
+            }))
+            .rootfs("/")
+            .unwrap()
+            .tmpfsmount("/mytmp")
+            .command("/bin/sh")
+            .args(["-c", "/bin/mkdir /mytmp/a /mytmp/b/c; true"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        // The syscalls made before the program is executed are not traced.
+        let events = events.lock().unwrap();
+        assert_eq!(events[0].sysname, "execve");
+        assert_eq!(events[0].args[0], r#""/bin/sh""#);
+        assert_eq!(events[0].retval, Some(0));
+
+        // mkdir is made by a descendant of the internal process.
+        let mkdirs = events
+            .iter()
+            .filter(|e| e.sysname.starts_with("mkdir"))
+            .collect::<Vec<_>>();
+        assert_eq!(mkdirs.len(), 2);
+        assert_ne!(mkdirs[0].pid, events[0].pid);
+        assert_contains!(mkdirs[0].args.join(", "), r#""/mytmp/a", 0777"#);
+        assert_eq!(mkdirs[0].errno(), None);
+        assert_contains!(mkdirs[1].args.join(", "), r#""/mytmp/b/c", 0777"#);
+        assert_eq!(mkdirs[1].errno(), Some(libc::ENOENT));
+        assert_contains!(
+            mkdirs[1].to_string(),
+            "= -1 ENOENT (No such file or directory)"
+        );
+
+        // exit_group does not return.
+        let last = events.last().unwrap();
+        assert_eq!(last.sysname, "exit_group");
+        assert_eq!(last.retval, None);
+    }
+
+    #[cfg(feature = "seccomp")]
+    #[test]
+    fn test_runctl_trace_syscalls_file() {
+        use std::fs::{self, File};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trace.txt");
+        let output = Container::new()
+            .runctl(Runctl::TraceSyscalls)
+            .trace_sink(File::create(&path).unwrap())
+            .rootfs("/")
+            .unwrap()
+            .command("/bin/cat")
+            .arg("/etc/hostname")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        // The file is complete once the status is returned.
+        let trace = fs::read_to_string(&path).unwrap();
+        assert_contains!(
+            trace,
+            r#"execve("/bin/cat", ["/bin/cat", "/etc/hostname"], "#
+        );
+        assert_contains!(trace, r#"openat(AT_FDCWD, "/etc/hostname", O_RDONLY"#);
+        assert!(trace.ends_with("exit_group(0) = ?\n"));
+    }
 }
@@ -82,3 +82,45 @@ fn test_from_str_x() {
     let mode = FsAccess::from_str("wx").unwrap();
     assert_eq!(mode, FsAccess::W | FsAccess::X);
 }
+
+#[test]
+fn test_format_flags() {
+    let mode = FsAccess::READ_FILE;
+    assert_eq!(format!("{mode}"), "read_file");
+
+    let mode = FsAccess::READ_FILE | FsAccess::MAKE_REG;
+    assert_eq!(format!("{mode}"), "read_file,make_reg");
+
+    let mode = FsAccess::R | FsAccess::MAKE_REG | FsAccess::REMOVE_FILE;
+    assert_eq!(format!("{mode}"), "r--,remove_file,make_reg");
+
+    let mode = FsAccess::READ_FILE | FsAccess::READ_DIR | FsAccess::EXECUTE;
+    assert_eq!(format!("{mode}"), "r-x");
+
+    let mode = FsAccess::empty();
+    assert_eq!(format!("{mode}"), "---");
+}
+
+#[test]
+fn test_from_str_flags() {
+    let mode = FsAccess::from_str("read_file").unwrap();
+    assert_eq!(mode, FsAccess::READ_FILE);
+
+    let mode = FsAccess::from_str("read_file,make_reg").unwrap();
+    assert_eq!(mode, FsAccess::READ_FILE | FsAccess::MAKE_REG);
+
+    let mode = FsAccess::from_str("r--, remove_file, make_reg").unwrap();
+    assert_eq!(
+        mode,
+        FsAccess::R | FsAccess::REMOVE_FILE | FsAccess::MAKE_REG
+    );
+
+    let mode = FsAccess::from_str("read_file,read_dir,execute").unwrap();
+    assert_eq!(mode, FsAccess::R | FsAccess::X);
+
+    let mode = FsAccess::from_str("r-x,truncate").unwrap();
+    assert_eq!(format!("{mode}"), "r-x,truncate");
+
+    let err = FsAccess::from_str("read_file,rwz").unwrap_err();
+    assert_eq!(err.to_string(), "unknown access \"z\"");
+}
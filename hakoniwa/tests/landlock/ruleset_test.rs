@@ -0,0 +1,126 @@
+use hakoniwa::landlock::*;
+use hakoniwa::{Container, MountOptions, RecordedFsAccess, Runctl};
+
+fn recorded_fs_accesses(accesses: &[(&str, &str)]) -> Vec<RecordedFsAccess> {
+    accesses
+        .iter()
+        .map(|(path, access)| RecordedFsAccess {
+            path: path.to_string(),
+            access: access.to_string(),
+        })
+        .collect()
+}
+
+fn fs_rules(ruleset: &Ruleset) -> Vec<String> {
+    ruleset
+        .get_fs_rules()
+        .iter()
+        .map(|rule| rule.to_string())
+        .collect()
+}
+
+#[test]
+fn test_from_recorded_fs_accesses() {
+    let accesses = recorded_fs_accesses(&[
+        ("/etc", "r--"),
+        ("/tmp", "-w-"),
+        ("/tmp", "r--"),
+        ("/usr/bin", "r-x"),
+    ]);
+    let ruleset = Ruleset::from_recorded_fs_accesses(&accesses).unwrap();
+    assert_eq!(fs_rules(&ruleset), ["r-- /etc", "rw- /tmp", "r-x /usr/bin"]);
+}
+
+#[test]
+fn test_from_recorded_fs_accesses_merge_siblings() {
+    let accesses = recorded_fs_accesses(&[
+        ("/usr/share/a", "r--"),
+        ("/usr/share/b", "r--"),
+        ("/usr/share/c", "r--"),
+        ("/usr/share/d", "r--"),
+        ("/usr/share/e", "rw-"),
+        ("/etc/a", "r--"),
+        ("/etc/b", "r--"),
+        ("/etc/c", "r--"),
+        ("/a", "r--"),
+        ("/b", "r--"),
+        ("/c", "r--"),
+        ("/d", "r--"),
+    ]);
+    let ruleset = Ruleset::from_recorded_fs_accesses(&accesses).unwrap();
+    assert_eq!(
+        fs_rules(&ruleset),
+        [
+            "r-- /a",
+            "r-- /b",
+            "r-- /c",
+            "r-- /d",
+            "r-- /etc/a",
+            "r-- /etc/b",
+            "r-- /etc/c",
+            "r-- /usr/share",
+            "rw- /usr/share/e",
+        ]
+    );
+}
+
+#[test]
+fn test_from_recorded_fs_accesses_drop_covered() {
+    let accesses = recorded_fs_accesses(&[
+        ("/usr", "r-x"),
+        ("/usr/bin", "r-x"),
+        ("/usr/lib", "r--"),
+        ("/usr/share", "rw-"),
+    ]);
+    let ruleset = Ruleset::from_recorded_fs_accesses(&accesses).unwrap();
+    assert_eq!(fs_rules(&ruleset), ["r-x /usr", "rw- /usr/share"]);
+}
+
+#[test]
+fn test_from_recorded_fs_accesses_unknown_access() {
+    let accesses = recorded_fs_accesses(&[("/usr", "rwz")]);
+    let err = Ruleset::from_recorded_fs_accesses(&accesses).unwrap_err();
+    assert_eq!(err.to_string(), "unknown access \"z\"");
+}
+
+#[test]
+fn test_from_container() {
+    let mut container = Container::new();
+    container
+        .bindmount_ro("/usr", "/usr")
+        .bindmount_rw("/var", "/var")
+        .bindmount("/opt", "/opt", MountOptions::RDONLY | MountOptions::NOEXEC)
+        .unwrap()
+        .devfsmount("/dev")
+        .tmpfsmount("/tmp")
+        .file("/etc/hosts", "")
+        .dir("/home", 0o700)
+        .symlink("usr/bin", "/bin");
+    let ruleset = Ruleset::from_container(&container);
+    assert_eq!(
+        fs_rules(&ruleset),
+        [
+            "r--,write_file,ioctl_dev /dev",
+            "rw- /dev/shm",
+            "r-- /etc/hosts",
+            "r-- /home",
+            "r-- /opt",
+            "rw- /proc",
+            "rw- /tmp",
+            "r-x /usr",
+            "rw- /var",
+        ]
+    );
+}
+
+#[test]
+fn test_from_container_rootdir() {
+    let mut container = Container::new();
+    container.rootdir("/");
+    let ruleset = Ruleset::from_container(&container);
+    assert_eq!(fs_rules(&ruleset), ["r-x /", "rw- /proc"]);
+
+    container.runctl(Runctl::RootdirRW);
+    let ruleset = Ruleset::from_container(&container);
+    assert_eq!(fs_rules(&ruleset), ["rwx /", "rw- /proc"]);
+}
@@ -1,4 +1,5 @@
 #[cfg(feature = "landlock")]
 mod landlock {
     mod fs_access_test;
+    mod ruleset_test;
 }
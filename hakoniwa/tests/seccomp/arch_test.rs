@@ -0,0 +1,28 @@
+use hakoniwa::seccomp::*;
+
+#[test]
+fn test_from_str() {
+    assert_eq!("x86_64".parse::<Arch>().unwrap(), Arch::X8664);
+    assert_eq!("amd64".parse::<Arch>().unwrap(), Arch::X8664);
+    assert_eq!("ARM64".parse::<Arch>().unwrap(), Arch::Aarch64);
+    assert_eq!("loong64".parse::<Arch>().unwrap(), Arch::Loongarch64);
+    assert_eq!("mips64n32".parse::<Arch>().unwrap(), Arch::Mips64n32);
+
+    let err = "native".parse::<Arch>().unwrap_err();
+    assert_eq!(err.to_string(), "unsupported architectures native");
+}
+
+#[test]
+fn test_display() {
+    for arch in [
+        Arch::X86,
+        Arch::X8664,
+        Arch::Aarch64,
+        Arch::Ppc64le,
+        Arch::S390x,
+        Arch::Riscv64,
+    ] {
+        assert_eq!(arch.to_string().parse::<Arch>().unwrap(), arch);
+    }
+    assert_eq!(Arch::Native.to_string(), "native");
+}
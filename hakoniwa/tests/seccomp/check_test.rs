@@ -0,0 +1,89 @@
+use hakoniwa::{scmp_argcmp, seccomp::*};
+
+#[test]
+fn test_check_ok() {
+    let mut filter = Filter::new(Action::Errno(libc::EPERM));
+    filter.add_rule(Action::Allow, "read");
+    filter.add_rule(Action::Allow, "write");
+    let check = filter.check();
+    assert!(check.is_ok());
+    assert_eq!(check.to_string(), "");
+}
+
+#[test]
+fn test_check_unknown_syscalls() {
+    let mut filter = Filter::new(Action::Errno(libc::EPERM));
+    filter.add_rule(Action::Allow, "read");
+    filter.add_rule(Action::Allow, "raed");
+    let check = filter.check();
+    assert!(!check.is_ok());
+    assert_eq!(check.unknown_syscalls.len(), 1);
+    assert_eq!(check.unknown_syscalls[0].1, "raed");
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_check_unknown_syscalls_arch() {
+    let mut filter = Filter::new(Action::Errno(libc::EPERM));
+    filter.add_arch(Arch::X86);
+    filter.add_rule(Action::Allow, "arch_prctl");
+    filter.add_rule(Action::Allow, "chown32");
+    filter.add_rule(Action::Allow, "raed");
+    let check = filter.check();
+    assert!(!check.is_ok());
+    assert_eq!(
+        check.to_string(),
+        [
+            "unknown syscall: chown32 (x86_64)",
+            "unknown syscall: raed (x86_64)",
+            "unknown syscall: raed (x86)",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn test_check_redundant_rules() {
+    let mut filter = Filter::new(Action::Allow);
+    filter.add_rule(Action::Allow, "read");
+    filter.add_rule(Action::Errno(libc::EPERM), "mkdir");
+    let check = filter.check();
+    assert!(check.is_ok());
+    assert_eq!(check.to_string(), "redundant rule: read(..) -> Allow");
+}
+
+#[test]
+fn test_check_shadowed_rules() {
+    let mut filter = Filter::new(Action::KillProcess);
+    filter.add_rule(Action::Allow, "socket");
+    filter.add_rule(Action::Errno(libc::EPERM), "socket");
+    filter.add_rule_conditional(Action::Log, "socket", &[scmp_argcmp!(arg0 == 16)]);
+    let check = filter.check();
+    assert!(check.is_ok());
+    assert_eq!(
+        check.to_string(),
+        [
+            "shadowed rule: socket(..) -> Allow",
+            "shadowed rule: socket($0 == 16, ..) -> Log",
+        ]
+        .join("\n")
+    );
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_check_dropped_argcmps() {
+    let mut filter = Filter::new(Action::Allow);
+    filter.add_arch(Arch::X86);
+    filter.add_rule_conditional(
+        Action::Errno(libc::EPERM),
+        "semop",
+        &[scmp_argcmp!(arg0 == 0)],
+    );
+    let check = filter.check();
+    assert!(!check.is_ok());
+    assert_eq!(
+        check.to_string(),
+        "dropped argcmps: semop($0 == 0, ..) -> Errno(1) (x86)"
+    );
+}
@@ -0,0 +1,67 @@
+use hakoniwa::{Container, seccomp::*};
+
+#[test]
+fn test_compile() {
+    let mut filter = Filter::new(Action::Errno(libc::EPERM));
+    filter.add_rule(Action::Allow, "read");
+    filter.add_rule(Action::Allow, "write");
+    let compiled = filter.compile().unwrap();
+    assert!(!compiled.is_empty());
+}
+
+#[test]
+fn test_compile_redundant_rules() {
+    let mut filter = Filter::new(Action::Allow);
+    let empty = filter.compile().unwrap();
+    filter.add_rule(Action::Allow, "read");
+    filter.add_rule(Action::Errno(libc::EPERM), "unknown_syscall");
+    let compiled = filter.compile().unwrap();
+    assert_eq!(compiled.len(), empty.len());
+}
+
+#[test]
+fn test_compile_cached() {
+    let mut filter = Filter::new(Action::Allow);
+    filter.add_rule(Action::Errno(libc::EPERM), "mkdir");
+    filter.add_rule(Action::Errno(libc::EPERM), "mkdirat");
+
+    let mut container = Container::new();
+    container.rootfs("/").unwrap().seccomp_filter(filter);
+    for _ in 0..3 {
+        let output = container
+            .command("/bin/mkdir")
+            .arg("/mkdir-denied")
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Operation not permitted"));
+    }
+}
+
+#[test]
+fn test_compile_overlay() {
+    let mut filter = Filter::new(Action::KillProcess);
+    filter.add_rule(Action::Allow, "socket");
+    let compiled = filter.compile().unwrap();
+    filter.add_socket_family_rule(Action::Errno(libc::EAFNOSUPPORT), libc::AF_NETLINK);
+    let overlay = filter.compile().unwrap();
+    assert!(overlay.len() > compiled.len());
+}
+
+#[test]
+fn test_compile_overlay_shadowed() {
+    let mut filter = Filter::new(Action::KillProcess);
+    filter.add_rule(Action::Errno(libc::EPERM), "socket");
+    let compiled = filter.compile().unwrap();
+    filter.add_socket_family_rule(Action::Log, libc::AF_NETLINK);
+    let shadowed = filter.compile().unwrap();
+    assert_eq!(shadowed.len(), compiled.len());
+}
+
+#[test]
+fn test_compile_overlay_notify() {
+    let mut filter = Filter::new(Action::Notify);
+    filter.add_rule(Action::Allow, "socket");
+    filter.add_socket_family_rule(Action::Notify, libc::AF_NETLINK);
+    assert!(filter.compile().is_err());
+}
@@ -0,0 +1,83 @@
+use hakoniwa::seccomp::*;
+
+#[test]
+fn test_syscalls() {
+    let syscalls = SyscallGroup::Mount.syscalls();
+    assert!(syscalls.contains(&"mount"));
+    assert!(syscalls.contains(&"umount2"));
+    assert!(syscalls.contains(&"pivot_root"));
+    assert!(!syscalls.contains(&"read"));
+}
+
+#[test]
+fn test_syscalls_nested() {
+    let syscalls = SyscallGroup::Privileged.syscalls();
+    assert!(syscalls.contains(&"chown")); // @chown
+    assert!(syscalls.contains(&"settimeofday")); // @clock
+    assert!(syscalls.contains(&"init_module")); // @module
+    assert!(syscalls.contains(&"iopl")); // @raw-io
+    assert!(syscalls.contains(&"reboot")); // @reboot
+    assert!(syscalls.contains(&"swapon")); // @swap
+    assert!(syscalls.contains(&"sethostname"));
+
+    let syscalls = SyscallGroup::SystemService.syscalls();
+    assert!(syscalls.contains(&"execve")); // @default
+    assert!(syscalls.contains(&"socket")); // @network-io
+    assert!(!syscalls.contains(&"mount"));
+    assert!(!syscalls.contains(&"ptrace"));
+}
+
+#[test]
+fn test_syscalls_dedup() {
+    let syscalls = SyscallGroup::SystemService.syscalls();
+    let count = syscalls.iter().filter(|&&s| s == "close").count();
+    assert_eq!(count, 1);
+    assert!(!syscalls.iter().any(|s| s.starts_with('@')));
+}
+
+#[test]
+fn test_from_str() {
+    assert_eq!(
+        "@system-service".parse::<SyscallGroup>().unwrap(),
+        SyscallGroup::SystemService
+    );
+    assert_eq!(
+        "@raw-io".parse::<SyscallGroup>().unwrap(),
+        SyscallGroup::RawIo
+    );
+    assert_eq!(SyscallGroup::NetworkIo.to_string(), "@network-io");
+
+    let err = "@unknown".parse::<SyscallGroup>().unwrap_err();
+    assert_eq!(err.to_string(), r#"unknown syscall group "@unknown""#);
+}
+
+#[test]
+fn test_add_group_rule() {
+    let mut filter = Filter::new(Action::Allow);
+    filter.add_group_rule(Action::Errno(libc::EPERM), SyscallGroup::Module);
+    let rules = filter
+        .get_rules()
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            "delete_module(..) -> Errno(1)",
+            "finit_module(..) -> Errno(1)",
+            "init_module(..) -> Errno(1)",
+        ]
+    );
+}
+
+#[test]
+fn test_add_socket_family_rule() {
+    let mut filter = Filter::new(Action::Allow);
+    filter.add_socket_family_rule(Action::Errno(libc::EAFNOSUPPORT), libc::AF_NETLINK);
+    let rules = filter
+        .get_rules()
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(rules, ["socket($0 == 16, ..) -> Errno(97)"]);
+}
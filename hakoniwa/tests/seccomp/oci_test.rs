@@ -0,0 +1,210 @@
+use hakoniwa::{scmp_argcmp, seccomp::*};
+use serde_json::Value;
+
+fn json(data: &str) -> Value {
+    serde_json::from_str(data).unwrap()
+}
+
+fn rules(filter: &Filter) -> Vec<String> {
+    filter.get_rules().iter().map(|r| r.to_string()).collect()
+}
+
+#[test]
+fn test_from_oci_json() {
+    let filter = Filter::from_oci_json(
+        r#"{
+            "defaultAction": "SCMP_ACT_ERRNO",
+            "defaultErrnoRet": 1,
+            "architectures": ["SCMP_ARCH_X86_64", "SCMP_ARCH_X86"],
+            "syscalls": [
+                { "names": ["read", "write"], "action": "SCMP_ACT_ALLOW" },
+                {
+                    "names": ["personality"],
+                    "action": "SCMP_ACT_ALLOW",
+                    "args": [{ "index": 0, "value": 8, "op": "SCMP_CMP_EQ" }]
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        rules(&filter),
+        [
+            "read(..) -> Allow",
+            "write(..) -> Allow",
+            "personality($0 == 8, ..) -> Allow"
+        ]
+    );
+}
+
+#[test]
+fn test_from_oci_json_invalid() {
+    let err = Filter::from_oci_json(r#"{ "defaultAction": "SCMP_ACT_UNKNOWN" }"#).unwrap_err();
+    assert_eq!(err.to_string(), r#"unknown action "SCMP_ACT_UNKNOWN""#);
+
+    let err = Filter::from_oci_json(
+        r#"{
+            "defaultAction": "SCMP_ACT_ALLOW",
+            "architectures": ["SCMP_ARCH_X86_64"],
+            "archMap": [{ "architecture": "SCMP_ARCH_X86_64" }]
+        }"#,
+    )
+    .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("use either 'architectures' or 'archMap'")
+    );
+}
+
+#[test]
+fn test_to_oci_json() {
+    let mut filter = Filter::new(Action::Errno(38));
+    filter.add_arch(Arch::X8664);
+    filter.add_rule(Action::Allow, "read");
+    filter.add_rule(Action::Allow, "write");
+    filter.add_rule_conditional(Action::Allow, "personality", &[scmp_argcmp!(arg0 == 8)]);
+    filter.add_rule(Action::Errno(1), "ptrace");
+    assert_eq!(
+        json(&filter.to_oci_json().unwrap()),
+        json(
+            r#"{
+                "defaultAction": "SCMP_ACT_ERRNO",
+                "defaultErrnoRet": 38,
+                "architectures": ["SCMP_ARCH_X86_64"],
+                "syscalls": [
+                    { "names": ["read", "write"], "action": "SCMP_ACT_ALLOW" },
+                    {
+                        "names": ["personality"],
+                        "action": "SCMP_ACT_ALLOW",
+                        "args": [{ "index": 0, "value": 8, "valueTwo": 0, "op": "SCMP_CMP_EQ" }]
+                    },
+                    { "names": ["ptrace"], "action": "SCMP_ACT_ERRNO", "errnoRet": 1 }
+                ]
+            }"#
+        )
+    );
+}
+
+#[test]
+fn test_round_trip_filter() {
+    let mut filter = Filter::new(Action::KillProcess);
+    filter.add_arch(Arch::Aarch64);
+    filter.add_rule(Action::Allow, "read");
+    filter.add_rule_conditional(
+        Action::Errno(1),
+        "socket",
+        &[scmp_argcmp!(arg0 & 0xff == 16)],
+    );
+    filter.add_rule(Action::Trace(7), "ptrace");
+
+    let data = filter.to_oci_json().unwrap();
+    let filter2 = Filter::from_oci_json(&data).unwrap();
+    assert_eq!(rules(&filter2), rules(&filter));
+    assert_eq!(filter2.to_oci_json().unwrap(), data);
+}
+
+#[test]
+fn test_round_trip_profile() {
+    let data = r#"{
+        "defaultAction": "SCMP_ACT_ERRNO",
+        "defaultErrnoRet": 38,
+        "defaultErrno": "ENOSYS",
+        "flags": ["SECCOMP_FILTER_FLAG_LOG"],
+        "archMap": [
+            { "architecture": "SCMP_ARCH_X86_64", "subArchitectures": ["SCMP_ARCH_X86"] }
+        ],
+        "syscalls": [
+            {
+                "names": ["ptrace"],
+                "action": "SCMP_ACT_ALLOW",
+                "comment": "allowed with CAP_SYS_PTRACE",
+                "includes": { "caps": ["CAP_SYS_PTRACE"], "minKernel": "4.8" }
+            },
+            {
+                "names": ["personality"],
+                "action": "SCMP_ACT_KILL",
+                "excludes": { "arches": ["s390x"] },
+                "args": [{ "index": 0, "value": 8, "op": "SCMP_CMP_NE" }]
+            }
+        ]
+    }"#;
+    let filter = Filter::from_oci_json(data).unwrap();
+    assert_eq!(json(&filter.to_oci_json().unwrap()), json(data));
+}
+
+#[test]
+fn test_round_trip_profile_layout() {
+    let data = r#"{
+        "defaultAction": "SCMP_ACT_ERRNO",
+        "architectures": ["SCMP_ARCH_X86_64", "SCMP_ARCH_X86"],
+        "syscalls": [
+            { "names": ["read"], "action": "SCMP_ACT_ALLOW" },
+            { "names": ["write"], "action": "SCMP_ACT_ALLOW" },
+            {
+                "names": ["personality"],
+                "action": "SCMP_ACT_KILL",
+                "args": [{ "index": 0, "value": 8, "op": "SCMP_CMP_EQ" }]
+            },
+            {
+                "names": ["s390_runtime_instr"],
+                "action": "SCMP_ACT_ALLOW",
+                "includes": { "arches": ["s390x"] }
+            },
+            {
+                "names": ["mount", "umount2"],
+                "action": "SCMP_ACT_ALLOW",
+                "includes": { "caps": ["CAP_SYS_ADMIN"] }
+            }
+        ]
+    }"#;
+    let mut filter = Filter::from_oci_json(data).unwrap();
+    assert_eq!(json(&filter.to_oci_json().unwrap()), json(data));
+    #[cfg(target_arch = "x86_64")]
+    assert!(!rules(&filter).contains(&"s390_runtime_instr(..) -> Allow".to_string()));
+
+    filter.add_arch(Arch::X32);
+    filter.add_rule(Action::Allow, "close");
+    assert_eq!(
+        json(&filter.to_oci_json().unwrap()),
+        json(
+            &data
+                .replace(
+                    r#"["SCMP_ARCH_X86_64", "SCMP_ARCH_X86"]"#,
+                    r#"["SCMP_ARCH_X86_64", "SCMP_ARCH_X86", "SCMP_ARCH_X32"]"#
+                )
+                .replace(
+                    r#""includes": { "caps": ["CAP_SYS_ADMIN"] }
+            }"#,
+                    r#""includes": { "caps": ["CAP_SYS_ADMIN"] }
+            },
+            { "names": ["close"], "action": "SCMP_ACT_ALLOW" }"#
+                )
+        )
+    );
+}
+
+#[test]
+fn test_round_trip_presets() {
+    let data = include_str!("../../src/seccomp/presets/podman.json");
+    assert_eq!(json(&presets::podman().to_oci_json().unwrap()), json(data));
+
+    let data = include_str!("../../src/seccomp/presets/audit.json");
+    assert_eq!(json(&presets::audit().to_oci_json().unwrap()), json(data));
+}
+
+#[test]
+fn test_add_rule_after_from_oci_json() {
+    let mut filter = presets::audit();
+    filter.add_rule(Action::Errno(1), "ptrace");
+    assert_eq!(
+        json(&filter.to_oci_json().unwrap()),
+        json(
+            r#"{
+                "defaultAction": "SCMP_ACT_LOG",
+                "syscalls": [
+                    { "names": ["ptrace"], "action": "SCMP_ACT_ERRNO", "errnoRet": 1 }
+                ]
+            }"#
+        )
+    );
+}
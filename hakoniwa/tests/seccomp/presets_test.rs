@@ -1,4 +1,4 @@
-use super::*;
+use hakoniwa::seccomp::*;
 
 fn contains_rule(rules: &[Rule], action: Action, sysname: &str) -> bool {
     rules
@@ -7,15 +7,15 @@ fn contains_rule(rules: &[Rule], action: Action, sysname: &str) -> bool {
 }
 
 #[test]
-fn test_load_audit() {
-    let filter = load("audit").unwrap();
+fn test_audit() {
+    let filter = presets::audit();
     let rules = filter.get_rules();
     assert!(rules.is_empty());
 }
 
 #[test]
-fn test_load_seccomp() {
-    let filter = load("podman").unwrap();
+fn test_podman() {
+    let filter = presets::podman();
     let rules = filter.get_rules();
 
     assert!(contains_rule(&rules, Action::Allow, "accept"));
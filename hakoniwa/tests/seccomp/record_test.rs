@@ -0,0 +1,63 @@
+use hakoniwa::RecordedSyscall;
+use hakoniwa::seccomp::*;
+
+fn recorded_syscall(sysname: &str, arch: &str, args: &[(u32, u64)]) -> RecordedSyscall {
+    RecordedSyscall {
+        syscall: 0,
+        sysname: sysname.to_string(),
+        arch: arch.to_string(),
+        args: args.to_vec(),
+    }
+}
+
+#[test]
+fn test_from_recorded_syscalls() {
+    let syscalls = [
+        recorded_syscall("write", "x86_64", &[]),
+        recorded_syscall("read", "x86_64", &[]),
+        recorded_syscall("socket", "x86_64", &[(0, 10), (0, 2)]),
+        recorded_syscall("read", "x86", &[]),
+    ];
+    let filter = Filter::from_recorded_syscalls(Action::Errno(libc::EPERM), &syscalls).unwrap();
+    let rules = filter
+        .get_rules()
+        .iter()
+        .map(|rule| rule.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rules,
+        [
+            "read(..) -> Allow",
+            "write(..) -> Allow",
+            "socket($0 == 2, ..) -> Allow",
+            "socket($0 == 10, ..) -> Allow",
+        ]
+    );
+}
+
+#[test]
+fn test_from_recorded_syscalls_to_oci_json() {
+    let syscalls = [
+        recorded_syscall("read", "x86", &[]),
+        recorded_syscall("read", "x86_64", &[]),
+        recorded_syscall("write", "x86_64", &[]),
+    ];
+    let filter = Filter::from_recorded_syscalls(Action::Errno(libc::EPERM), &syscalls).unwrap();
+    let profile: serde_json::Value = serde_json::from_str(&filter.to_oci_json().unwrap()).unwrap();
+    assert_eq!(profile["defaultAction"], "SCMP_ACT_ERRNO");
+    assert_eq!(
+        profile["architectures"],
+        serde_json::json!(["SCMP_ARCH_X86", "SCMP_ARCH_X86_64"])
+    );
+    assert_eq!(
+        profile["syscalls"],
+        serde_json::json!([{ "names": ["read", "write"], "action": "SCMP_ACT_ALLOW" }])
+    );
+}
+
+#[test]
+fn test_from_recorded_syscalls_unsupported_arch() {
+    let syscalls = [recorded_syscall("read", "vax", &[])];
+    let err = Filter::from_recorded_syscalls(Action::Errno(libc::EPERM), &syscalls).unwrap_err();
+    assert_eq!(err.to_string(), "unsupported architectures vax");
+}
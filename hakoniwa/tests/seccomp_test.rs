@@ -1,4 +1,11 @@
 #[cfg(feature = "seccomp")]
 mod seccomp {
+    mod arch_test;
     mod argcmp_test;
+    mod check_test;
+    mod compiled_filter_test;
+    mod group_test;
+    mod oci_test;
+    mod presets_test;
+    mod record_test;
 }